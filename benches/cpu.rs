@@ -0,0 +1,168 @@
+// Interpreter throughput on fixed workloads. Run with `cargo bench --bench cpu`.
+// Every workload is deterministic, so each one is measured twice: once reported as
+// instructions/second and once as emulated cycles/second.
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use nes_emulator::cpu::{StepOutcome, CPU};
+use nes_emulator::snake;
+
+// What a workload got through, used as the throughput of its benchmark
+struct Counts {
+    instructions: u64,
+    cycles: u64,
+}
+
+// Runs until BRK, counting every instruction executed
+fn run_to_brk(cpu: &mut CPU) -> Counts {
+    let start: u64 = cpu.cycles();
+    let mut instructions: u64 = 0;
+    let outcome: StepOutcome = cpu
+        .run_until(|_| {
+            instructions += 1;
+            false
+        })
+        .unwrap();
+    assert_eq!(outcome, StepOutcome::Halted);
+    Counts {
+        instructions,
+        cycles: cpu.cycles() - start,
+    }
+}
+
+fn load(program: &[u8], addr: u16) -> CPU {
+    let mut cpu: CPU = CPU::new();
+    cpu.load_at(program.to_vec(), addr).unwrap();
+    cpu.mem_write_u16(0xFFFC, addr);
+    cpu.reset();
+    cpu.stack_ptr = 0xfd;
+    cpu
+}
+
+// Adds 7 to a 16-bit counter 64 * 256 times
+const ARITHMETIC_LOOP: [u8; 24] = [
+    0xa0, 0x40, //       LDY #$40
+    0xa2, 0x00, // outer LDX #$00
+    0x18, //       inner CLC
+    0xa5, 0x00, //       LDA $00
+    0x69, 0x07, //       ADC #$07
+    0x85, 0x00, //       STA $00
+    0xa5, 0x01, //       LDA $01
+    0x69, 0x00, //       ADC #$00
+    0x85, 0x01, //       STA $01
+    0xca, //             DEX
+    0xd0, 0xf0, //       BNE inner
+    0x88, //             DEY
+    0xd0, 0xeb, //       BNE outer
+    0x00, //             BRK
+];
+
+fn arithmetic_loop() -> Counts {
+    let mut cpu: CPU = load(&ARITHMETIC_LOOP, 0x0600);
+    run_to_brk(&mut cpu)
+}
+
+// 32 passes over a 256-byte buffer at $0200 through a zero page pointer, calling a subroutine
+// per byte that mixes indexed, read-modify-write, stack, bit test and branch instructions
+fn instruction_mix_program() -> Vec<u8> {
+    let mut program: Vec<u8> = vec![
+        0xa9, 0x00, //       LDA #$00
+        0x85, 0x10, //       STA $10
+        0xa9, 0x02, //       LDA #$02
+        0x85, 0x11, //       STA $11
+        0xa9, 0x20, //       LDA #$20
+        0x85, 0x12, //       STA $12
+        0xa0, 0x00, // pass  LDY #$00
+        0xb1, 0x10, // fill  LDA ($10),Y
+        0x0a, //             ASL A
+        0x69, 0x3b, //       ADC #$3B
+        0x45, 0x12, //       EOR $12
+        0x91, 0x10, //       STA ($10),Y
+        0x48, //             PHA
+        0x20, 0x40, 0x06, // JSR mix
+        0x68, //             PLA
+        0xc8, //             INY
+        0xd0, 0xef, //       BNE fill
+        0xc6, 0x12, //       DEC $12
+        0xd0, 0xe9, //       BNE pass
+        0x00, //             BRK
+    ];
+    program.resize(0x40, 0x00);
+    program.extend_from_slice(&[
+        0xbe, 0x00, 0x02, // mix  LDX $0200,Y
+        0xe8, //                  INX
+        0x8a, //                  TXA
+        0x4a, //                  LSR A
+        0x66, 0x13, //            ROR $13
+        0x24, 0x13, //            BIT $13
+        0x30, 0x03, //            BMI skip
+        0xee, 0x00, 0x03, //      INC $0300
+        0x9d, 0x00, 0x03, // skip STA $0300,X
+        0xdd, 0x00, 0x03, //      CMP $0300,X
+        0xf0, 0x01, //            BEQ done
+        0xea, //                  NOP
+        0x60, //             done RTS
+    ]);
+    program
+}
+
+fn instruction_mix(program: &[u8]) -> Counts {
+    let mut cpu: CPU = load(program, 0x0600);
+    run_to_brk(&mut cpu)
+}
+
+// The snake game for a fixed number of cycles, steering in a square. A new game starts
+// whenever the snake dies.
+const SNAKE_CYCLES: u64 = 2_000_000;
+const SNAKE_KEYS: [u8; 4] = [0x64, 0x73, 0x61, 0x77]; // D, S, A, W
+
+fn snake_game() -> Counts {
+    let mut cpu: CPU = load(&snake::GAME_CODE, snake::LOAD_ADDRESS);
+    let start: u64 = cpu.cycles();
+    let mut instructions: u64 = 0;
+    let mut random: u8 = 1;
+    let mut chunk: usize = 0;
+    while cpu.cycles() - start < SNAKE_CYCLES {
+        // Fresh input every 20k cycles, roughly once per game tick
+        random = random.wrapping_mul(5).wrapping_add(3);
+        cpu.mem_write(0xfe, random % 15 + 1);
+        cpu.mem_write(0xff, SNAKE_KEYS[chunk / 4 % SNAKE_KEYS.len()]);
+        chunk += 1;
+        let target: u64 = cpu.cycles() + 20_000;
+        let outcome: StepOutcome = cpu
+            .run_until(|cpu| {
+                let done: bool = cpu.cycles() >= target;
+                if !done {
+                    instructions += 1;
+                }
+                done
+            })
+            .unwrap();
+        if outcome == StepOutcome::Halted {
+            cpu.reset();
+        }
+    }
+    Counts {
+        instructions,
+        cycles: cpu.cycles() - start,
+    }
+}
+
+fn bench_workload(c: &mut Criterion, name: &str, workload: &dyn Fn() -> Counts) {
+    let counts: Counts = workload();
+    let mut group = c.benchmark_group(name);
+    group.sample_size(20);
+    for (unit, count) in [("instructions", counts.instructions), ("cycles", counts.cycles)] {
+        group.throughput(Throughput::Elements(count));
+        group.bench_function(unit, |b| b.iter(|| black_box(workload())));
+    }
+    group.finish();
+}
+
+fn cpu_benchmarks(c: &mut Criterion) {
+    let mix: Vec<u8> = instruction_mix_program();
+    bench_workload(c, "arithmetic_loop", &arithmetic_loop);
+    bench_workload(c, "instruction_mix", &|| instruction_mix(&mix));
+    bench_workload(c, "snake", &snake_game);
+}
+
+criterion_group!(benches, cpu_benchmarks);
+criterion_main!(benches);
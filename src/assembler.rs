@@ -0,0 +1,769 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::cpu::AddressingMode;
+use crate::cpu::Variant;
+use crate::opcodes::OpCode;
+
+// A small two pass 6502 assembler, so tests and demos can be written as source instead of hex.
+// It assembles with the same opcode tables the CPU decodes with, so anything in them works,
+// undocumented opcodes and the 65C02 additions included.
+//
+//     ; comments run to the end of the line
+//     SCREEN = $0200          ; constants
+//             .org $0600      ; where the next byte goes (default $8000, where CPU::load puts it)
+//     start:  LDX #<SCREEN    ; #imm  zp  zp,X  zp,Y  abs  abs,X  abs,Y  (zp,X)  (zp),Y  (abs)  A
+//     loop:   STA SCREEN,X
+//             DEX
+//             BNE loop        ; branch targets are addresses, the offset is worked out here
+//             JMP *           ; * is the address of the current instruction
+//             .byte 1, $02, %11, 'A', "text"
+//             .word start, table+2
+//
+// Expressions are numbers ($hex, %binary, decimal, 'c'), symbols and *, combined with
+// + - * / & | ^ << >> and parentheses, and the unary -, ~, < (low byte) and > (high byte).
+// An operand wrapped in parentheses as a whole is taken as indirect.
+// A value known to fit in the zero page when it's first seen gets the zero page form.
+pub const DEFAULT_ORIGIN: u16 = 0x8000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    // Address of bytes[0]
+    pub origin: u16,
+    // Everything from the first byte emitted to the last, with gaps left by .org zero filled
+    pub bytes: Vec<u8>,
+    pub labels: BTreeMap<String, u16>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize, // Counting from 1
+    pub kind: AsmErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    // Anything that doesn't parse
+    Syntax(String),
+    // Mnemonic isn't in the variant's instruction table
+    UnknownInstruction(String),
+    // The instruction exists, but not with this kind of operand
+    InvalidAddressingMode(String),
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    // Operand doesn't fit in the byte or word it's encoded in
+    ValueOutOfRange(i64),
+    // Branch target further than -128..+127 bytes from the next instruction
+    BranchOutOfRange(i64),
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AsmErrorKind::Syntax(message) => write!(f, "{}", message),
+            AsmErrorKind::UnknownInstruction(name) => write!(f, "unknown instruction {}", name),
+            AsmErrorKind::InvalidAddressingMode(name) => {
+                write!(f, "{} doesn't support this addressing mode", name)
+            }
+            AsmErrorKind::UndefinedSymbol(name) => write!(f, "undefined symbol {}", name),
+            AsmErrorKind::DuplicateSymbol(name) => write!(f, "{} is already defined", name),
+            AsmErrorKind::ValueOutOfRange(value) => write!(f, "value {} is out of range", value),
+            AsmErrorKind::BranchOutOfRange(offset) => {
+                write!(f, "branch target is {} bytes away, more than a branch can reach", offset)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+// Assembles source for the 2A03 and returns the bytes, panicking with the error on bad source.
+// Meant for tests: cpu.load_and_run(asm!("LDA #$05", "BRK"))
+#[macro_export]
+macro_rules! asm {
+    ($($line:expr),+ $(,)?) => {
+        match $crate::assembler::assemble(&[$($line),+].join("\n"), $crate::cpu::Variant::Ricoh2A03) {
+            Ok(assembly) => assembly.bytes,
+            Err(e) => panic!("{}", e),
+        }
+    };
+}
+
+pub fn assemble(source: &str, variant: Variant) -> Result<Assembly, AsmError> {
+    let statements: Vec<(usize, Statement)> = parse(source)?;
+    let mut assembler: Assembler = Assembler {
+        opcodes: variant.opcodes(),
+        symbols: BTreeMap::new(),
+        labels: BTreeMap::new(),
+        encodings: Vec::new(),
+    };
+    assembler.first_pass(&statements)?;
+    assembler.second_pass(&statements)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Operand {
+    Implied,
+    Accumulator,
+    Immediate(String),
+    Direct(String), // Zero page, absolute, or a branch/jump target
+    IndexedX(String),
+    IndexedY(String),
+    IndirectX(String),
+    IndirectY(String),
+    Indirect(String),
+    ZeroPageAndTarget(String, String), // BBRn/BBSn
+}
+
+enum Statement {
+    Label(String),
+    Constant(String, String),
+    Org(String),
+    Bytes(Vec<String>),
+    Words(Vec<String>),
+    Instruction(String, Operand),
+}
+
+struct Assembler {
+    opcodes: &'static [OpCode; 256],
+    symbols: BTreeMap<String, i64>,
+    labels: BTreeMap<String, u16>,
+    // The opcode picked for each instruction in the first pass, so both passes agree on sizes
+    encodings: Vec<&'static OpCode>,
+}
+
+impl Assembler {
+    // Defines every symbol and picks the encoding, and so the size, of every instruction
+    fn first_pass(&mut self, statements: &[(usize, Statement)]) -> Result<(), AsmError> {
+        let mut pc: u16 = DEFAULT_ORIGIN;
+        for (line, statement) in statements {
+            let error = |kind: AsmErrorKind| AsmError { line: *line, kind };
+            match statement {
+                Statement::Label(name) => {
+                    self.define(name, pc as i64).map_err(error)?;
+                    self.labels.insert(name.clone(), pc);
+                }
+                Statement::Constant(name, expr) => {
+                    let value: i64 = evaluate(expr, &self.symbols, pc).map_err(error)?;
+                    self.define(name, value).map_err(error)?;
+                }
+                Statement::Org(expr) => {
+                    pc = to_word(evaluate(expr, &self.symbols, pc).map_err(error)?).map_err(error)?;
+                }
+                Statement::Bytes(items) => {
+                    let len: usize = items.iter().map(|item| string_literal(item).map_or(1, |s| s.len())).sum();
+                    pc = pc.wrapping_add(len as u16);
+                }
+                Statement::Words(items) => pc = pc.wrapping_add(2 * items.len() as u16),
+                Statement::Instruction(mnemonic, operand) => {
+                    let opcode: &'static OpCode = self.encode(mnemonic, operand, pc).map_err(error)?;
+                    self.encodings.push(opcode);
+                    pc = pc.wrapping_add(opcode.num_bytes as u16);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Emits the bytes, now that every symbol has a value
+    fn second_pass(self, statements: &[(usize, Statement)]) -> Result<Assembly, AsmError> {
+        let mut pc: u16 = DEFAULT_ORIGIN;
+        let mut origin: Option<u16> = None;
+        let mut bytes: Vec<u8> = Vec::new();
+        let mut encodings = self.encodings.iter();
+        for (line, statement) in statements {
+            let error = |kind: AsmErrorKind| AsmError { line: *line, kind };
+            let eval = |expr: &str| evaluate(expr, &self.symbols, pc).map_err(error);
+            let mut emitted: Vec<u8> = Vec::new();
+            match statement {
+                Statement::Label(_) | Statement::Constant(_, _) => {}
+                Statement::Org(expr) => {
+                    pc = to_word(eval(expr)?).map_err(error)?;
+                    continue;
+                }
+                Statement::Bytes(items) => {
+                    for item in items {
+                        match string_literal(item) {
+                            Some(text) => emitted.extend(text.bytes()),
+                            None => emitted.push(to_byte(eval(item)?).map_err(error)?),
+                        }
+                    }
+                }
+                Statement::Words(items) => {
+                    for item in items {
+                        emitted.extend(to_word(eval(item)?).map_err(error)?.to_le_bytes());
+                    }
+                }
+                Statement::Instruction(_, operand) => {
+                    let opcode: &OpCode = encodings.next().unwrap();
+                    emitted.push(opcode.instruction);
+                    emitted.extend(self.operand_bytes(opcode, operand, pc).map_err(error)?);
+                }
+            }
+            if emitted.is_empty() {
+                continue;
+            }
+            let start: u16 = *origin.get_or_insert(pc);
+            if pc < start {
+                return Err(error(AsmErrorKind::Syntax(format!(
+                    "${:04X} is below the start of the program at ${:04X}",
+                    pc, start
+                ))));
+            }
+            let offset: usize = (pc - start) as usize;
+            if bytes.len() < offset + emitted.len() {
+                bytes.resize(offset + emitted.len(), 0);
+            }
+            bytes[offset..offset + emitted.len()].copy_from_slice(&emitted);
+            pc = pc.wrapping_add(emitted.len() as u16);
+        }
+        Ok(Assembly {
+            origin: origin.unwrap_or(pc),
+            bytes,
+            labels: self.labels,
+        })
+    }
+
+    fn define(&mut self, name: &str, value: i64) -> Result<(), AsmErrorKind> {
+        if self.symbols.insert(name.to_string(), value).is_some() {
+            return Err(AsmErrorKind::DuplicateSymbol(name.to_string()));
+        }
+        Ok(())
+    }
+
+    // The official opcode for name that matches, falling back to an undocumented one
+    // (SBC #imm and the NOPs have undocumented duplicates)
+    fn find(&self, name: &str, matches: impl Fn(&OpCode) -> bool) -> Option<&'static OpCode> {
+        let mut candidates = self.opcodes.iter().filter(|op| op.name == name && matches(op));
+        let first: &'static OpCode = candidates.next()?;
+        if !first.unofficial {
+            return Some(first);
+        }
+        Some(candidates.find(|op| !op.unofficial).unwrap_or(first))
+    }
+
+    fn encode(&self, mnemonic: &str, operand: &Operand, pc: u16) -> Result<&'static OpCode, AsmErrorKind> {
+        if self.find(mnemonic, |_| true).is_none() {
+            return Err(AsmErrorKind::UnknownInstruction(mnemonic.to_string()));
+        }
+        // Only values already known in the first pass can pick the zero page forms
+        let fits_zero_page = |expr: &str| -> bool {
+            matches!(evaluate(expr, &self.symbols, pc), Ok(value) if (0..=0xFF).contains(&value))
+        };
+        let by_mode = |mode: AddressingMode| self.find(mnemonic, |op| op.mode == mode);
+        let implied = |num_bytes: u8| {
+            self.find(mnemonic, |op| op.mode == AddressingMode::NoneAddressing && op.num_bytes == num_bytes)
+        };
+        let by_opcode = |instruction: u8| self.find(mnemonic, |op| op.instruction == instruction);
+        let zero_page_or = |zero_page: AddressingMode, absolute: AddressingMode, expr: &str| {
+            if fits_zero_page(expr) {
+                by_mode(zero_page).or_else(|| by_mode(absolute))
+            } else {
+                by_mode(absolute)
+            }
+        };
+
+        let opcode: Option<&'static OpCode> = match operand {
+            Operand::Implied | Operand::Accumulator => implied(1),
+            Operand::Immediate(_) => by_mode(AddressingMode::Immediate),
+            // Branches take a target address like everything else, and so do JSR and JMP
+            Operand::Direct(expr) => implied(2)
+                .or_else(|| by_opcode(0x20))
+                .or_else(|| by_opcode(0x4c))
+                .or_else(|| zero_page_or(AddressingMode::ZeroPage, AddressingMode::Absolute, expr)),
+            Operand::IndexedX(expr) => zero_page_or(AddressingMode::ZeroPage_X, AddressingMode::Absolute_X, expr),
+            Operand::IndexedY(expr) => zero_page_or(AddressingMode::ZeroPage_Y, AddressingMode::Absolute_Y, expr),
+            Operand::IndirectX(_) => by_opcode(0x7c).or_else(|| by_mode(AddressingMode::Indirect_X)),
+            Operand::IndirectY(_) => by_mode(AddressingMode::Indirect_Y),
+            Operand::Indirect(_) => by_opcode(0x6c).or_else(|| by_mode(AddressingMode::ZeroPage_Indirect)),
+            Operand::ZeroPageAndTarget(_, _) if mnemonic.starts_with("BB") => implied(3),
+            Operand::ZeroPageAndTarget(_, _) => None,
+        };
+        opcode.ok_or_else(|| AsmErrorKind::InvalidAddressingMode(mnemonic.to_string()))
+    }
+
+    fn operand_bytes(&self, opcode: &OpCode, operand: &Operand, pc: u16) -> Result<Vec<u8>, AsmErrorKind> {
+        let eval = |expr: &str| evaluate(expr, &self.symbols, pc);
+        let branch = |expr: &str, num_bytes: u16| -> Result<u8, AsmErrorKind> {
+            let offset: i64 = eval(expr)? - (pc as i64 + num_bytes as i64);
+            if !(-128..=127).contains(&offset) {
+                return Err(AsmErrorKind::BranchOutOfRange(offset));
+            }
+            Ok(offset as u8)
+        };
+        match operand {
+            Operand::Implied | Operand::Accumulator => Ok(vec![]),
+            Operand::ZeroPageAndTarget(zero_page, target) => Ok(vec![to_byte(eval(zero_page)?)?, branch(target, 3)?]),
+            Operand::Immediate(expr)
+            | Operand::Direct(expr)
+            | Operand::IndexedX(expr)
+            | Operand::IndexedY(expr)
+            | Operand::IndirectX(expr)
+            | Operand::IndirectY(expr)
+            | Operand::Indirect(expr) => match (opcode.mode, opcode.num_bytes) {
+                (AddressingMode::NoneAddressing, 2) => Ok(vec![branch(expr, 2)?]),
+                (_, 2) => Ok(vec![to_byte(eval(expr)?)?]),
+                _ => Ok(to_word(eval(expr)?)?.to_le_bytes().to_vec()),
+            },
+        }
+    }
+}
+
+// Bytes can be written signed or unsigned
+fn to_byte(value: i64) -> Result<u8, AsmErrorKind> {
+    if !(-0x80..=0xFF).contains(&value) {
+        return Err(AsmErrorKind::ValueOutOfRange(value));
+    }
+    Ok(value as u8)
+}
+
+fn to_word(value: i64) -> Result<u16, AsmErrorKind> {
+    if !(-0x8000..=0xFFFF).contains(&value) {
+        return Err(AsmErrorKind::ValueOutOfRange(value));
+    }
+    Ok(value as u16)
+}
+
+fn string_literal(item: &str) -> Option<&str> {
+    item.strip_prefix('"')?.strip_suffix('"')
+}
+
+fn is_symbol(text: &str) -> bool {
+    let mut chars = text.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn parse(source: &str) -> Result<Vec<(usize, Statement)>, AsmError> {
+    let mut statements: Vec<(usize, Statement)> = Vec::new();
+    for (i, raw) in source.lines().enumerate() {
+        let line: usize = i + 1;
+        let syntax = |message: String| AsmError {
+            line,
+            kind: AsmErrorKind::Syntax(message),
+        };
+        let mut text: &str = strip_comment(raw).trim();
+
+        // Any number of labels can come before the statement
+        while let Some((label, rest)) = text.split_once(':') {
+            if !is_symbol(label.trim()) {
+                break;
+            }
+            statements.push((line, Statement::Label(label.trim().to_string())));
+            text = rest.trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+        if let Some((name, expr)) = text.split_once('=').filter(|(name, _)| is_symbol(name.trim())) {
+            statements.push((line, Statement::Constant(name.trim().to_string(), expr.trim().to_string())));
+            continue;
+        }
+
+        let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest: &str = rest.trim();
+        let args: Vec<String> = split_commas(rest).iter().map(|arg| arg.to_string()).collect();
+        let statement: Statement = match word.to_ascii_lowercase().as_str() {
+            ".org" if args.len() == 1 && !rest.is_empty() => Statement::Org(args[0].clone()),
+            ".byte" | ".db" if !rest.is_empty() => Statement::Bytes(args),
+            ".word" | ".dw" if !rest.is_empty() => Statement::Words(args),
+            directive if directive.starts_with('.') => {
+                return Err(syntax(format!("bad directive {}", text)));
+            }
+            _ => Statement::Instruction(word.to_ascii_uppercase(), parse_operand(rest).map_err(syntax)?),
+        };
+        statements.push((line, statement));
+    }
+    Ok(statements)
+}
+
+// Everything from a ';' that isn't inside quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote: Option<char> = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, ';') => return &line[..i],
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if q == c => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+// Splits at the commas that aren't inside parentheses or quotes
+fn split_commas(text: &str) -> Vec<&str> {
+    let mut parts: Vec<&str> = Vec::new();
+    let mut depth: i32 = 0;
+    let mut quote: Option<char> = None;
+    let mut start: usize = 0;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(q), _) if q == c => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts
+}
+
+// The inside of text if the parentheses it starts with close at its very end
+fn parenthesized(text: &str) -> Option<&str> {
+    let inner: &str = text.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth: i32 = 0;
+    for c in inner.chars() {
+        depth += match c {
+            '(' => 1,
+            ')' => -1,
+            _ => 0,
+        };
+        if depth < 0 {
+            return None;
+        }
+    }
+    Some(inner)
+}
+
+fn parse_operand(text: &str) -> Result<Operand, String> {
+    if text.is_empty() {
+        return Ok(Operand::Implied);
+    }
+    if text.eq_ignore_ascii_case("A") {
+        return Ok(Operand::Accumulator);
+    }
+    if let Some(expr) = text.strip_prefix('#') {
+        return Ok(Operand::Immediate(expr.trim().to_string()));
+    }
+    let parts: Vec<&str> = split_commas(text);
+    let register = |part: &str| part.to_ascii_uppercase();
+    match parts.as_slice() {
+        [operand] => match parenthesized(operand) {
+            Some(inner) => match split_commas(inner).as_slice() {
+                [expr, x] if register(x) == "X" => Ok(Operand::IndirectX(expr.to_string())),
+                [expr] => Ok(Operand::Indirect(expr.to_string())),
+                _ => Err(format!("bad operand {}", text)),
+            },
+            None => Ok(Operand::Direct(operand.to_string())),
+        },
+        [operand, y] if register(y) == "Y" => match parenthesized(operand) {
+            Some(inner) => Ok(Operand::IndirectY(inner.trim().to_string())),
+            None => Ok(Operand::IndexedY(operand.to_string())),
+        },
+        [operand, x] if register(x) == "X" => Ok(Operand::IndexedX(operand.to_string())),
+        [zero_page, target] => Ok(Operand::ZeroPageAndTarget(zero_page.to_string(), target.to_string())),
+        _ => Err(format!("bad operand {}", text)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 14] = ["<<", ">>", "+", "-", "*", "/", "&", "|", "^", "~", "<", ">", "(", ")"];
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, AsmErrorKind> {
+    let syntax = || AsmErrorKind::Syntax(format!("bad expression {}", expr));
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut rest: &str = expr.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            (Token::Op(op), op.len())
+        } else if c == '\'' {
+            let mut chars = rest[1..].chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), Some('\'')) => (Token::Number(ch as i64), 2 + ch.len_utf8()),
+                _ => return Err(syntax()),
+            }
+        } else {
+            let (radix, digits) = match c {
+                '$' => (16, &rest[1..]),
+                '%' => (2, &rest[1..]),
+                _ => (10, rest),
+            };
+            let len: usize = digits
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(digits.len());
+            let word: &str = &digits[..len];
+            let prefix: usize = rest.len() - digits.len();
+            if radix == 10 && is_symbol(word) {
+                (Token::Symbol(word.to_string()), len)
+            } else {
+                let value: i64 = i64::from_str_radix(word, radix).map_err(|_| syntax())?;
+                (Token::Number(value), prefix + len)
+            }
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+fn evaluate(expr: &str, symbols: &BTreeMap<String, i64>, pc: u16) -> Result<i64, AsmErrorKind> {
+    let tokens: Vec<Token> = tokenize(expr)?;
+    let mut parser: ExprParser = ExprParser {
+        tokens: &tokens,
+        pos: 0,
+        symbols,
+        pc,
+    };
+    let value: i64 = parser.binary(0)?;
+    if parser.pos != tokens.len() {
+        return Err(AsmErrorKind::Syntax(format!("bad expression {}", expr)));
+    }
+    Ok(value)
+}
+
+// Binary operators from the loosest binding to the tightest
+const PRECEDENCE: [&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/"]];
+
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    symbols: &'a BTreeMap<String, i64>,
+    pc: u16,
+}
+
+impl ExprParser<'_> {
+    fn next(&mut self) -> Option<&Token> {
+        let token: Option<&Token> = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+    fn peek_op(&self) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<i64, AsmErrorKind> {
+        if level == PRECEDENCE.len() {
+            return self.unary();
+        }
+        let mut value: i64 = self.binary(level + 1)?;
+        while let Some(op) = self.peek_op().filter(|op| PRECEDENCE[level].contains(op)) {
+            self.pos += 1;
+            let rhs: i64 = self.binary(level + 1)?;
+            value = match op {
+                "|" => value | rhs,
+                "^" => value ^ rhs,
+                "&" => value & rhs,
+                "<<" => value.checked_shl(rhs as u32).unwrap_or(0),
+                ">>" => value.checked_shr(rhs as u32).unwrap_or(0),
+                "+" => value.wrapping_add(rhs),
+                "-" => value.wrapping_sub(rhs),
+                "*" => value.wrapping_mul(rhs),
+                _ => value
+                    .checked_div(rhs)
+                    .ok_or_else(|| AsmErrorKind::Syntax("division by zero".to_string()))?,
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i64, AsmErrorKind> {
+        let syntax = || AsmErrorKind::Syntax("expression ends too soon".to_string());
+        match self.next().cloned().ok_or_else(syntax)? {
+            Token::Number(value) => Ok(value),
+            Token::Symbol(name) => self
+                .symbols
+                .get(&name)
+                .copied()
+                .ok_or(AsmErrorKind::UndefinedSymbol(name)),
+            Token::Op("*") => Ok(self.pc as i64),
+            Token::Op("-") => Ok(self.unary()?.wrapping_neg()),
+            Token::Op("~") => Ok(!self.unary()?),
+            Token::Op("<") => Ok(self.unary()? & 0xFF),
+            Token::Op(">") => Ok((self.unary()? >> 8) & 0xFF),
+            Token::Op("(") => {
+                let value: i64 = self.binary(0)?;
+                match self.next() {
+                    Some(Token::Op(")")) => Ok(value),
+                    _ => Err(AsmErrorKind::Syntax("missing )".to_string())),
+                }
+            }
+            Token::Op(op) => Err(AsmErrorKind::Syntax(format!("unexpected {}", op))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::disasm;
+    use crate::snake;
+
+    fn assemble_ok(source: &str) -> Vec<u8> {
+        assemble(source, Variant::Ricoh2A03).unwrap().bytes
+    }
+    fn error_kind(source: &str) -> AsmErrorKind {
+        assemble(source, Variant::Ricoh2A03).unwrap_err().kind
+    }
+
+    #[test]
+    fn test_addressing_modes() {
+        let cases: [(&str, &[u8]); 15] = [
+            ("LDA #$10", &[0xa9, 0x10]),
+            ("LDA $10", &[0xa5, 0x10]),
+            ("LDA $10,X", &[0xb5, 0x10]),
+            ("LDX $10,Y", &[0xb6, 0x10]),
+            ("LDA $1234", &[0xad, 0x34, 0x12]),
+            ("LDA $1234,x", &[0xbd, 0x34, 0x12]),
+            ("LDA $1234,Y", &[0xb9, 0x34, 0x12]),
+            ("LDA ($10,X)", &[0xa1, 0x10]),
+            ("lda ($10),y", &[0xb1, 0x10]),
+            ("ASL A", &[0x0a]),
+            ("ASL", &[0x0a]),
+            ("JMP $1234", &[0x4c, 0x34, 0x12]),
+            ("JMP ($1234)", &[0x6c, 0x34, 0x12]),
+            ("JSR $1234", &[0x20, 0x34, 0x12]),
+            // No zero page,Y form of LDA, so it's widened
+            ("LDA $10,Y", &[0xb9, 0x10, 0x00]),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(assemble_ok(source), expected, "{}", source);
+        }
+    }
+    #[test]
+    fn test_prefers_official_opcodes() {
+        assert_eq!(assemble_ok("SBC #1\nNOP"), vec![0xe9, 0x01, 0xea]);
+        // Undocumented ones still assemble
+        assert_eq!(assemble_ok("LAX $10\nDCP $1234,X"), vec![0xa7, 0x10, 0xdf, 0x34, 0x12]);
+    }
+    #[test]
+    fn test_65c02() {
+        let assembly: Assembly = assemble(
+            "LDA ($10)\nJMP ($1234,X)\nINC A\nSTZ $10\nloop: BRA loop\nBBR0 $10,loop",
+            Variant::Wdc65C02,
+        )
+        .unwrap();
+        assert_eq!(
+            assembly.bytes,
+            vec![0xb2, 0x10, 0x7c, 0x34, 0x12, 0x1a, 0x64, 0x10, 0x80, 0xfe, 0x0f, 0x10, 0xfb]
+        );
+        assert_eq!(
+            error_kind("STZ $10"),
+            AsmErrorKind::UnknownInstruction("STZ".to_string())
+        );
+    }
+    #[test]
+    fn test_labels_and_branches() {
+        let assembly: Assembly = assemble(
+            "
+            start:  LDX #3
+            loop:   DEX
+                    BNE loop
+                    BEQ done    ; forward reference
+                    NOP
+            done:   JMP start
+            ",
+            Variant::Ricoh2A03,
+        )
+        .unwrap();
+        assert_eq!(assembly.origin, DEFAULT_ORIGIN);
+        assert_eq!(
+            assembly.bytes,
+            vec![0xa2, 0x03, 0xca, 0xd0, 0xfd, 0xf0, 0x01, 0xea, 0x4c, 0x00, 0x80]
+        );
+        assert_eq!(assembly.labels["loop"], 0x8002);
+        assert_eq!(assembly.labels["done"], 0x8008);
+    }
+    #[test]
+    fn test_zero_page_selection() {
+        // A constant defined up front gets the zero page form, a forward label can't
+        assert_eq!(assemble_ok("PTR = $10\nLDA PTR"), vec![0xa5, 0x10]);
+        assert_eq!(
+            assemble_ok(".org $0000\nLDA data\ndata: .byte 7"),
+            vec![0xad, 0x03, 0x00, 0x07]
+        );
+    }
+    #[test]
+    fn test_directives() {
+        let assembly: Assembly = assemble(
+            "
+                    .org $0600
+            table:  .byte 1, $02, %11, 'A', \"hi;\", -1
+                    .word table, $1234
+                    .org $0610
+                    .db <table, >table
+            ",
+            Variant::Ricoh2A03,
+        )
+        .unwrap();
+        assert_eq!(assembly.origin, 0x0600);
+        assert_eq!(assembly.bytes.len(), 0x12);
+        assert_eq!(
+            assembly.bytes[..12],
+            [0x01, 0x02, 0x03, 0x41, 0x68, 0x69, 0x3b, 0xff, 0x00, 0x06, 0x34, 0x12]
+        );
+        assert_eq!(assembly.bytes[12..0x10], [0, 0, 0, 0]);
+        assert_eq!(assembly.bytes[0x10..], [0x00, 0x06]);
+    }
+    #[test]
+    fn test_expressions() {
+        let symbols: BTreeMap<String, i64> = [("base".to_string(), 0x1200)].into_iter().collect();
+        let eval = |expr: &str| evaluate(expr, &symbols, 0x8000).unwrap();
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("base + $34"), 0x1234);
+        assert_eq!(eval(">base"), 0x12);
+        assert_eq!(eval("<(base + $1ff)"), 0xff);
+        assert_eq!(eval("1 << 4 | 1"), 0x11);
+        assert_eq!(eval("%1010 & $0f ^ 1"), 0b1011);
+        assert_eq!(eval("-1"), -1);
+        assert_eq!(eval("* + 2"), 0x8002);
+        assert_eq!(eval("100 / 7"), 14);
+        assert_eq!(
+            evaluate("nowhere + 1", &symbols, 0),
+            Err(AsmErrorKind::UndefinedSymbol("nowhere".to_string()))
+        );
+    }
+    #[test]
+    fn test_errors() {
+        assert_eq!(error_kind("FOO #1"), AsmErrorKind::UnknownInstruction("FOO".to_string()));
+        assert_eq!(error_kind("STX $1234,X"), AsmErrorKind::InvalidAddressingMode("STX".to_string()));
+        assert_eq!(error_kind("JMP nowhere"), AsmErrorKind::UndefinedSymbol("nowhere".to_string()));
+        assert_eq!(error_kind("a: NOP\na: NOP"), AsmErrorKind::DuplicateSymbol("a".to_string()));
+        assert_eq!(error_kind("LDA #$100"), AsmErrorKind::ValueOutOfRange(0x100));
+        assert_eq!(error_kind("BNE * + 200"), AsmErrorKind::BranchOutOfRange(198));
+        assert!(matches!(error_kind(".fill 3"), AsmErrorKind::Syntax(_)));
+
+        let error: AsmError = assemble("NOP\n\nLDA (", Variant::Ricoh2A03).unwrap_err();
+        assert_eq!(error.line, 3);
+        assert_eq!(error.to_string(), "line 3: expression ends too soon");
+    }
+    #[test]
+    fn test_asm_macro() {
+        assert_eq!(crate::asm!("LDA #$05", "BRK"), vec![0xa9, 0x05, 0x00]);
+    }
+    #[test]
+    #[should_panic(expected = "line 2: unknown instruction FOO")]
+    fn test_asm_macro_panics_on_bad_source() {
+        crate::asm!("NOP", "FOO");
+    }
+    #[test]
+    fn test_reassembles_disassembly_of_snake() {
+        // Drop the address and hex columns from the labelled listing to get source back
+        let listing: String = disasm::listing(&snake::GAME_CODE, snake::LOAD_ADDRESS, Variant::Ricoh2A03, true);
+        let body: Vec<&str> = listing
+            .lines()
+            .map(|line| if line.ends_with(':') { line } else { &line[16..] })
+            .collect();
+        let source: String = format!(".org ${:04X}\n{}", snake::LOAD_ADDRESS, body.join("\n"));
+        let assembly: Assembly = assemble(&source, Variant::Ricoh2A03).unwrap();
+        assert_eq!(assembly.origin, snake::LOAD_ADDRESS);
+        assert_eq!(assembly.bytes, snake::GAME_CODE.to_vec());
+    }
+}
@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use nes_emulator::cartridge::Rom;
+use nes_emulator::cpu::Variant;
+use nes_emulator::disasm;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+const USAGE: &str = "usage: nes-disasm <file.nes> [--bank N] [--origin HEX] [--labels]
+
+Disassembles one 16KB PRG ROM bank of an iNES file.
+  --bank N      bank to disassemble, counting from 0 (default: the last one)
+  --origin HEX  address the bank is mapped at (default: $C000 for the last bank, $8000 otherwise)
+  --labels      name branch and jump targets instead of printing raw addresses";
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
+
+fn main() {
+    let mut path: Option<String> = None;
+    let mut bank: Option<usize> = None;
+    let mut origin: Option<u16> = None;
+    let mut labels: bool = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bank" => {
+                bank = Some(args.next().and_then(|n| n.parse().ok()).unwrap_or_else(|| exit_with(USAGE)))
+            }
+            "--origin" => {
+                let value: Option<String> = args.next();
+                origin = Some(
+                    value
+                        .and_then(|hex| u16::from_str_radix(hex.trim_start_matches('$'), 16).ok())
+                        .unwrap_or_else(|| exit_with(USAGE)),
+                )
+            }
+            "--labels" => labels = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with("--") || path.is_some() => exit_with(USAGE),
+            _ => path = Some(arg),
+        }
+    }
+    let path: String = path.unwrap_or_else(|| exit_with(USAGE));
+
+    let rom: Rom = match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|raw| {
+        Rom::new(&raw).map_err(|e| e.to_string())
+    }) {
+        Ok(rom) => rom,
+        Err(e) => exit_with(&format!("{}: {}", path, e)),
+    };
+
+    let banks: usize = rom.prg_rom.len().div_ceil(PRG_BANK_SIZE);
+    let last: usize = banks.saturating_sub(1);
+    let bank: usize = bank.unwrap_or(last);
+    if bank >= banks {
+        exit_with(&format!("{}: no PRG bank {}, the ROM has {}", path, bank, banks));
+    }
+    // The last bank is the one mappers keep fixed at the top of memory, and NROM-128's
+    // only bank is mirrored there too, so that's where the vectors point
+    let origin: u16 = origin.unwrap_or(if bank == last { 0xC000 } else { 0x8000 });
+
+    let start: usize = bank * PRG_BANK_SIZE;
+    let end: usize = (start + PRG_BANK_SIZE).min(rom.prg_rom.len());
+    let listing: String = disasm::listing(&rom.prg_rom[start..end], origin, Variant::Ricoh2A03, labels);
+    // Not print!, which panics when piped into head
+    let _ = std::io::stdout().write_all(listing.as_bytes());
+}
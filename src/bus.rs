@@ -0,0 +1,454 @@
+use crate::cartridge::Rom;
+use crate::mapper;
+use crate::mapper::MapperError;
+use crate::mapper::SharedMapper;
+use crate::ppu::NesPPU;
+
+// Anything the CPU can be wired to: it sees a 16-bit address space and nothing else.
+// Reads take &mut self because on real hardware reading a register can have side effects
+// (e.g. reading PPUSTATUS clears the vblank flag).
+pub trait Bus {
+    fn mem_read(&mut self, addr: u16) -> u8;
+    fn mem_write(&mut self, addr: u16, data: u8);
+    // Reads without any side effects, for tracers and debuggers. Registers that can't be read
+    // that way return whatever is cheapest to report (open bus on the NES).
+    fn peek(&self, addr: u16) -> u8;
+    // Lets whatever else is on the bus (PPU, APU, ...) catch up with the CPU cycles just spent
+    fn tick(&mut self, _cycles: u8) {}
+    // Interrupt inputs as seen by the CPU. NMI is edge triggered, so the CPU only reacts when
+    // this goes from low to high; IRQ is level triggered and fires for as long as it is held.
+    fn nmi_line(&self) -> bool {
+        false
+    }
+    fn irq_line(&self) -> bool {
+        false
+    }
+    // Cycles the CPU has to sit out because of something it did (OAM DMA), cleared once taken
+    fn take_stall_cycles(&mut self) -> u16 {
+        0
+    }
+    // (scanline, dot) of the PPU for traces, if there is one on the bus
+    fn ppu_position(&self) -> Option<(u16, u16)> {
+        None
+    }
+
+    // NES uses little endian for u16: 0x8000 written as 00 80 (L to R)
+    // When reading u16, read two consecutive registers, and switch their order around to get
+    // the stored value
+    fn mem_read_u16(&mut self, addr: u16) -> u16 {
+        let lo: u16 = self.mem_read(addr) as u16;
+        let hi: u16 = self.mem_read(addr.wrapping_add(1)) as u16;
+        (hi << 8) | lo
+    }
+    // When writing u16, take upper and lower 8 bits and store them in reverse order.
+    fn mem_write_u16(&mut self, addr: u16, data: u16) {
+        let hi: u8 = (data >> 8) as u8; // Extract last 8 bits
+        let lo: u8 = (data & 0xff) as u8; // Extract first 8 bits
+        self.mem_write(addr, lo);
+        self.mem_write(addr.wrapping_add(1), hi);
+    }
+}
+
+// Plain 64 KiB of RAM with nothing else mapped. Used by the snake demo and the unit tests,
+// where every address simply stores whatever was last written to it.
+pub struct FlatRam {
+    pub memory: [u8; 0x10000],
+}
+
+impl FlatRam {
+    pub fn new() -> Self {
+        FlatRam {
+            memory: [0; 0x10000],
+        }
+    }
+}
+
+impl Default for FlatRam {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Bus for FlatRam {
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+    fn peek(&self, addr: u16) -> u8 {
+        self.memory[addr as usize]
+    }
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.memory[addr as usize] = data;
+    }
+}
+
+//  _______________ $10000  _______________
+// | PRG-ROM       |       |               |
+// | Upper Bank    |       |               |
+// |_ _ _ _ _ _ _ _| $C000 | PRG-ROM       |
+// | PRG-ROM       |       |               |
+// | Lower Bank    |       |               |
+// |_______________| $8000 |_______________|
+// | SRAM          |       | SRAM          |
+// |_______________| $6000 |_______________|
+// | Expansion ROM |       | Expansion ROM |
+// |_______________| $4020 |_______________|
+// | I/O Registers |       |               |
+// |_ _ _ _ _ _ _ _| $4000 |               |
+// | Mirrors       |       | I/O Registers |
+// | $2000-$2007   |       |               |
+// |_ _ _ _ _ _ _ _| $2008 |               |
+// | I/O Registers |       |               |
+// |_______________| $2000 |_______________|
+// | Mirrors       |       |               |
+// | $0000-$07FF   |       |               |
+// |_ _ _ _ _ _ _ _| $0800 |               |
+// | RAM           |       | RAM           |
+// |_ _ _ _ _ _ _ _| $0200 |               |
+// | Stack         |       |               |
+// |_ _ _ _ _ _ _ _| $0100 |               |
+// | Zero Page     |       |               |
+// |_______________| $0000 |_______________|
+const RAM: u16 = 0x0000;
+const RAM_MIRRORS_END: u16 = 0x1FFF;
+const PPU_REGISTERS: u16 = 0x2000;
+const PPU_REGISTERS_MIRRORS_END: u16 = 0x3FFF;
+const APU_IO_REGISTERS: u16 = 0x4000;
+const APU_IO_REGISTERS_END: u16 = 0x4017;
+const CARTRIDGE_SPACE: u16 = 0x4020;
+const CARTRIDGE_SPACE_END: u16 = 0xFFFF;
+
+// The CPU side of the NES: 2 KiB of internal RAM, the PPU and APU/IO registers and the
+// cartridge, each decoded from the address the same way the console's address decoder does.
+pub struct NesBus {
+    cpu_vram: [u8; 2048],
+    pub ppu: NesPPU,
+    apu_io_registers: [u8; 0x18],
+    mapper: SharedMapper, // Shared with the PPU, which reads CHR through it
+    // Last value seen on the data bus, returned by reads nothing responds to
+    open_bus: u8,
+    cycles: u64,
+    oam_dma: bool, // $4014 was written during the instruction being executed
+}
+
+impl NesBus {
+    // Fails if the cartridge uses a mapper that isn't implemented
+    pub fn new(rom: Rom) -> Result<Self, MapperError> {
+        let mapper: SharedMapper = mapper::for_rom(rom)?;
+        Ok(NesBus {
+            cpu_vram: [0; 2048],
+            ppu: NesPPU::new(mapper.clone()),
+            apu_io_registers: [0; 0x18],
+            mapper,
+            open_bus: 0,
+            cycles: 0,
+            oam_dma: false,
+        })
+    }
+    // Last value written to one of the $4000-$4017 APU and IO registers
+    pub fn apu_io_register(&self, addr: u16) -> u8 {
+        self.apu_io_registers[(addr - APU_IO_REGISTERS) as usize]
+    }
+    // Copy of the cartridge's battery backed RAM, if it has any
+    pub fn battery_ram(&self) -> Option<Vec<u8>> {
+        self.mapper.borrow().battery_ram().map(|ram| ram.to_vec())
+    }
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        self.mapper.borrow_mut().load_battery_ram(data);
+    }
+    fn read_cartridge(&self, addr: u16) -> u8 {
+        self.mapper.borrow().cpu_read(addr).unwrap_or(self.open_bus)
+    }
+}
+
+impl Bus for NesBus {
+    fn peek(&self, addr: u16) -> u8 {
+        match addr {
+            RAM..=RAM_MIRRORS_END => self.cpu_vram[(addr & 0b0000_0111_1111_1111) as usize],
+            CARTRIDGE_SPACE..=CARTRIDGE_SPACE_END => self.read_cartridge(addr),
+            // Reading these has side effects, so peek shows $FF like nestest.log does
+            APU_IO_REGISTERS..=APU_IO_REGISTERS_END => 0xFF,
+            _ => self.open_bus,
+        }
+    }
+    fn mem_read(&mut self, addr: u16) -> u8 {
+        let data: u8 = match addr {
+            RAM..=RAM_MIRRORS_END => {
+                // 11 address lines are wired to the RAM chip, so only the bottom 11 bits count
+                let mirror_down_addr: u16 = addr & 0b0000_0111_1111_1111;
+                self.cpu_vram[mirror_down_addr as usize]
+            }
+            PPU_REGISTERS..=PPU_REGISTERS_MIRRORS_END => {
+                let mirror_down_addr: u16 = addr & 0b0010_0000_0000_0111;
+                match mirror_down_addr {
+                    0x2002 => self.ppu.read_status(),
+                    0x2004 => self.ppu.read_oam_data(),
+                    0x2007 => self.ppu.read_data(),
+                    // The rest are write only
+                    _ => self.ppu.read_io_latch(),
+                }
+            }
+            APU_IO_REGISTERS..=APU_IO_REGISTERS_END => match addr {
+                // APU status: nothing playing and no frame IRQ. Bit 5 is not driven.
+                0x4015 => self.open_bus & 0b0010_0000,
+                // Controller ports only drive the low bits; nothing is plugged in.
+                0x4016 | 0x4017 => self.open_bus & 0b1110_0000,
+                // The rest of the APU registers are write only
+                _ => self.open_bus,
+            },
+            // Whatever the cartridge doesn't answer floats, as does $4018-$401F (CPU test mode)
+            CARTRIDGE_SPACE..=CARTRIDGE_SPACE_END => self.read_cartridge(addr),
+            _ => self.open_bus,
+        };
+        self.open_bus = data;
+        data
+    }
+    fn mem_write(&mut self, addr: u16, data: u8) {
+        self.open_bus = data;
+        match addr {
+            RAM..=RAM_MIRRORS_END => {
+                let mirror_down_addr: u16 = addr & 0b0000_0111_1111_1111;
+                self.cpu_vram[mirror_down_addr as usize] = data;
+            }
+            PPU_REGISTERS..=PPU_REGISTERS_MIRRORS_END => {
+                let mirror_down_addr: u16 = addr & 0b0010_0000_0000_0111;
+                match mirror_down_addr {
+                    0x2000 => self.ppu.write_to_ctrl(data),
+                    0x2001 => self.ppu.write_to_mask(data),
+                    0x2002 => self.ppu.write_to_status(data),
+                    0x2003 => self.ppu.write_to_oam_addr(data),
+                    0x2004 => self.ppu.write_to_oam_data(data),
+                    0x2005 => self.ppu.write_to_scroll(data),
+                    0x2006 => self.ppu.write_to_ppu_addr(data),
+                    _ => self.ppu.write_to_data(data),
+                }
+            }
+            0x4014 => {
+                // OAM DMA: copy page $XX00-$XXFF into sprite memory
+                let mut buffer: [u8; 256] = [0; 256];
+                let hi: u16 = (data as u16) << 8;
+                for (i, byte) in buffer.iter_mut().enumerate() {
+                    *byte = self.mem_read(hi + i as u16);
+                }
+                self.ppu.write_oam_dma(&buffer);
+                self.oam_dma = true;
+                self.apu_io_registers[(addr - APU_IO_REGISTERS) as usize] = data;
+            }
+            APU_IO_REGISTERS..=APU_IO_REGISTERS_END => {
+                self.apu_io_registers[(addr - APU_IO_REGISTERS) as usize] = data;
+            }
+            CARTRIDGE_SPACE..=CARTRIDGE_SPACE_END => self.mapper.borrow_mut().cpu_write(addr, data),
+            _ => {}
+        }
+    }
+    fn tick(&mut self, cycles: u8) {
+        // The PPU runs three dots for every CPU cycle
+        self.ppu.tick(cycles as u32 * 3);
+        self.mapper.borrow_mut().cpu_tick(cycles);
+        self.cycles += cycles as u64;
+    }
+    fn take_stall_cycles(&mut self) -> u16 {
+        if !std::mem::take(&mut self.oam_dma) {
+            return 0;
+        }
+        // The store wrote $4014 on its last cycle and the instruction has been ticked since, so
+        // the DMA starts on the cycle `cycles` points at. 513 cycles, plus one to line up with a
+        // read cycle when that one is odd.
+        513 + (self.cycles % 2) as u16
+    }
+    fn ppu_position(&self) -> Option<(u16, u16)> {
+        Some((self.ppu.scanline, self.ppu.cycle))
+    }
+    fn nmi_line(&self) -> bool {
+        self.ppu.nmi_line()
+    }
+    fn irq_line(&self) -> bool {
+        self.mapper.borrow().irq_line()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::test_rom;
+    use crate::cpu::CPU;
+
+    #[test]
+    fn test_flat_ram_read_write() {
+        let mut ram: FlatRam = FlatRam::new();
+        ram.mem_write(0x1234, 0x56);
+        assert_eq!(ram.mem_read(0x1234), 0x56);
+        assert_eq!(ram.memory[0x1234], 0x56);
+    }
+    #[test]
+    fn test_flat_ram_top_of_memory() {
+        let mut ram: FlatRam = FlatRam::new();
+        ram.mem_write(0xFFFF, 0xAB);
+        assert_eq!(ram.mem_read(0xFFFF), 0xAB);
+    }
+    #[test]
+    fn test_flat_ram_u16_wraps_around() {
+        let mut ram: FlatRam = FlatRam::new();
+        ram.mem_write_u16(0xFFFF, 0x1234);
+        assert_eq!(ram.mem_read(0xFFFF), 0x34);
+        assert_eq!(ram.mem_read(0x0000), 0x12);
+        assert_eq!(ram.mem_read_u16(0xFFFF), 0x1234);
+    }
+    #[test]
+    fn test_nes_ram_is_mirrored() {
+        let mut bus: NesBus = NesBus::new(test_rom(vec![])).unwrap();
+        bus.mem_write(0x0012, 0x34);
+        assert_eq!(bus.mem_read(0x0812), 0x34);
+        assert_eq!(bus.mem_read(0x1012), 0x34);
+        assert_eq!(bus.mem_read(0x1812), 0x34);
+        bus.mem_write(0x1FFF, 0x56);
+        assert_eq!(bus.mem_read(0x07FF), 0x56);
+    }
+    #[test]
+    fn test_nes_ppu_registers_are_mirrored() {
+        let mut bus: NesBus = NesBus::new(test_rom(vec![])).unwrap();
+        bus.mem_write(0x3456, 0x21); // Mirror of $2006
+        bus.mem_write(0x2006, 0x08);
+        assert_eq!(bus.ppu.vram_addr(), 0x2108);
+        bus.mem_write(0x2007, 0x42);
+        bus.mem_write(0x2008, 0x00); // Mirror of $2000
+        bus.mem_write(0x2006, 0x21);
+        bus.mem_write(0x3FFE, 0x08); // Mirror of $2006
+        bus.mem_read(0x2007);
+        assert_eq!(bus.mem_read(0x3FFF), 0x42); // Mirror of $2007
+        // Write only registers read back the PPU's data latch
+        bus.mem_write(0x2003, 0x5C);
+        assert_eq!(bus.mem_read(0x2005), 0x5C);
+    }
+    #[test]
+    fn test_nes_apu_io_registers() {
+        let mut bus: NesBus = NesBus::new(test_rom(vec![])).unwrap();
+        bus.mem_write(0x4000, 0x3F);
+        assert_eq!(bus.apu_io_register(0x4000), 0x3F);
+        // Write only, so the read sees the last value on the bus
+        bus.mem_write(0x0000, 0x5A);
+        assert_eq!(bus.mem_read(0x4000), 0x5A);
+        bus.mem_write(0x0000, 0xFF);
+        assert_eq!(bus.mem_read(0x4016), 0xE0);
+    }
+    #[test]
+    fn test_nes_oam_dma() {
+        let mut bus: NesBus = NesBus::new(test_rom(vec![])).unwrap();
+        for i in 0..256 {
+            bus.mem_write(0x0200 + i, i as u8);
+        }
+        bus.mem_write(0x4014, 0x02);
+        assert_eq!(bus.ppu.oam_data[0x00], 0x00);
+        assert_eq!(bus.ppu.oam_data[0x80], 0x80);
+        assert_eq!(bus.ppu.oam_data[0xFF], 0xFF);
+    }
+    #[test]
+    fn test_oam_dma_stalls_the_cpu() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+        prg_rom[..6].copy_from_slice(&[0xa9, 0x02, 0x8d, 0x14, 0x40, 0x00]); // LDA #$02 STA $4014 BRK
+        prg_rom[0x3FFD] = 0x80; // Reset vector -> $8000
+        let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(test_rom(prg_rom)).unwrap());
+        cpu.reset();
+        cpu.run().unwrap();
+        // Reset, LDA, STA, then the DMA, which starts on cycle 13 and needs an alignment cycle
+        assert_eq!(cpu.cycles(), 7 + 2 + 4 + 514);
+    }
+    #[test]
+    fn test_oam_dma_parity_comes_from_the_write() {
+        // STA $4004,X takes 5 cycles, so the write and the instruction start on different parities
+        let run = |delay: bool| -> u64 {
+            let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+            prg_rom[..10].copy_from_slice(&[
+                0xa9, 0x02, // LDA #$02
+                0xa2, 0x10, // LDX #$10
+                0xea, 0xea, // NOP NOP, or BIT $00 for one cycle more
+                0x9d, 0x04, 0x40, // STA $4004,X
+                0x00, // BRK
+            ]);
+            if delay {
+                prg_rom[4..6].copy_from_slice(&[0x24, 0x00]);
+            }
+            prg_rom[0x3FFD] = 0x80; // Reset vector -> $8000
+            let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(test_rom(prg_rom)).unwrap());
+            cpu.reset();
+            cpu.run().unwrap();
+            cpu.cycles()
+        };
+        // The STA starts on cycle 15 and the DMA on cycle 20: no alignment cycle
+        assert_eq!(run(false), 7 + 2 + 2 + 2 + 2 + 5 + 513);
+        // Starting on cycle 14, the DMA starts on cycle 19 and needs one
+        assert_eq!(run(true), 7 + 2 + 2 + 3 + 5 + 514);
+    }
+    #[test]
+    fn test_nes_unmapped_reads_return_open_bus() {
+        let mut bus: NesBus = NesBus::new(test_rom(vec![])).unwrap();
+        bus.mem_write(0x0010, 0xA5);
+        assert_eq!(bus.mem_read(0x0010), 0xA5);
+        assert_eq!(bus.mem_read(0x5000), 0xA5);
+        assert_eq!(bus.mem_read(0x8000), 0xA5);
+    }
+    #[test]
+    fn test_nes_prg_ram() {
+        let mut bus: NesBus = NesBus::new(test_rom(vec![])).unwrap();
+        bus.mem_write(0x6000, 0xDE);
+        bus.mem_write(0x7FFF, 0xB0);
+        assert_eq!(bus.mem_read(0x6000), 0xDE);
+        assert_eq!(bus.mem_read(0x7FFF), 0xB0);
+        assert_eq!(bus.peek(0x6000), 0xDE);
+    }
+    #[test]
+    fn test_nes_prg_rom_16k_is_mirrored() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+        prg_rom[0x0000] = 0x11;
+        prg_rom[0x3FFC] = 0x00;
+        prg_rom[0x3FFD] = 0x80;
+        let mut bus: NesBus = NesBus::new(test_rom(prg_rom)).unwrap();
+        assert_eq!(bus.mem_read(0x8000), 0x11);
+        assert_eq!(bus.mem_read(0xC000), 0x11);
+        assert_eq!(bus.mem_read_u16(0xFFFC), 0x8000);
+        // Writes don't touch ROM
+        bus.mem_write(0x8000, 0x22);
+        assert_eq!(bus.mem_read(0x8000), 0x11);
+    }
+    #[test]
+    fn test_cpu_runs_from_nes_ram() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+        prg_rom[0x3FFC] = 0x00; // Reset vector -> $0600
+        prg_rom[0x3FFD] = 0x06;
+        let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(test_rom(prg_rom)).unwrap());
+        cpu.load_at(vec![0xa9, 0x05, 0x8d, 0x00, 0x08, 0x00], 0x0600).unwrap(); // LDA 0x05 STA 0x0800 BRK
+        cpu.reset();
+        assert_eq!(cpu.program_counter, 0x0600);
+        cpu.run().unwrap();
+        assert_eq!(cpu.mem_read(0x0000), 0x05);
+    }
+    #[test]
+    fn test_mapper_irq_reaches_the_cpu() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x8000];
+        prg_rom[0x7FFC] = 0x00; // Reset vector -> $0600
+        prg_rom[0x7FFD] = 0x06;
+        prg_rom[0x7FFE] = 0x00; // IRQ vector -> $0700
+        prg_rom[0x7FFF] = 0x07;
+        let mut rom: Rom = test_rom(prg_rom);
+        rom.mapper = 4;
+        let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(rom).unwrap());
+        // MMC3 IRQ on every scanline, sprites at $1000, rendering on, then wait
+        let program: Vec<u8> = vec![
+            0xa9, 0x00, // LDA #$00
+            0x8d, 0x00, 0xc0, // STA $C000
+            0x8d, 0x01, 0xc0, // STA $C001
+            0x8d, 0x01, 0xe0, // STA $E001
+            0xa9, 0x08, // LDA #$08
+            0x8d, 0x00, 0x20, // STA $2000
+            0xa9, 0x18, // LDA #$18
+            0x8d, 0x01, 0x20, // STA $2001
+            0x58, // CLI
+            0x4c, 0x16, 0x06, // JMP $0616
+        ];
+        cpu.load_at(program, 0x0600).unwrap();
+        cpu.load_at(vec![0xa9, 0x42, 0x85, 0x00, 0x00], 0x0700).unwrap(); // LDA #$42 STA $00 BRK
+        cpu.reset();
+        cpu.run().unwrap();
+        assert_eq!(cpu.mem_read(0x0000), 0x42);
+        assert!(cpu.bus.ppu.scanline < 2);
+    }
+}
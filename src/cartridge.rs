@@ -0,0 +1,436 @@
+use std::fmt;
+
+// "NES" followed by MS-DOS end-of-file
+const NES_TAG: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
+const HEADER_SIZE: usize = 16;
+const TRAINER_SIZE: usize = 512;
+pub const PRG_ROM_PAGE_SIZE: usize = 16384;
+pub const CHR_ROM_PAGE_SIZE: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mirroring {
+    Vertical,
+    Horizontal,
+    FourScreen,
+    // All four nametables show the same 1 KiB. Only mappers switch to these.
+    SingleScreenLower,
+    SingleScreenUpper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomFormat {
+    INes,
+    Nes20,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingRegion {
+    Ntsc,
+    Pal,
+    MultiRegion,
+    Dendy,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    // File is smaller than the 16 byte header
+    MissingHeader,
+    // First four bytes aren't "NES\x1A"
+    NotINes,
+    // Header promises more PRG/CHR/trainer data than the file holds
+    Truncated { expected: usize, actual: usize },
+    // NES 2.0 exponent-multiplier size that doesn't fit in memory
+    SizeOverflow,
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RomError::MissingHeader => write!(f, "file is too short to hold an iNES header"),
+            RomError::NotINes => write!(f, "file is not in iNES file format"),
+            RomError::Truncated { expected, actual } => write!(
+                f,
+                "file is truncated: header describes {} bytes but file has {}",
+                expected, actual
+            ),
+            RomError::SizeOverflow => write!(f, "ROM size in NES 2.0 header is too large"),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+// A parsed .nes file: the raw PRG and CHR data plus everything the header says about the board.
+#[derive(Debug, Clone)]
+pub struct Rom {
+    pub format: RomFormat,
+    pub prg_rom: Vec<u8>,
+    pub chr_rom: Vec<u8>, // Empty when the board uses CHR RAM instead
+    pub trainer: Option<Vec<u8>>, // 512 bytes meant for $7000-$71FF
+    pub mapper: u16,
+    pub submapper: u8,
+    pub screen_mirroring: Mirroring,
+    pub battery: bool,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
+    pub timing: TimingRegion,
+}
+
+impl Rom {
+    pub fn new(raw: &[u8]) -> Result<Rom, RomError> {
+        if raw.len() < HEADER_SIZE {
+            return Err(RomError::MissingHeader);
+        }
+        if raw[0..4] != NES_TAG {
+            return Err(RomError::NotINes);
+        }
+        let flags_6: u8 = raw[6];
+        let flags_7: u8 = raw[7];
+        // Bits 2-3 of flags 7 set to 0b10 identify NES 2.0
+        let format: RomFormat = if flags_7 & 0b0000_1100 == 0b0000_1000 {
+            RomFormat::Nes20
+        } else {
+            RomFormat::INes
+        };
+
+        let four_screen: bool = flags_6 & 0b1000 != 0;
+        let vertical_mirroring: bool = flags_6 & 0b1 != 0;
+        let screen_mirroring: Mirroring = match (four_screen, vertical_mirroring) {
+            (true, _) => Mirroring::FourScreen,
+            (false, true) => Mirroring::Vertical,
+            (false, false) => Mirroring::Horizontal,
+        };
+        let battery: bool = flags_6 & 0b10 != 0;
+        let has_trainer: bool = flags_6 & 0b100 != 0;
+
+        let (mapper, submapper, prg_rom_size, chr_rom_size) = match format {
+            RomFormat::Nes20 => {
+                let mapper: u16 = ((raw[8] as u16 & 0x0F) << 8)
+                    | (flags_7 & 0xF0) as u16
+                    | (flags_6 >> 4) as u16;
+                let prg_rom_size: usize = nes2_rom_size(raw[4], raw[9] & 0x0F, PRG_ROM_PAGE_SIZE)?;
+                let chr_rom_size: usize = nes2_rom_size(raw[5], raw[9] >> 4, CHR_ROM_PAGE_SIZE)?;
+                (mapper, raw[8] >> 4, prg_rom_size, chr_rom_size)
+            }
+            RomFormat::INes => {
+                // Old dumping tools wrote signatures like "DiskDude!" over bytes 7-15, which
+                // garbles the upper mapper nibble. A clean header has zeroes in bytes 12-15.
+                let upper_nibble: u8 = if raw[12..16].iter().all(|&b| b == 0) {
+                    flags_7 & 0xF0
+                } else {
+                    0
+                };
+                let mapper: u16 = (upper_nibble | (flags_6 >> 4)) as u16;
+                (
+                    mapper,
+                    0,
+                    raw[4] as usize * PRG_ROM_PAGE_SIZE,
+                    raw[5] as usize * CHR_ROM_PAGE_SIZE,
+                )
+            }
+        };
+
+        let (prg_ram_size, prg_nvram_size, chr_ram_size, chr_nvram_size, timing) = match format {
+            RomFormat::Nes20 => {
+                let timing: TimingRegion = match raw[12] & 0b11 {
+                    0 => TimingRegion::Ntsc,
+                    1 => TimingRegion::Pal,
+                    2 => TimingRegion::MultiRegion,
+                    _ => TimingRegion::Dendy,
+                };
+                (
+                    nes2_ram_size(raw[10] & 0x0F),
+                    nes2_ram_size(raw[10] >> 4),
+                    nes2_ram_size(raw[11] & 0x0F),
+                    nes2_ram_size(raw[11] >> 4),
+                    timing,
+                )
+            }
+            RomFormat::INes => {
+                // A value of 0 means 8 KiB, for compatibility with dumps that never set it
+                let prg_ram_size: usize = raw[8].max(1) as usize * 8192;
+                let (prg_ram_size, prg_nvram_size) = if battery {
+                    (0, prg_ram_size)
+                } else {
+                    (prg_ram_size, 0)
+                };
+                let chr_ram_size: usize = if chr_rom_size == 0 { 8192 } else { 0 };
+                let timing: TimingRegion = if raw[9] & 0b1 != 0 {
+                    TimingRegion::Pal
+                } else {
+                    TimingRegion::Ntsc
+                };
+                (prg_ram_size, prg_nvram_size, chr_ram_size, 0, timing)
+            }
+        };
+
+        let trainer_size: usize = if has_trainer { TRAINER_SIZE } else { 0 };
+        let prg_rom_start: usize = HEADER_SIZE + trainer_size;
+        let chr_rom_start: usize = prg_rom_start
+            .checked_add(prg_rom_size)
+            .ok_or(RomError::SizeOverflow)?;
+        let expected: usize = chr_rom_start
+            .checked_add(chr_rom_size)
+            .ok_or(RomError::SizeOverflow)?;
+        if raw.len() < expected {
+            return Err(RomError::Truncated {
+                expected,
+                actual: raw.len(),
+            });
+        }
+
+        Ok(Rom {
+            format,
+            prg_rom: raw[prg_rom_start..chr_rom_start].to_vec(),
+            chr_rom: raw[chr_rom_start..expected].to_vec(),
+            trainer: if has_trainer {
+                Some(raw[HEADER_SIZE..prg_rom_start].to_vec())
+            } else {
+                None
+            },
+            mapper,
+            submapper,
+            screen_mirroring,
+            battery,
+            prg_ram_size,
+            prg_nvram_size,
+            chr_ram_size,
+            chr_nvram_size,
+            timing,
+        })
+    }
+}
+
+// NES 2.0 ROM sizes: the header byte is the LSB of the page count and the nibble in byte 9 the
+// MSB, unless that nibble is $F, in which case the byte is EEEEEEMM and size = 2^E * (MM*2+1).
+fn nes2_rom_size(lsb: u8, msb: u8, page_size: usize) -> Result<usize, RomError> {
+    if msb == 0x0F {
+        let exponent: u32 = (lsb >> 2) as u32;
+        let multiplier: usize = (lsb & 0b11) as usize * 2 + 1;
+        1usize
+            .checked_shl(exponent)
+            .and_then(|size| size.checked_mul(multiplier))
+            .ok_or(RomError::SizeOverflow)
+    } else {
+        Ok((((msb as usize) << 8) | lsb as usize) * page_size)
+    }
+}
+
+// NES 2.0 RAM sizes are shift counts: 0 means none, otherwise 64 << shift bytes.
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    pub struct TestRom {
+        pub header: Vec<u8>,
+        pub trainer: Option<Vec<u8>>,
+        pub prg_rom: Vec<u8>,
+        pub chr_rom: Vec<u8>,
+    }
+
+    pub fn create_rom(rom: TestRom) -> Vec<u8> {
+        let mut result: Vec<u8> = Vec::with_capacity(
+            rom.header.len()
+                + rom.trainer.as_ref().map_or(0, |t| t.len())
+                + rom.prg_rom.len()
+                + rom.chr_rom.len(),
+        );
+        result.extend(&rom.header);
+        if let Some(t) = rom.trainer {
+            result.extend(t);
+        }
+        result.extend(&rom.prg_rom);
+        result.extend(&rom.chr_rom);
+        result
+    }
+
+    // NROM cartridge with the given PRG and 8 KiB of blank CHR
+    pub fn test_rom(prg_rom: Vec<u8>) -> Rom {
+        let prg_banks: u8 = prg_rom.len().div_ceil(PRG_ROM_PAGE_SIZE) as u8;
+        let mut padded: Vec<u8> = prg_rom;
+        padded.resize(prg_banks as usize * PRG_ROM_PAGE_SIZE, 0);
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, prg_banks, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: padded,
+            chr_rom: vec![0; CHR_ROM_PAGE_SIZE],
+        });
+        Rom::new(&raw).unwrap()
+    }
+    // `count` banks of `bank_size` bytes, each filled with its own number, so a read shows
+    // which bank a mapper has switched in
+    pub fn numbered_banks(count: usize, bank_size: usize) -> Vec<u8> {
+        (0..count * bank_size).map(|i| (i / bank_size) as u8).collect()
+    }
+
+    #[test]
+    fn test_ines() {
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x31, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: vec![1; 2 * PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![2; CHR_ROM_PAGE_SIZE],
+        });
+        let rom: Rom = Rom::new(&raw).unwrap();
+
+        assert_eq!(rom.format, RomFormat::INes);
+        assert_eq!(rom.chr_rom, vec![2; CHR_ROM_PAGE_SIZE]);
+        assert_eq!(rom.prg_rom, vec![1; 2 * PRG_ROM_PAGE_SIZE]);
+        assert_eq!(rom.mapper, 3);
+        assert_eq!(rom.screen_mirroring, Mirroring::Vertical);
+        assert!(!rom.battery);
+        assert_eq!(rom.trainer, None);
+        assert_eq!(rom.prg_ram_size, 8192);
+        assert_eq!(rom.chr_ram_size, 0);
+        assert_eq!(rom.timing, TimingRegion::Ntsc);
+    }
+
+    #[test]
+    fn test_ines_with_trainer_and_battery() {
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x1E, 0x10, 0x02, 0x01, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: Some(vec![0xAB; TRAINER_SIZE]),
+            prg_rom: vec![1; PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![],
+        });
+        let rom: Rom = Rom::new(&raw).unwrap();
+
+        assert_eq!(rom.mapper, 0x11);
+        assert_eq!(rom.screen_mirroring, Mirroring::FourScreen);
+        assert!(rom.battery);
+        assert_eq!(rom.trainer, Some(vec![0xAB; TRAINER_SIZE]));
+        assert_eq!(rom.prg_rom, vec![1; PRG_ROM_PAGE_SIZE]);
+        assert_eq!(rom.prg_ram_size, 0);
+        assert_eq!(rom.prg_nvram_size, 2 * 8192);
+        assert!(rom.chr_rom.is_empty());
+        assert_eq!(rom.chr_ram_size, 8192);
+        assert_eq!(rom.timing, TimingRegion::Pal);
+    }
+
+    #[test]
+    fn test_ines_ignores_garbage_in_byte_7() {
+        let mut header: Vec<u8> = b"NES\x1A\x01\x01\x10DiskDude!".to_vec();
+        header.truncate(HEADER_SIZE);
+        let raw: Vec<u8> = create_rom(TestRom {
+            header,
+            trainer: None,
+            prg_rom: vec![0; PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![0; CHR_ROM_PAGE_SIZE],
+        });
+        let rom: Rom = Rom::new(&raw).unwrap();
+        assert_eq!(rom.mapper, 1);
+    }
+
+    #[test]
+    fn test_nes2() {
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, 0x02, 0x00, 0x41, 0x08, 0x31, 0x00, 0x97, 0x07, 0x01,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: vec![1; 2 * PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![],
+        });
+        let rom: Rom = Rom::new(&raw).unwrap();
+
+        assert_eq!(rom.format, RomFormat::Nes20);
+        assert_eq!(rom.mapper, 0x104);
+        assert_eq!(rom.submapper, 3);
+        assert_eq!(rom.screen_mirroring, Mirroring::Vertical);
+        assert_eq!(rom.prg_ram_size, 64 << 7);
+        assert_eq!(rom.prg_nvram_size, 64 << 9);
+        assert_eq!(rom.chr_ram_size, 64 << 7);
+        assert_eq!(rom.chr_nvram_size, 0);
+        assert_eq!(rom.timing, TimingRegion::Pal);
+    }
+
+    #[test]
+    fn test_nes2_exponent_multiplier_size() {
+        // PRG: E=14, MM=1 -> 2^14 * 3 = 48 KiB
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, 0b0011_1001, 0x01, 0x00, 0x08, 0x00, 0x0F, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: vec![1; 3 * PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![2; CHR_ROM_PAGE_SIZE],
+        });
+        let rom: Rom = Rom::new(&raw).unwrap();
+        assert_eq!(rom.prg_rom.len(), 3 * PRG_ROM_PAGE_SIZE);
+        assert_eq!(rom.chr_rom.len(), CHR_ROM_PAGE_SIZE);
+    }
+
+    #[test]
+    fn test_nes2_size_overflow() {
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, 0xFF, 0xFF, 0x00, 0x08, 0x00, 0xFF, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: vec![],
+            chr_rom: vec![],
+        });
+        assert_eq!(Rom::new(&raw).unwrap_err(), RomError::SizeOverflow);
+    }
+
+    #[test]
+    fn test_missing_header() {
+        assert_eq!(Rom::new(&[0x4E, 0x45, 0x53]).unwrap_err(), RomError::MissingHeader);
+    }
+
+    #[test]
+    fn test_not_ines() {
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: vec![1; PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![2; CHR_ROM_PAGE_SIZE],
+        });
+        assert_eq!(Rom::new(&raw).unwrap_err(), RomError::NotINes);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let raw: Vec<u8> = create_rom(TestRom {
+            header: vec![
+                0x4E, 0x45, 0x53, 0x1A, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00,
+            ],
+            trainer: None,
+            prg_rom: vec![1; PRG_ROM_PAGE_SIZE],
+            chr_rom: vec![],
+        });
+        assert_eq!(
+            Rom::new(&raw).unwrap_err(),
+            RomError::Truncated {
+                expected: HEADER_SIZE + 2 * PRG_ROM_PAGE_SIZE + CHR_ROM_PAGE_SIZE,
+                actual: HEADER_SIZE + PRG_ROM_PAGE_SIZE,
+            }
+        );
+    }
+}
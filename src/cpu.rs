@@ -1,9 +1,19 @@
-
-use crate::opcodes::Instructions;
+// The interpreter predates clippy in CI; its long-hand style is kept as written
+#![allow(
+    clippy::assign_op_pattern,
+    clippy::explicit_counter_loop,
+    clippy::needless_borrow,
+    clippy::unnecessary_cast
+)]
+use crate::bus::Bus;
+use crate::bus::FlatRam;
 use crate::opcodes::OpCode;
+use crate::opcodes::OPCODES;
+use crate::opcodes::OPCODES_65C02;
+use std::fmt;
 pub fn main() {}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(non_camel_case_types)]
 pub enum AddressingMode {
     Immediate,
@@ -15,10 +25,119 @@ pub enum AddressingMode {
     Absolute_Y,
     Indirect_X,
     Indirect_Y,
+    ZeroPage_Indirect, // (zp), 65C02 only
     NoneAddressing,
 }
+
+// Where the CPU fetches the handler address from for each kind of interrupt
+const NMI_VECTOR: u16 = 0xFFFA;
+const IRQ_BRK_VECTOR: u16 = 0xFFFE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Nmi,
+    Irq,
+    Brk,
+}
+
+// How a run or step ended when nothing went wrong
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepOutcome {
+    // Instruction ran, the CPU can carry on
+    Continue,
+    // BRK with halt_on_brk set
+    Halted,
+}
+
+// Everything step() saw while running one instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    pub pc: u16, // Where the instruction was fetched from (the handler, if an interrupt was taken)
+    pub opcode: u8,
+    pub cycles: u64, // Including the interrupt sequence and any DMA stall
+    pub interrupt: Option<Interrupt>, // NMI or IRQ serviced before the instruction
+    pub accesses: Vec<MemoryAccess>, // In bus order, starting with the opcode fetch
+    pub outcome: StepOutcome,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessKind {
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryAccess {
+    pub kind: AccessKind,
+    pub addr: u16,
+    pub data: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CpuError {
+    // Byte at PC isn't in the instruction table. The built-in table has an entry for all 256
+    // opcodes, so the interpreter never returns this; it stays for callers that match on it.
+    UnknownOpcode { opcode: u8, pc: u16 },
+    // A KIL opcode locked up the CPU; only reset recovers
+    Jammed { pc: u16 },
+    // A 65C02 STP opcode stopped the clock; only reset recovers
+    Stopped { pc: u16 },
+    // Program doesn't fit in the address space at the requested address
+    InvalidLoadRange { start: u16, len: usize },
+    // Operand address asked for a mode that has no operand (implied, accumulator, ...)
+    UnsupportedAddressingMode { mode: AddressingMode, pc: u16 },
+}
+
+impl fmt::Display for CpuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CpuError::UnknownOpcode { opcode, pc } => {
+                write!(f, "unknown opcode {:#04x} at {:#06x}", opcode, pc)
+            }
+            CpuError::Jammed { pc } => write!(f, "CPU jammed by KIL opcode at {:#06x}", pc),
+            CpuError::Stopped { pc } => write!(f, "CPU stopped by STP opcode at {:#06x}", pc),
+            CpuError::InvalidLoadRange { start, len } => write!(
+                f,
+                "program of {} bytes does not fit in memory at {:#06x}",
+                len, start
+            ),
+            CpuError::UnsupportedAddressingMode { mode, pc } => {
+                write!(f, "addressing mode {:?} has no operand (at {:#06x})", mode, pc)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CpuError {}
+
+// Bits the unstable XAA/LXA opcodes OR into A before masking
+const UNSTABLE_MAGIC: u8 = 0xEE;
+
+// Which chip is being emulated. The NES's 2A03 is an NMOS 6502 with the decimal mode
+// circuitry cut out: SED and CLD still toggle D, but ADC and SBC always work in binary.
+// The 65C02 is the CMOS redesign, with extra instructions and the NMOS bugs fixed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Ricoh2A03,
+    Nmos6502,
+    Wdc65C02,
+}
+
+impl Variant {
+    // The instruction table the variant decodes with
+    pub fn opcodes(self) -> &'static [OpCode; 256] {
+        match self {
+            Variant::Wdc65C02 => &OPCODES_65C02,
+            Variant::Ricoh2A03 | Variant::Nmos6502 => &OPCODES,
+        }
+    }
+}
+
+fn page_crossed(from: u16, to: u16) -> bool {
+    from & 0xFF00 != to & 0xFF00
+}
 // CPU Registers:
-pub struct CPU {
+pub struct CPU<B: Bus = FlatRam> {
     pub register_a: u8, // Accumulator
     pub register_x: u8,
     pub register_y: u8,
@@ -30,11 +149,41 @@ pub struct CPU {
     // E.g. if value is 0100011: Zero, Overflow, Negative flags all set
     pub status: u8,
     pub program_counter: u16, // Holds address for next instruction
-    pub memory: [u8; 0xFFFF], // 64 KiB array simulating memory
+    pub bus: B, // Everything the CPU can address goes through here
+    // When set, BRK stops `run` instead of taking the interrupt. Programs loaded with
+    // `load_and_run` use BRK as their "exit", so this is on unless a real machine turns it off.
+    pub halt_on_brk: bool,
+    pub variant: Variant, // 2A03 unless set otherwise
+    jammed: bool, // Hit a KIL opcode; only a reset gets the CPU going again
+    stopped: bool, // Same for the 65C02's STP
+    waiting: bool, // 65C02 WAI: asleep until an interrupt line is asserted
+    cycles: u64, // CPU cycles since power on
+    extra_cycles: u8, // Page crossing and branch penalties for the current instruction
+    // Only step() pays for logging bus accesses
+    record_accesses: bool,
+    accesses: Vec<MemoryAccess>,
+    // Interrupt inputs driven from outside the bus, ORed with the bus' own lines
+    nmi_input: bool,
+    irq_input: bool,
+    nmi_previous: bool, // NMI level at the last poll, for edge detection
+    nmi_pending: bool,
+    irq_pending: bool,
 }
 
-impl CPU {
+impl CPU<FlatRam> {
     pub fn new() -> Self {
+        CPU::with_bus(FlatRam::new())
+    }
+}
+
+impl Default for CPU<FlatRam> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B: Bus> CPU<B> {
+    pub fn with_bus(bus: B) -> Self {
         CPU {
             register_a: 0,
             register_x: 0,
@@ -43,40 +192,86 @@ impl CPU {
             stack_start: 0x0100,
             status: 0,
             program_counter: 0,
-            memory: [0; 0xFFFF],
+            bus,
+            halt_on_brk: true,
+            variant: Variant::Ricoh2A03,
+            jammed: false,
+            stopped: false,
+            waiting: false,
+            cycles: 0,
+            extra_cycles: 0,
+            record_accesses: false,
+            accesses: Vec::new(),
+            nmi_input: false,
+            irq_input: false,
+            nmi_previous: false,
+            nmi_pending: false,
+            irq_pending: false,
         }
     }
-    pub fn get_operand_address(&self, mode: &AddressingMode) -> u16 {
-        match mode {
-            AddressingMode::Immediate => self.program_counter,
-            AddressingMode::ZeroPage => self.mem_read(self.program_counter) as u16,
+    // True once a KIL/JAM opcode has locked up the CPU
+    pub fn is_jammed(&self) -> bool {
+        self.jammed
+    }
+    // Total CPU cycles executed, including interrupt sequences and DMA stalls
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+    // Advances the cycle counter and lets the rest of the bus catch up
+    fn tick(&mut self, cycles: u8) {
+        self.cycles += cycles as u64;
+        self.bus.tick(cycles);
+    }
+    pub fn set_nmi_line(&mut self, level: bool) {
+        self.nmi_input = level;
+    }
+    pub fn set_irq_line(&mut self, level: bool) {
+        self.irq_input = level;
+    }
+    pub fn get_operand_address(&mut self, mode: &AddressingMode) -> Result<u16, CpuError> {
+        Ok(self.operand_address(mode)?.0)
+    }
+    // Same as get_operand_address, but read instructions pay an extra cycle when indexing
+    // crosses into the next page (the CPU has to fix up the high byte first)
+    fn read_operand_address(&mut self, mode: &AddressingMode) -> Result<u16, CpuError> {
+        let (addr, page_crossed) = self.operand_address(mode)?;
+        if page_crossed {
+            self.extra_cycles += 1;
+        }
+        Ok(addr)
+    }
+    // Resolves the operand address, and whether indexing moved it to a different page
+    fn operand_address(&mut self, mode: &AddressingMode) -> Result<(u16, bool), CpuError> {
+        let operand: (u16, bool) = match mode {
+            AddressingMode::Immediate => (self.program_counter, false),
+            AddressingMode::ZeroPage => (self.mem_read(self.program_counter) as u16, false),
             AddressingMode::ZeroPage_X => {
                 let pos: u8 = self.mem_read(self.program_counter);
                 let addr: u16 = pos.wrapping_add(self.register_x) as u16;
-                addr
+                (addr, false)
             }
             AddressingMode::ZeroPage_Y => {
                 let pos: u8 = self.mem_read(self.program_counter);
                 let addr: u16 = pos.wrapping_add(self.register_y) as u16;
-                addr
+                (addr, false)
             }
-            AddressingMode::Absolute => self.mem_read_u16(self.program_counter),
+            AddressingMode::Absolute => (self.mem_read_u16(self.program_counter), false),
             AddressingMode::Absolute_X => {
                 let pos: u16 = self.mem_read_u16(self.program_counter);
                 let addr: u16 = pos.wrapping_add(self.register_x as u16) as u16;
-                addr
+                (addr, page_crossed(pos, addr))
             }
             AddressingMode::Absolute_Y => {
                 let pos: u16 = self.mem_read_u16(self.program_counter);
                 let addr: u16 = pos.wrapping_add(self.register_y as u16) as u16;
-                addr
+                (addr, page_crossed(pos, addr))
             }
             AddressingMode::Indirect_X => {
                 let pos: u8 = self.mem_read(self.program_counter);
                 let ptr: u8 = pos.wrapping_add(self.register_x);
                 let lo: u8 = self.mem_read(ptr as u16);
                 let hi: u8 = self.mem_read(ptr.wrapping_add(1) as u16);
-                (hi as u16) << 8 | (lo as u16)
+                ((hi as u16) << 8 | (lo as u16), false)
             }
             AddressingMode::Indirect_Y => {
                 let pos: u8 = self.mem_read(self.program_counter);
@@ -84,35 +279,51 @@ impl CPU {
                 let lo: u8 = self.mem_read(pos as u16);
                 let hi: u8 = self.mem_read(pos.wrapping_add(1) as u16);
                 let deref_pos: u16 = (hi as u16) << 8 | (lo as u16);
-                deref_pos.wrapping_add(self.register_y as u16)
+                let addr: u16 = deref_pos.wrapping_add(self.register_y as u16);
+                (addr, page_crossed(deref_pos, addr))
+            }
+            AddressingMode::ZeroPage_Indirect => {
+                let pos: u8 = self.mem_read(self.program_counter);
+                let lo: u8 = self.mem_read(pos as u16);
+                let hi: u8 = self.mem_read(pos.wrapping_add(1) as u16);
+                ((hi as u16) << 8 | (lo as u16), false)
             }
             AddressingMode::NoneAddressing => {
-                panic!("mode {:?} is not supported", mode);
+                return Err(CpuError::UnsupportedAddressingMode {
+                    mode: *mode,
+                    pc: self.program_counter,
+                });
             }
-        }
+        };
+        Ok(operand)
     }
     // Reads from given address in memory
-    pub fn mem_read(&self, addr: u16) -> u8 {
-        self.memory[addr as usize]
+    pub fn mem_read(&mut self, addr: u16) -> u8 {
+        let data: u8 = self.bus.mem_read(addr);
+        if self.record_accesses {
+            self.accesses.push(MemoryAccess { kind: AccessKind::Read, addr, data });
+        }
+        data
     }
     // Writes data to given address
     pub fn mem_write(&mut self, addr: u16, data: u8) {
-        self.memory[addr as usize] = data;
+        if self.record_accesses {
+            self.accesses.push(MemoryAccess { kind: AccessKind::Write, addr, data });
+        }
+        self.bus.mem_write(addr, data);
     }
     // NES uses little endian for u16: 0x8000 written as 00 80 (L to R)
     // When reading u16, read two consecutive registers, and switch their order around to get
-    // the stored value
-    pub fn mem_read_u16(&self, addr: u16) -> u16 {
+    // the stored value. Byte by byte rather than through the bus' own u16 helpers, so step()
+    // sees both accesses
+    pub fn mem_read_u16(&mut self, addr: u16) -> u16 {
         let lo: u16 = self.mem_read(addr) as u16;
-        let hi: u16 = self.mem_read(addr + 1) as u16;
-        
-        (hi << 8) | (lo as u16)
+        let hi: u16 = self.mem_read(addr.wrapping_add(1)) as u16;
+        (hi << 8) | lo
     }
-    pub fn print_memory(&self){
-        let mut addr = 0;
-        for item in self.memory.iter() {
-            println!("0x{:x}: 0x{:x}", addr, item);
-            addr += 1;
+    pub fn print_memory(&mut self) {
+        for addr in 0..=0xFFFF_u16 {
+            println!("0x{:x}: 0x{:x}", addr, self.mem_read(addr));
         }
     }
     pub fn stack_push(&mut self, data: u8) {
@@ -134,24 +345,36 @@ impl CPU {
     }
     // When writing u16, take upper and lower 8 bits and store them in reverse order.
     pub fn mem_write_u16(&mut self, addr: u16, data: u16) {
-        let hi: u8 = (data >> 8) as u8; // Extract last 8 bits
-        let lo: u8 = (data & 0xff) as u8; // Extract first 8 bits
-        self.mem_write(addr, lo);
-        self.mem_write(addr + 1, hi);
+        self.mem_write(addr, (data & 0xff) as u8);
+        self.mem_write(addr.wrapping_add(1), (data >> 8) as u8);
     }
     // Loads program into memory, resets registers, then runs the program
-    pub fn load_and_run(&mut self, program: Vec<u8>) {
-        self.load(program);
+    pub fn load_and_run(&mut self, program: Vec<u8>) -> Result<StepOutcome, CpuError> {
+        self.load(program)?;
         self.reset();
-        self.run();
-    }
-    // Loads program into memory and sets PC to value found in 0xFFFC
-    pub fn load(&mut self, program: Vec<u8>) {
-        self.load_at(program, 0x8000);
-    }
-    pub fn load_at(&mut self, program: Vec<u8>, addr: u16) {
-        self.memory[addr as usize..(addr as usize + program.len())].copy_from_slice(&program[..]); // Load program into memory
-        self.mem_write_u16(0xFFFC, addr);
+        self.run()
+    }
+    // Loads program into memory at 0x8000 and points the reset vector at it, for plain RAM
+    pub fn load(&mut self, program: Vec<u8>) -> Result<(), CpuError> {
+        self.load_at(program, 0x8000)?;
+        self.mem_write_u16(0xFFFC, 0x8000);
+        Ok(())
+    }
+    // Copies program into memory. The reset vector is left alone: on a cartridge bus $FFFC is
+    // ROM or a mapper register, so callers on plain RAM point it at the program themselves.
+    pub fn load_at(&mut self, program: Vec<u8>, addr: u16) -> Result<(), CpuError> {
+        // The program has to fit below the top of the address space
+        if addr as usize + program.len() > 0x10000 {
+            return Err(CpuError::InvalidLoadRange {
+                start: addr,
+                len: program.len(),
+            });
+        }
+        // Load program into memory
+        for (i, byte) in program.iter().enumerate() {
+            self.mem_write(addr + i as u16, *byte);
+        }
+        Ok(())
     }
     // Restore set of all registers and initialize PC to 2 byte value stored in 0xFFFC
     pub fn reset(&mut self) {
@@ -159,15 +382,24 @@ impl CPU {
         self.register_x = 0;
         self.status = 0;
         self.program_counter = self.mem_read_u16(0xFFFC);
-    }
-    pub fn run(&mut self) { 
-        self.run_with_callback(|_| {});
-    }
-    // Interprets instructions.
+        self.nmi_previous = false;
+        self.nmi_pending = false;
+        self.irq_pending = false;
+        self.jammed = false;
+        self.stopped = false;
+        self.waiting = false;
+        // The reset sequence takes 7 cycles before the first instruction is fetched
+        self.tick(7);
+    }
+    pub fn run(&mut self) -> Result<StepOutcome, CpuError> {
+        self.run_with_callback(|_| {})
+    }
+    // Interprets instructions until BRK halts the CPU or something goes wrong.
     // Takes mutable reference to self to change registers and program instructions.
-    pub fn run_with_callback<F>(&mut self, mut callback: F) where F: FnMut(&mut CPU),{
-        // Get our instruction set
-        let instructions: Instructions = Instructions::new();
+    pub fn run_with_callback<F>(&mut self, mut callback: F) -> Result<StepOutcome, CpuError>
+    where
+        F: FnMut(&mut CPU<B>),
+    {
         // CPU Cycle:
         // Fetch
         // Decode
@@ -175,115 +407,322 @@ impl CPU {
         // Repeat
         '_cpu_cycle: loop {
             callback(self);
-            
-            let opcode: u8 = self.mem_read(self.program_counter); // Fetch
-            let operation: &OpCode = instructions
-                .map
-                .get(&opcode)
-                .expect("Failed to get from map");
-            let mode: &AddressingMode = &operation.mode;
-            self.program_counter += 1; // PC UPDATE
-            let first_program_counter: u16 = self.program_counter;
-
-            println!(
-                "Instruction: {}, Addressing Mode: {:?}",
-                operation.name, operation.mode
-            );
-            // DECODE, then on match EXECUTE
-            match opcode {
-                0x00 => return,                                             // BRK
-                0xea => println!("NOP!"),                                                  // NOP
-                0x69 | 0x65 | 0x75 | 0x6d | 0x7d | 0x79 | 0x61 | 0x71 => self.adc(&mode), // ADC
-                0xe9 | 0xe5 | 0xf5 | 0xed | 0xfd | 0xf9 | 0xe1 | 0xf1 => self.sbc(&mode), // SBC
-                0x29 | 0x25 | 0x35 | 0x2d | 0x3d | 0x39 | 0x21 | 0x31 => self.and(&mode), // AND
-                0x49 | 0x45 | 0x55 | 0x4d | 0x5d | 0x59 | 0x41 | 0x51 => self.eor(&mode), // EOR
-                0x09 | 0x05 | 0x15 | 0x0d | 0x1d | 0x19 | 0x01 | 0x11 => self.ora(&mode), // ORA
-                0x0a | 0x06 | 0x16 | 0x0e | 0x1e => self.asl(&mode),        // ASL
-                0x4a | 0x46 | 0x56 | 0x4e | 0x5e => self.lsr(&mode),                     // LSR
-                0x2a | 0x26 | 0x36 | 0x2e | 0x3e => self.rol(&mode),                      // ROL
-                0x6a | 0x66 | 0x76 | 0x6e | 0x7e => self.ror(&mode),                      // ROR
-                0xe6 | 0xf6 | 0xee | 0xfe => self.inc(&mode),                             // INC
-                0xe8 => self.inx(),                                         // INX
-                0xc8 => self.iny(),                                                  // INY
-                0xc6 | 0xd6 | 0xce | 0xde => self.dec(&mode),                             // DEC
-                0xca => self.dex(),                                                  // DEX
-                0x88 => self.dey(),                                                  // DEY
-                0xc9 | 0xc5 | 0xd5 | 0xcd | 0xdd | 0xd9 | 0xc1 | 0xd1 => self.cmp(&mode), // CMP
-                0xc0 | 0xc4 | 0xcc => self.cpy(&mode),                                    // CPY
-                0xe0 | 0xe4 | 0xec => self.cpx(&mode),                                    // CPX
-                0x4c | 0x6c => self.jmp(&mode),                                           // JMP
-                0x20 => self.jsr(),                                                  // JSR
-                0x60 => self.rts(),                                                 // RTS
-                0x40 => self.rti(),                                                  // RTI
-                0xd0 => self.bne(),                                                  // BNE
-                0x70 => self.bvs(),                                                  // BVS
-                0x50 => self.bvc(),                                                  // BVC
-                0x30 => self.bmi(),                                                  // BMI
-                0xf0 => self.beq(),                                                  // BEQ
-                0xb0 => self.bcs(),                                                  // BCS
-                0x90 => self.bcc(),                                                  // BCC
-                0x10 => self.bpl(),                                                  // BPL
-                0x24 | 0x2c => self.bit(&mode),                                           // BIT
-                0xA9 | 0xa5 | 0xb5 | 0xad | 0xbd | 0xb9 | 0xa1 | 0xb1 => self.lda(&mode), // LDA
-                0xa2 | 0xa6 | 0xb6 | 0xae | 0xbe => self.ldx(&mode),        // LDX
-                0xa0 | 0xa4 | 0xb4 | 0xac | 0xbc => self.ldy(&mode),        // LDY
-                0x85 | 0x95 | 0x8d | 0x9d | 0x99 | 0x81 | 0x91 => self.sta(&mode), // STA
-                0x86 | 0x96 | 0x8e => self.stx(&mode),                      // STX
-                0x84 | 0x94 | 0x8c => self.sty(&mode),                      // STY
-                0xd8 => self.cld(),                                                  // CLD
-                0x58 => self.cli(),                                         // CLI
-                0xb8 => self.clv(),                                                  // CLV
-                0x18 => self.clc(),                                                  // CLC
-                0x38 => self.sec(),                                                  // SEC
-                0x78 => self.sei(),                                                  // SEI
-                0xf8 => self.sed(),                                                  // SED
-                0xaa => self.tax(),                                         // TAX
-                0xa8 => self.tay(),                                         // TAY
-                0xba => self.tsx(),                                         // TSX
-                0x8A => self.txa(),                                         // TXA
-                0x9a => self.txs(),                                         // TXS
-                0x98 => self.tya(),                                         // TYA
-                0x48 => self.pha(),                                         // PHA
-                0x68 => self.pla(),                                         // PLA
-                0x08 => self.php(),                                         // PHP
-                0x28 => self.plp(),                                         // PLP
-
-                _ => todo!(""),
+            if self.execute()?.outcome == StepOutcome::Halted {
+                return Ok(StepOutcome::Halted);
             }
+        } // REPEAT
+    }
+    // Executes exactly one instruction (after servicing a pending interrupt) and reports what
+    // it did, down to every bus access
+    pub fn step(&mut self) -> Result<StepReport, CpuError> {
+        self.accesses.clear();
+        self.record_accesses = true;
+        let report: Result<StepReport, CpuError> = self.execute();
+        self.record_accesses = false;
+        let mut report: StepReport = report?;
+        report.accesses = std::mem::take(&mut self.accesses);
+        Ok(report)
+    }
+    // Runs whole instructions until at least `cycles` CPU cycles have gone by, so it may
+    // overshoot by part of an instruction. Stops early with Halted if BRK halts the CPU.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> Result<StepOutcome, CpuError> {
+        let target: u64 = self.cycles + cycles;
+        while self.cycles < target {
+            if self.execute()?.outcome == StepOutcome::Halted {
+                return Ok(StepOutcome::Halted);
+            }
+        }
+        Ok(StepOutcome::Continue)
+    }
+    // Runs until the predicate holds, checking it before every instruction
+    pub fn run_until<P>(&mut self, mut predicate: P) -> Result<StepOutcome, CpuError>
+    where
+        P: FnMut(&CPU<B>) -> bool,
+    {
+        while !predicate(self) {
+            if self.execute()?.outcome == StepOutcome::Halted {
+                return Ok(StepOutcome::Halted);
+            }
+        }
+        Ok(StepOutcome::Continue)
+    }
+    // Runs a single instruction, servicing a pending interrupt first
+    fn execute(&mut self) -> Result<StepReport, CpuError> {
+        if self.jammed {
+            return Err(CpuError::Jammed {
+                pc: self.program_counter,
+            });
+        }
+        if self.stopped {
+            return Err(CpuError::Stopped {
+                pc: self.program_counter,
+            });
+        }
+        let start_cycles: u64 = self.cycles;
+        if self.waiting {
+            // Any interrupt wakes WAI up, even an IRQ masked by I (which then just resumes)
+            if !(self.nmi_pending || self.irq_pending || self.irq_input || self.bus.irq_line()) {
+                self.tick(1);
+                self.poll_interrupts(self.status & 0b0000_0100);
+                return Ok(StepReport {
+                    pc: self.program_counter.wrapping_sub(1),
+                    opcode: 0xcb,
+                    cycles: self.cycles - start_cycles,
+                    interrupt: None,
+                    accesses: Vec::new(),
+                    outcome: StepOutcome::Continue,
+                });
+            }
+            self.waiting = false;
+        }
+        // Interrupts are polled at the end of each instruction and taken before the next one
+        let interrupt: Option<Interrupt> = if self.nmi_pending {
+            self.nmi_pending = false;
+            Some(Interrupt::Nmi)
+        } else if self.irq_pending {
+            self.irq_pending = false;
+            Some(Interrupt::Irq)
+        } else {
+            None
+        };
+        if let Some(kind) = interrupt {
+            self.interrupt(kind);
+        }
 
-            // PC hasn't changed, so no branching
-            if first_program_counter == self.program_counter {
-                // -1 because already moved up the instruction that was read
-                self.program_counter += (operation.num_bytes - 1) as u16;
+        let pc: u16 = self.program_counter;
+        let opcode: u8 = self.mem_read(pc); // Fetch
+        let report = |cpu: &CPU<B>, outcome: StepOutcome| StepReport {
+            pc,
+            opcode,
+            cycles: cpu.cycles - start_cycles,
+            interrupt,
+            accesses: Vec::new(),
+            outcome,
+        };
+        let operation: &OpCode = self
+            .variant
+            .opcodes()
+            .get(opcode as usize)
+            .ok_or(CpuError::UnknownOpcode {
+                opcode,
+                pc: self.program_counter,
+            })?;
+        let mode: &AddressingMode = &operation.mode;
+        self.program_counter = self.program_counter.wrapping_add(1); // PC UPDATE
+        let first_program_counter: u16 = self.program_counter;
+        // CLI, SEI and PLP change I after the interrupt poll, so their IRQ check sees the old value
+        let interrupt_disable: u8 = self.status & 0b0000_0100;
+        self.extra_cycles = 0;
+        let mut cycles: u8 = operation.num_cycles;
+
+        // DECODE, then on match EXECUTE
+        // The 65C02 gets first go, for everything it added or changed
+        let handled: bool = self.variant == Variant::Wdc65C02 && self.execute_65c02(opcode, mode)?;
+        match opcode {
+            _ if handled => {}
+            0x00 => {                                                   // BRK
+                if self.halt_on_brk {
+                    return Ok(report(self, StepOutcome::Halted));
+                }
+                self.interrupt(Interrupt::Brk);
+                cycles = 0; // The interrupt sequence already ticked the bus
             }
-        } // REPEAT
+            0xea => {}                                                              // NOP
+            0x69 | 0x65 | 0x75 | 0x6d | 0x7d | 0x79 | 0x61 | 0x71 => self.adc(mode)?, // ADC
+            0xe9 | 0xe5 | 0xf5 | 0xed | 0xfd | 0xf9 | 0xe1 | 0xf1 => self.sbc(mode)?, // SBC
+            0x29 | 0x25 | 0x35 | 0x2d | 0x3d | 0x39 | 0x21 | 0x31 => self.and(mode)?, // AND
+            0x49 | 0x45 | 0x55 | 0x4d | 0x5d | 0x59 | 0x41 | 0x51 => self.eor(mode)?, // EOR
+            0x09 | 0x05 | 0x15 | 0x0d | 0x1d | 0x19 | 0x01 | 0x11 => self.ora(mode)?, // ORA
+            0x0a | 0x06 | 0x16 | 0x0e | 0x1e => self.asl(mode)?,        // ASL
+            0x4a | 0x46 | 0x56 | 0x4e | 0x5e => self.lsr(mode)?,                     // LSR
+            0x2a | 0x26 | 0x36 | 0x2e | 0x3e => self.rol(mode)?,                      // ROL
+            0x6a | 0x66 | 0x76 | 0x6e | 0x7e => self.ror(mode)?,                      // ROR
+            0xe6 | 0xf6 | 0xee | 0xfe => self.inc(mode)?,                             // INC
+            0xe8 => self.inx(),                                         // INX
+            0xc8 => self.iny(),                                                  // INY
+            0xc6 | 0xd6 | 0xce | 0xde => self.dec(mode)?,                             // DEC
+            0xca => self.dex(),                                                  // DEX
+            0x88 => self.dey(),                                                  // DEY
+            0xc9 | 0xc5 | 0xd5 | 0xcd | 0xdd | 0xd9 | 0xc1 | 0xd1 => self.cmp(mode)?, // CMP
+            0xc0 | 0xc4 | 0xcc => self.cpy(mode)?,                                    // CPY
+            0xe0 | 0xe4 | 0xec => self.cpx(mode)?,                                    // CPX
+            0x4c | 0x6c => self.jmp(mode),                                           // JMP
+            0x20 => self.jsr(),                                                  // JSR
+            0x60 => self.rts(),                                                 // RTS
+            0x40 => self.rti(),                                                  // RTI
+            0xd0 => self.bne(),                                                  // BNE
+            0x70 => self.bvs(),                                                  // BVS
+            0x50 => self.bvc(),                                                  // BVC
+            0x30 => self.bmi(),                                                  // BMI
+            0xf0 => self.beq(),                                                  // BEQ
+            0xb0 => self.bcs(),                                                  // BCS
+            0x90 => self.bcc(),                                                  // BCC
+            0x10 => self.bpl(),                                                  // BPL
+            0x24 | 0x2c => self.bit(mode)?,                                           // BIT
+            0xA9 | 0xa5 | 0xb5 | 0xad | 0xbd | 0xb9 | 0xa1 | 0xb1 => self.lda(mode)?, // LDA
+            0xa2 | 0xa6 | 0xb6 | 0xae | 0xbe => self.ldx(mode)?,        // LDX
+            0xa0 | 0xa4 | 0xb4 | 0xac | 0xbc => self.ldy(mode)?,        // LDY
+            0x85 | 0x95 | 0x8d | 0x9d | 0x99 | 0x81 | 0x91 => self.sta(mode)?, // STA
+            0x86 | 0x96 | 0x8e => self.stx(mode)?,                      // STX
+            0x84 | 0x94 | 0x8c => self.sty(mode)?,                      // STY
+            0xd8 => self.cld(),                                                  // CLD
+            0x58 => self.cli(),                                         // CLI
+            0xb8 => self.clv(),                                                  // CLV
+            0x18 => self.clc(),                                                  // CLC
+            0x38 => self.sec(),                                                  // SEC
+            0x78 => self.sei(),                                                  // SEI
+            0xf8 => self.sed(),                                                  // SED
+            0xaa => self.tax(),                                         // TAX
+            0xa8 => self.tay(),                                         // TAY
+            0xba => self.tsx(),                                         // TSX
+            0x8A => self.txa(),                                         // TXA
+            0x9a => self.txs(),                                         // TXS
+            0x98 => self.tya(),                                         // TYA
+            0x48 => self.pha(),                                         // PHA
+            0x68 => self.pla(),                                         // PLA
+            0x08 => self.php(),                                         // PHP
+            0x28 => self.plp(),                                         // PLP
+
+            /* Unofficial */
+            0x1a | 0x3a | 0x5a | 0x7a | 0xda | 0xfa => {}                      // NOP
+            0x80 | 0x82 | 0x89 | 0xc2 | 0xe2 | 0x04 | 0x44 | 0x64 | 0x14 | 0x34 | 0x54 | 0x74
+            | 0xd4 | 0xf4 | 0x0c | 0x1c | 0x3c | 0x5c | 0x7c | 0xdc | 0xfc => self.nop_read(mode)?, // NOP
+            0x07 | 0x17 | 0x0f | 0x1f | 0x1b | 0x03 | 0x13 => self.slo(mode)?,        // SLO
+            0x27 | 0x37 | 0x2f | 0x3f | 0x3b | 0x23 | 0x33 => self.rla(mode)?,        // RLA
+            0x47 | 0x57 | 0x4f | 0x5f | 0x5b | 0x43 | 0x53 => self.sre(mode)?,        // SRE
+            0x67 | 0x77 | 0x6f | 0x7f | 0x7b | 0x63 | 0x73 => self.rra(mode)?,        // RRA
+            0xc7 | 0xd7 | 0xcf | 0xdf | 0xdb | 0xc3 | 0xd3 => self.dcp(mode)?,        // DCP
+            0xe7 | 0xf7 | 0xef | 0xff | 0xfb | 0xe3 | 0xf3 => self.isb(mode)?,        // ISB
+            0x87 | 0x97 | 0x8f | 0x83 => self.sax(mode)?,                             // SAX
+            0xa7 | 0xb7 | 0xaf | 0xbf | 0xa3 | 0xb3 => self.lax(mode)?,               // LAX
+            0xab => self.lxa(mode)?,                                                  // LAX #imm
+            0x0b | 0x2b => self.anc(mode)?,                                           // ANC
+            0x4b => self.alr(mode)?,                                                  // ALR
+            0x6b => self.arr(mode)?,                                                  // ARR
+            0xcb => self.axs(mode)?,                                                  // AXS
+            0xeb => self.sbc(mode)?,                                                  // SBC
+            0x8b => self.xaa(mode)?,                                                  // XAA
+            0x9f | 0x93 => self.store_and_high(mode, self.register_a & self.register_x)?, // AHX
+            0x9c => self.store_and_high(mode, self.register_y)?,                      // SHY
+            0x9e => self.store_and_high(mode, self.register_x)?,                      // SHX
+            0x9b => self.tas(mode)?,                                                  // TAS
+            0xbb => self.las(mode)?,                                                  // LAS
+            0x02 | 0x12 | 0x22 | 0x32 | 0x42 | 0x52 | 0x62 | 0x72 | 0x92 | 0xb2 | 0xd2 | 0xf2 => {
+                // KIL: the CPU locks up with PC stuck on the opcode, after fetching it and the
+                // byte behind it
+                self.tick(cycles);
+                self.jammed = true;
+                self.program_counter = self.program_counter.wrapping_sub(1);
+                return Err(CpuError::Jammed {
+                    pc: self.program_counter,
+                });
+            }
+        }
+
+        // PC hasn't changed, so no branching
+        if first_program_counter == self.program_counter {
+            // -1 because already moved up the instruction that was read
+            self.program_counter = self.program_counter.wrapping_add((operation.num_bytes - 1) as u16);
+        }
+
+        self.tick(cycles + self.extra_cycles);
+        // Writing $4014 halts the CPU while OAM DMA copies a page to the PPU
+        let mut stall: u16 = self.bus.take_stall_cycles();
+        while stall > 0 {
+            let chunk: u8 = stall.min(u8::MAX as u16) as u8;
+            self.tick(chunk);
+            stall -= chunk as u16;
+        }
+
+        let interrupt_disable: u8 = match opcode {
+            0x58 | 0x78 | 0x28 => interrupt_disable,
+            _ => self.status & 0b0000_0100,
+        };
+        self.poll_interrupts(interrupt_disable);
+        Ok(report(self, StepOutcome::Continue))
+    }
+    // Samples the interrupt lines: latches a rising edge on NMI and checks whether IRQ is
+    // asserted while interrupts are enabled
+    fn poll_interrupts(&mut self, interrupt_disable: u8) {
+        self.poll_nmi();
+        self.irq_pending = (self.irq_input || self.bus.irq_line()) && interrupt_disable == 0;
+    }
+    fn poll_nmi(&mut self) {
+        let nmi: bool = self.nmi_input || self.bus.nmi_line();
+        if nmi && !self.nmi_previous {
+            self.nmi_pending = true;
+        }
+        self.nmi_previous = nmi;
+    }
+    // Pushes PC and status, sets I and jumps through the vector. Takes 7 cycles.
+    // BRK pushes the address after its padding byte, with the B flag set in the pushed status.
+    pub fn interrupt(&mut self, kind: Interrupt) {
+        let return_addr: u16 = match kind {
+            Interrupt::Brk => self.program_counter.wrapping_add(1),
+            _ => self.program_counter,
+        };
+        self.stack_push_u16(return_addr);
+        let pushed_status: u8 = match kind {
+            Interrupt::Brk => self.status | 0b0011_0000,
+            _ => (self.status | 0b0010_0000) & 0b1110_1111,
+        };
+        self.stack_push(pushed_status);
+        self.status = self.status | 0b0000_0100;
+        if self.variant == Variant::Wdc65C02 {
+            self.status &= 0b1111_0111; // The 65C02 also clears D, so handlers start in binary
+        }
+        self.tick(5);
+
+        // Hijacking: an NMI that shows up before the vector is fetched takes over a BRK or IRQ,
+        // which then runs the NMI handler (BRK keeps its B flag on the stack)
+        self.poll_nmi();
+        let vector: u16 = if kind == Interrupt::Nmi || self.nmi_pending {
+            self.nmi_pending = false;
+            NMI_VECTOR
+        } else {
+            IRQ_BRK_VECTOR
+        };
+        self.program_counter = self.mem_read_u16(vector);
+        self.tick(2);
     }
     // LDA: Load Accumulator to Memory
-    fn lda(&mut self, mode: &AddressingMode) {
-        self.register_a = self.mem_read(self.get_operand_address(mode));
+    fn lda(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        self.register_a = self.mem_read(addr);
         self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
     }
     // LDX: Load Index Register X From Memory
-    fn ldx(&mut self, mode: &AddressingMode) {
-        self.register_x = self.mem_read(self.get_operand_address(mode));
+    fn ldx(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        self.register_x = self.mem_read(addr);
         self.update_zero_and_negative_flags(self.register_x);
+        Ok(())
     }
     // LDY: Load Index Register Y From Memory
-    fn ldy(&mut self, mode: &AddressingMode) {
-        self.register_y = self.mem_read(self.get_operand_address(mode));
+    fn ldy(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        self.register_y = self.mem_read(addr);
         self.update_zero_and_negative_flags(self.register_y);
+        Ok(())
     }
     // STA: Store Accumulator in Memory
-    fn sta(&mut self, mode: &AddressingMode) {
-        self.mem_write(self.get_operand_address(mode), self.register_a);
+    fn sta(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        self.mem_write(addr, self.register_a);
+        Ok(())
     }
     // STY: Store Accumulator in Memory
-    fn sty(&mut self, mode: &AddressingMode) {
-        self.mem_write(self.get_operand_address(mode), self.register_y);
+    fn sty(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        self.mem_write(addr, self.register_y);
+        Ok(())
     }
     // STX: Store Accumulator in Memory
-    fn stx(&mut self, mode: &AddressingMode) {
-        self.mem_write(self.get_operand_address(mode), self.register_x);
+    fn stx(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        self.mem_write(addr, self.register_x);
+        Ok(())
     }
     // TAX: Transfer Accumulator to X
     fn tax(&mut self) {
@@ -320,8 +759,8 @@ impl CPU {
     }
     // PHA: Push Status On Stack
     fn php(&mut self) {
-        self.status = self.status | 0b0011_0000;
-        self.stack_push(self.status);
+        // B only exists in the pushed copy
+        self.stack_push(self.status | 0b0011_0000);
     }
     // PLA: Pull Accumulator From Stack
     fn pla(&mut self) {
@@ -335,10 +774,13 @@ impl CPU {
         self.status = self.status & 0b1110_1111; // Unset Break
     }
     // INC: Increment Memory by One
-    fn inc(&mut self, mode: &AddressingMode) {
-        let addr: u16 = self.get_operand_address(mode);
-        self.mem_write_u16(addr, self.mem_read_u16(addr).wrapping_add(1));
-        self.update_zero_and_negative_flags(self.mem_read_u16(addr) as u8);
+    fn inc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let original: u8 = self.mem_read(addr);
+        let data: u8 = original.wrapping_add(1);
+        self.write_back(addr, original, data);
+        self.update_zero_and_negative_flags(data);
+        Ok(())
     }
     // INX: Increment index X by one
     fn inx(&mut self) {
@@ -351,117 +793,190 @@ impl CPU {
         self.update_zero_and_negative_flags(self.register_y);
     }
     // AND: "AND" Memory with Accumulator
-    fn and(&mut self, mode: &AddressingMode) {
-        let data: u8 = self.mem_read(self.get_operand_address(mode) as u16);
+    fn and(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
         self.register_a = self.register_a & data;
         self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
     }
     // ASL: Arithmetic Shift Left
-    fn asl(&mut self, mode: &AddressingMode) {
-        match mode {
-            AddressingMode::NoneAddressing => {
-                let original: u8 = self.register_a;
-                self.register_a = self.register_a << 1;
-
-                self.status = self.status | original >> 7;
-                self.update_zero_and_negative_flags(self.register_a);
-            }
-            _ => {
-                let addr: u16 = self.get_operand_address(mode);
-                let data: u8 = self.mem_read(addr);
-
-                self.mem_write(addr, data << 1);
-
-                self.status = self.status | data >> 7;
-                self.update_zero_and_negative_flags(data);
-
-            }
-        }
+    fn asl(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.read_modify_write(mode, Self::shift_left)?;
+        Ok(())
     }
     // LSR: Logical Shift Right
-    fn lsr(&mut self, mode: &AddressingMode){
+    fn lsr(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.read_modify_write(mode, Self::shift_right)?;
+        Ok(())
+    }
+    // ROL: Rotate Left
+    fn rol(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.read_modify_write(mode, Self::rotate_left)?;
+        Ok(())
+    }
+    // ROR: Rotate Right
+    fn ror(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.read_modify_write(mode, Self::rotate_right)?;
+        Ok(())
+    }
+    // Applies a shift/rotate to the accumulator (NoneAddressing) or to memory, updating Z and N
+    // from the result. Returns the result so the unofficial combined opcodes can reuse it.
+    fn read_modify_write(
+        &mut self,
+        mode: &AddressingMode,
+        operation: fn(&mut Self, u8) -> u8,
+    ) -> Result<u8, CpuError> {
         match mode {
             AddressingMode::NoneAddressing => {
-                let original: u8 = self.register_a;
-                self.register_a = self.register_a >> 1;
-
-                self.status = self.status | (original & 0b0000_0001);
+                self.register_a = operation(self, self.register_a);
                 self.update_zero_and_negative_flags(self.register_a);
+                Ok(self.register_a)
             }
             _ => {
-                let addr: u16 = self.get_operand_address(mode);
+                let addr: u16 = self.get_operand_address(mode)?;
                 let data: u8 = self.mem_read(addr);
-
-                self.mem_write(addr, data >> 1);
-
-                self.status = self.status | (data & 0b0000_0001);
-                self.update_zero_and_negative_flags(data);
+                let result: u8 = operation(self, data);
+                self.write_back(addr, data, result);
+                self.update_zero_and_negative_flags(result);
+                Ok(result)
             }
         }
     }
-    // ROL: Rotate Left
-    fn rol(&mut self, mode: &AddressingMode) {
+    // The write at the end of a read-modify-write instruction. The NMOS chips first write the
+    // unmodified value back during the cycle they spend on the operation, which registers
+    // like MMC1's can see. The 65C02 reads the address a second time instead.
+    fn write_back(&mut self, addr: u16, original: u8, result: u8) {
+        if self.variant != Variant::Wdc65C02 {
+            self.mem_write(addr, original);
+        }
+        self.mem_write(addr, result);
+    }
+    fn shift_left(&mut self, data: u8) -> u8 {
+        self.set_carry(data & 0b1000_0000 != 0);
+        data << 1
+    }
+    fn shift_right(&mut self, data: u8) -> u8 {
+        self.set_carry(data & 0b0000_0001 != 0);
+        data >> 1
+    }
+    fn rotate_left(&mut self, data: u8) -> u8 {
         let carry: u8 = self.status & 0b0000_0001;
-        // Perform rotation and store carry
-        self.asl(mode);
-        
-        // update 0th bit with carry's value before changed by asl
-        match mode {
-            AddressingMode::NoneAddressing => self.register_a = self.register_a | carry,
-            _ => {
-                let addr: u16 = self.get_operand_address(mode);
-                self.mem_write(addr, self.mem_read(addr) | carry);
-            },
-        }  
+        self.set_carry(data & 0b1000_0000 != 0);
+        data << 1 | carry
     }
-    // ROR: Rotate Right
-    fn ror(&mut self, mode: &AddressingMode) {
+    fn rotate_right(&mut self, data: u8) -> u8 {
         let carry: u8 = self.status & 0b0000_0001;
-        // Perform rotation and store carry
-        self.lsr(mode);
-        
-        // update 7th bit with carry's value before changed by asl
-        match mode {
-            AddressingMode::NoneAddressing => {
-                self.register_a = self.register_a | (carry << 7);
-                self.update_zero_and_negative_flags(self.register_a);
-            }
-            _ => {
-                let addr: u16 = self.get_operand_address(mode);
-                let data: u8 = self.mem_read(addr) | (carry << 7);
-                self.mem_write(addr, data);
-                self.update_zero_and_negative_flags(data);
-            },
-        }
-       
+        self.set_carry(data & 0b0000_0001 != 0);
+        data >> 1 | carry << 7
     }
     // BIT: Test Bits in Memory with Accumulator
-    fn bit(&mut self, mode: &AddressingMode) {
-        let data: u8 = self.mem_read(self.get_operand_address(mode) as u16);
+    fn bit(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
         let result: u8 = self.register_a & data;
-        
+
         // Set N flag to M7, V flag to M6, Z flag to result of and
-        self.status = self.status | (0b1100_0000 & data);
-        if result == 0 {
-            self.status = self.status | 0b0000_0010;
-        }
+        self.status = (self.status & 0b0011_1111) | (data & 0b1100_0000);
+        self.set_zero(result == 0);
+        Ok(())
     }
     // EOR: "Exclusive OR" Memory with Accumulator
-    fn eor(&mut self, mode: &AddressingMode) {
-        let data: u8 = self.mem_read(self.get_operand_address(mode) as u16);
+    fn eor(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
         self.register_a = self.register_a ^ data;
         self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
     }
     // ORA: "OR" Memory with Accumulator
-    fn ora(&mut self, mode: &AddressingMode) {
-        let data: u8 = self.mem_read(self.get_operand_address(mode) as u16);
+    fn ora(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
         self.register_a = self.register_a | data;
         self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
     }
     // ADC: Add Memory to Accumulator with Carry
-    fn adc(&mut self, mode: &AddressingMode) {
-        let data: u8 = self.mem_read(self.get_operand_address(mode));
-        self.actual_adc(data);
+    fn adc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.add_with_carry(data);
+        Ok(())
+    }
+    fn add_with_carry(&mut self, data: u8) {
+        if self.decimal_mode() {
+            self.decimal_adc(data);
+        } else {
+            self.actual_adc(data);
+        }
+    }
+    fn subtract_with_borrow(&mut self, data: u8) {
+        if self.decimal_mode() {
+            self.decimal_sbc(data);
+        } else {
+            // A - M - (1 - C) is the same as A + !M + C
+            self.actual_adc(!data);
+        }
+    }
+    fn decimal_mode(&self) -> bool {
+        self.variant != Variant::Ricoh2A03 && self.status & 0b0000_1000 != 0
+    }
+    // The 65C02 spends an extra cycle in decimal mode to make N and Z match the BCD result
+    fn fix_decimal_flags(&mut self) {
+        if self.variant == Variant::Wdc65C02 {
+            self.update_zero_and_negative_flags(self.register_a);
+            self.extra_cycles += 1;
+        }
+    }
+    // BCD addition as the NMOS 6502 does it. Only C is a real decimal carry: Z comes from the
+    // binary sum, while N and V come from the sum after the low digit has been adjusted but
+    // before the high one has.
+    fn decimal_adc(&mut self, data: u8) {
+        let a: u8 = self.register_a;
+        let carry: u8 = self.status & 0b0000_0001;
+        let mut low: u16 = (a & 0x0f) as u16 + (data & 0x0f) as u16 + carry as u16;
+        if low > 0x09 {
+            low = ((low + 0x06) & 0x0f) + 0x10;
+        }
+        let mut sum: u16 = (a & 0xf0) as u16 + (data & 0xf0) as u16 + low;
+        // Same sum, with the high digits taken as signed
+        let signed_sum: i16 = (a & 0xf0) as i8 as i16 + (data & 0xf0) as i8 as i16 + low as i16;
+
+        self.update_zero_and_negative_flags(a.wrapping_add(data).wrapping_add(carry));
+        if sum & 0x80 != 0 {
+            self.status |= 0b1000_0000;
+        } else {
+            self.status &= 0b0111_1111;
+        }
+        if !(-128..=127).contains(&signed_sum) {
+            self.status |= 0b0100_0000;
+        } else {
+            self.status &= 0b1011_1111;
+        }
+        if sum > 0x9f {
+            sum += 0x60;
+        }
+        self.set_carry(sum > 0xff);
+        self.register_a = sum as u8;
+        self.fix_decimal_flags();
+    }
+    // BCD subtraction on the NMOS 6502. All the flags are the ones binary SBC would give.
+    fn decimal_sbc(&mut self, data: u8) {
+        let a: u8 = self.register_a;
+        let carry: u8 = self.status & 0b0000_0001;
+        self.actual_adc(!data);
+
+        let mut low: i16 = (a & 0x0f) as i16 - (data & 0x0f) as i16 + carry as i16 - 1;
+        if low < 0 {
+            low = ((low - 0x06) & 0x0f) - 0x10;
+        }
+        let mut result: i16 = (a & 0xf0) as i16 - (data & 0xf0) as i16 + low;
+        if result < 0 {
+            result -= 0x60;
+        }
+        self.register_a = result as u8;
+        self.fix_decimal_flags();
     }
     fn actual_adc(&mut self, to_add: u8){
         let mut u16_result: u16 = self.register_a as u16 + to_add as u16;
@@ -469,9 +984,7 @@ impl CPU {
             u16_result += 1;
         }
         
-        if u16_result > 255 {
-            self.status = self.status | 0b000_0001;
-        }
+        self.set_carry(u16_result > 255);
         let u8_result: u8 = u16_result as u8;
         
         // data ^ u8_result: do data and u8_result have the same sign
@@ -488,49 +1001,47 @@ impl CPU {
         self.update_zero_and_negative_flags(self.register_a);
     }
     // CMP: Compare Memory and Accumulator
-    fn cmp(&mut self, mode: &AddressingMode) {
-        let mem_data: u8 = self.mem_read(self.get_operand_address(mode));
-        
-        if mem_data <= self.register_a {
-            self.status = self.status | 0b0000_0001;
-        } else {
-            self.status = self.status & 0b1111_1110;
-        }
-        self.update_zero_and_negative_flags(self.register_a.wrapping_sub(mem_data));
+    fn cmp(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let mem_data: u8 = self.mem_read(addr);
+        self.compare(self.register_a, mem_data);
+        Ok(())
     }
     // CPX: Compare Index Register X To Memory
-    fn cpx(&mut self, mode: &AddressingMode) {
-        let mem_data: u8 = self.mem_read(self.get_operand_address(mode));
-        
-        if mem_data <= self.register_x {
-            self.status = self.status | 0b0000_0001;
-        } else {
-            self.status = self.status & 0b1111_1110;
-        }
-        self.update_zero_and_negative_flags(self.register_x.wrapping_sub(mem_data));
+    fn cpx(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let mem_data: u8 = self.mem_read(addr);
+        self.compare(self.register_x, mem_data);
+        Ok(())
     }
     // CPY: Compare Index Register Y to Memory
-    fn cpy(&mut self, mode: &AddressingMode) {
-        let mem_data: u8 = self.mem_read(self.get_operand_address(mode));
-        
-        if mem_data <= self.register_y {
-            self.status = self.status | 0b0000_0001;
-        } else {
-            self.status = self.status & 0b1111_1110;
-        }
-        self.update_zero_and_negative_flags(self.register_y.wrapping_sub(mem_data));
+    fn cpy(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let mem_data: u8 = self.mem_read(addr);
+        self.compare(self.register_y, mem_data);
+        Ok(())
+    }
+    // Sets C when register >= data, and Z/N from the difference
+    fn compare(&mut self, register: u8, data: u8) {
+        self.set_carry(data <= register);
+        self.update_zero_and_negative_flags(register.wrapping_sub(data));
     }
     // SBC: Subtract Memory from Accumulator with Borrow
-    fn sbc(&mut self, mode: &AddressingMode) {
-        let data: u8 = self.mem_read(self.get_operand_address(mode));
-        self.actual_adc((!data).wrapping_add(1));
+    fn sbc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.subtract_with_borrow(data);
+        Ok(())
     }
 
     // DEC: Decrement Memory by One
-    fn dec(&mut self, mode: &AddressingMode){
-        let addr: u16 = self.get_operand_address(mode);
-        self.mem_write_u16(addr, self.mem_read_u16(addr).wrapping_sub(1));
-        self.update_zero_and_negative_flags(self.mem_read_u16(addr) as u8);
+    fn dec(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let original: u8 = self.mem_read(addr);
+        let data: u8 = original.wrapping_sub(1);
+        self.write_back(addr, original, data);
+        self.update_zero_and_negative_flags(data);
+        Ok(())
     }
     // DEX: Decrement Register X by One
     fn dex(&mut self){
@@ -576,67 +1087,58 @@ impl CPU {
     // RTI: Return From Interrupt
     fn rti(&mut self) {
         self.status = self.stack_pop();
+        self.status = self.status | 0b0010_0000; // Set Break2
+        self.status = self.status & 0b1110_1111; // Unset Break
         self.program_counter = self.stack_pop_u16();
     }
     // RTS: Return From Subroutine
     fn rts(&mut self) {
         self.program_counter = self.stack_pop_u16().wrapping_add(1);
     }
+    // Relative branch: one extra cycle when taken, another if the target is on a different page
+    fn branch(&mut self, condition: bool) {
+        if condition {
+            let jump: i8 = self.mem_read(self.program_counter) as i8;
+            let next: u16 = self.program_counter.wrapping_add(1);
+            let target: u16 = next.wrapping_add(jump as u16);
+            self.extra_cycles += 1;
+            if page_crossed(next, target) {
+                self.extra_cycles += 1;
+            }
+            self.program_counter = target;
+        }
+    }
     // BCC: Branch on Carry Clear
     fn bcc(&mut self) {
-        if self.status & 0b0000_0001 == 0 {
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b0000_0001 == 0);
     }
     // BCS: Branch on Carry Set
     fn bcs(&mut self) {
-        if self.status & 0b0000_0001 != 0 {
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b0000_0001 != 0);
     }
     // BEQ: Branch on Result Zero
     fn beq(&mut self) {
-        if self.status & 0b0000_0010 != 0 {
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b0000_0010 != 0);
     }
     // BMI: Branch on Result Minus
     fn bmi(&mut self) {
-        if self.status & 0b1000_0000 != 0 {    
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;    
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b1000_0000 != 0);
     }
     // BNE: Branch on Result Not Zero
     fn bne(&mut self) {
-        if self.status & 0b0000_0010 == 0 {    
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;    
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b0000_0010 == 0);
     }
     // BPL: Branch on Result Plus
     fn bpl(&mut self) {
-        if self.status & 0b1000_0000 == 0 {    
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;    
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b1000_0000 == 0);
     }
     // BVC: Branch on Overflow Clear
     fn bvc(&mut self) {
-        if self.status & 0b0100_0000 == 0 {    
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;    
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b0100_0000 == 0);
     }
     // BVS: Branch on Overflow Set
     fn bvs(&mut self) {
-        if self.status & 0b0100_0000 != 0 {    
-            let jump: i8 = self.mem_read_u16(self.program_counter) as i8;    
-            self.program_counter = self.program_counter.wrapping_add(1).wrapping_add(jump as u16);
-        }
+        self.branch(self.status & 0b0100_0000 != 0);
     }
     // CLC: Clear Carry Flag
     fn clc(&mut self) {
@@ -667,13 +1169,313 @@ impl CPU {
         self.status = self.status | 0b0000_0100;
     }
 
+    // Unofficial opcodes. Most are two official instructions glued together by the decoder.
+    // NOP variants that take an operand still perform the read
+    fn nop_read(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        self.mem_read(addr);
+        Ok(())
+    }
+    // LAX: LDA + LDX
+    fn lax(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.register_a = data;
+        self.register_x = data;
+        self.update_zero_and_negative_flags(data);
+        Ok(())
+    }
+    // SAX: Store A AND X
+    fn sax(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        self.mem_write(addr, self.register_a & self.register_x);
+        Ok(())
+    }
+    // SLO: ASL + ORA
+    fn slo(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let data: u8 = self.read_modify_write(mode, Self::shift_left)?;
+        self.register_a = self.register_a | data;
+        self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
+    }
+    // RLA: ROL + AND
+    fn rla(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let data: u8 = self.read_modify_write(mode, Self::rotate_left)?;
+        self.register_a = self.register_a & data;
+        self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
+    }
+    // SRE: LSR + EOR
+    fn sre(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let data: u8 = self.read_modify_write(mode, Self::shift_right)?;
+        self.register_a = self.register_a ^ data;
+        self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
+    }
+    // RRA: ROR + ADC
+    fn rra(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let data: u8 = self.read_modify_write(mode, Self::rotate_right)?;
+        self.add_with_carry(data);
+        Ok(())
+    }
+    // DCP: DEC + CMP
+    fn dcp(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let original: u8 = self.mem_read(addr);
+        let data: u8 = original.wrapping_sub(1);
+        self.write_back(addr, original, data);
+        self.compare(self.register_a, data);
+        Ok(())
+    }
+    // ISB: INC + SBC
+    fn isb(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let original: u8 = self.mem_read(addr);
+        let data: u8 = original.wrapping_add(1);
+        self.write_back(addr, original, data);
+        self.subtract_with_borrow(data);
+        Ok(())
+    }
+    // ANC: AND #imm, then copy N into C
+    fn anc(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.and(mode)?;
+        self.set_carry(self.register_a & 0b1000_0000 != 0);
+        Ok(())
+    }
+    // ALR: AND #imm + LSR A
+    fn alr(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.and(mode)?;
+        self.lsr(&AddressingMode::NoneAddressing)?;
+        Ok(())
+    }
+    // ARR: AND #imm + ROR A, with C from bit 6 and V from bit 6 XOR bit 5
+    fn arr(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.and(mode)?;
+        self.ror(&AddressingMode::NoneAddressing)?;
+        let result: u8 = self.register_a;
+        self.set_carry(result & 0b0100_0000 != 0);
+        if (result >> 6 ^ result >> 5) & 1 != 0 {
+            self.status = self.status | 0b0100_0000;
+        } else {
+            self.status = self.status & 0b1011_1111;
+        }
+        Ok(())
+    }
+    // AXS: X = (A AND X) - #imm, setting flags like CMP
+    fn axs(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        let and: u8 = self.register_a & self.register_x;
+        self.compare(and, data);
+        self.register_x = and.wrapping_sub(data);
+        Ok(())
+    }
+    // XAA (ANE) and LXA (LAX #imm) mix in whatever the bus happens to hold; $EE is the value
+    // most chips settle on
+    fn xaa(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.register_a = (self.register_a | UNSTABLE_MAGIC) & self.register_x & data;
+        self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
+    }
+    fn lxa(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.register_a = (self.register_a | UNSTABLE_MAGIC) & data;
+        self.register_x = self.register_a;
+        self.update_zero_and_negative_flags(self.register_a);
+        Ok(())
+    }
+    // LAS: A = X = SP = memory AND SP
+    fn las(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.read_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr) & self.stack_ptr;
+        self.register_a = data;
+        self.register_x = data;
+        self.stack_ptr = data;
+        self.update_zero_and_negative_flags(data);
+        Ok(())
+    }
+    // TAS: SP = A AND X, then store like AHX
+    fn tas(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        self.stack_ptr = self.register_a & self.register_x;
+        self.store_and_high(mode, self.stack_ptr)
+    }
+    // AHX, SHX, SHY, TAS store the register ANDed with the high byte of the base address + 1.
+    // When indexing crosses a page, that value also replaces the high byte of the address.
+    fn store_and_high(&mut self, mode: &AddressingMode, data: u8) -> Result<(), CpuError> {
+        let base: u16 = match mode {
+            AddressingMode::Indirect_Y => {
+                let pos: u8 = self.mem_read(self.program_counter);
+                let lo: u8 = self.mem_read(pos as u16);
+                let hi: u8 = self.mem_read(pos.wrapping_add(1) as u16);
+                (hi as u16) << 8 | (lo as u16)
+            }
+            _ => self.mem_read_u16(self.program_counter),
+        };
+        let (addr, page_crossed) = self.operand_address(mode)?;
+        let value: u8 = data & ((base >> 8) as u8).wrapping_add(1);
+        let addr: u16 = if page_crossed {
+            (value as u16) << 8 | (addr & 0x00FF)
+        } else {
+            addr
+        };
+        self.mem_write(addr, value);
+        Ok(())
+    }
+
+    // 65C02 opcodes that decode differently from the NMOS chip. Returns false for the ones
+    // that behave the same, which the shared decoder then handles.
+    fn execute_65c02(&mut self, opcode: u8, mode: &AddressingMode) -> Result<bool, CpuError> {
+        match opcode {
+            0x80 => self.branch(true),                                               // BRA
+            0xda => self.stack_push(self.register_x),                                // PHX
+            0x5a => self.stack_push(self.register_y),                                // PHY
+            0xfa => {                                                                // PLX
+                self.register_x = self.stack_pop();
+                self.update_zero_and_negative_flags(self.register_x);
+            }
+            0x7a => {                                                                // PLY
+                self.register_y = self.stack_pop();
+                self.update_zero_and_negative_flags(self.register_y);
+            }
+            0x1a => {                                                                // INC A
+                self.register_a = self.register_a.wrapping_add(1);
+                self.update_zero_and_negative_flags(self.register_a);
+            }
+            0x3a => {                                                                // DEC A
+                self.register_a = self.register_a.wrapping_sub(1);
+                self.update_zero_and_negative_flags(self.register_a);
+            }
+            0x64 | 0x74 | 0x9c | 0x9e => self.stz(mode)?,                            // STZ
+            0x04 | 0x0c => self.tsb(mode)?,                                          // TSB
+            0x14 | 0x1c => self.trb(mode)?,                                          // TRB
+            0x89 => self.bit_immediate(),                                            // BIT #imm
+            0x34 | 0x3c => self.bit(mode)?,                                          // BIT
+            0x12 => self.ora(mode)?,                                                 // ORA (zp)
+            0x32 => self.and(mode)?,                                                 // AND (zp)
+            0x52 => self.eor(mode)?,                                                 // EOR (zp)
+            0x72 => self.adc(mode)?,                                                 // ADC (zp)
+            0x92 => self.sta(mode)?,                                                 // STA (zp)
+            0xb2 => self.lda(mode)?,                                                 // LDA (zp)
+            0xd2 => self.cmp(mode)?,                                                 // CMP (zp)
+            0xf2 => self.sbc(mode)?,                                                 // SBC (zp)
+            0x6c => {                                                                // JMP (abs)
+                // The pointer's high byte comes from the next page, no more wrapping bug
+                let addr: u16 = self.mem_read_u16(self.program_counter);
+                self.program_counter = self.mem_read_u16(addr);
+            }
+            0x7c => {                                                                // JMP (abs,X)
+                let base: u16 = self.mem_read_u16(self.program_counter);
+                self.program_counter = self.mem_read_u16(base.wrapping_add(self.register_x as u16));
+            }
+            0x1e | 0x3e | 0x5e | 0x7e => {
+                // ASL, ROL, LSR, ROR abs,X only take the 7th cycle when indexing crosses a page.
+                // Otherwise they're the NMOS instructions.
+                let pc: u16 = self.program_counter;
+                let base: u16 = (self.bus.peek(pc.wrapping_add(1)) as u16) << 8 | self.bus.peek(pc) as u16;
+                if page_crossed(base, base.wrapping_add(self.register_x as u16)) {
+                    self.extra_cycles += 1;
+                }
+                return Ok(false);
+            }
+            0x0f | 0x1f | 0x2f | 0x3f | 0x4f | 0x5f | 0x6f | 0x7f                     // BBR0-7
+            | 0x8f | 0x9f | 0xaf | 0xbf | 0xcf | 0xdf | 0xef | 0xff => {              // BBS0-7
+                self.branch_on_bit(opcode)
+            }
+            0x07 | 0x17 | 0x27 | 0x37 | 0x47 | 0x57 | 0x67 | 0x77                     // RMB0-7
+            | 0x87 | 0x97 | 0xa7 | 0xb7 | 0xc7 | 0xd7 | 0xe7 | 0xf7 => {              // SMB0-7
+                self.change_bit(mode, opcode)?
+            }
+            0xcb => self.waiting = true,                                             // WAI
+            0xdb => {                                                                // STP
+                self.stopped = true;
+                self.program_counter = self.program_counter.wrapping_sub(1);
+                return Err(CpuError::Stopped {
+                    pc: self.program_counter,
+                });
+            }
+            0x02 | 0x22 | 0x42 | 0x62 | 0x82 | 0xc2 | 0xe2 | 0x44 | 0x54 | 0xd4 | 0xf4 | 0xdc
+            | 0xfc => self.nop_read(mode)?,                                          // NOP
+            // Everything else in the $x3 and $xB columns, plus $5C, is a NOP that reads nothing
+            _ if opcode & 0x07 == 0x03 || opcode == 0x5c => {}                       // NOP
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    // STZ: Store Zero in Memory
+    fn stz(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        self.mem_write(addr, 0);
+        Ok(())
+    }
+    // TSB: Test and Set Bits. Z is set when A and the memory have no bits in common.
+    fn tsb(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.set_zero(data & self.register_a == 0);
+        self.mem_write(addr, data | self.register_a);
+        Ok(())
+    }
+    // TRB: Test and Reset Bits
+    fn trb(&mut self, mode: &AddressingMode) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        self.set_zero(data & self.register_a == 0);
+        self.mem_write(addr, data & !self.register_a);
+        Ok(())
+    }
+    // BIT #imm only touches Z; there's no memory for N and V to come from
+    fn bit_immediate(&mut self) {
+        let data: u8 = self.mem_read(self.program_counter);
+        self.set_zero(data & self.register_a == 0);
+    }
+    // BBRn/BBSn: branch if bit n of a zero page byte is reset/set. Bits 4-6 of the opcode
+    // pick the bit and bit 7 whether it has to be set. The offset is the third byte.
+    fn branch_on_bit(&mut self, opcode: u8) {
+        let addr: u8 = self.mem_read(self.program_counter);
+        let data: u8 = self.mem_read(addr as u16);
+        let bit_set: bool = data >> ((opcode >> 4) & 0x07) & 1 != 0;
+        let condition: bool = bit_set == (opcode & 0x80 != 0);
+        self.program_counter = self.program_counter.wrapping_add(1);
+        self.branch(condition);
+        if !condition {
+            self.program_counter = self.program_counter.wrapping_add(1);
+        }
+    }
+    // RMBn/SMBn: reset/set bit n of a zero page byte, picked the same way as for BBRn/BBSn
+    fn change_bit(&mut self, mode: &AddressingMode, opcode: u8) -> Result<(), CpuError> {
+        let addr: u16 = self.get_operand_address(mode)?;
+        let data: u8 = self.mem_read(addr);
+        let mask: u8 = 1 << ((opcode >> 4) & 0x07);
+        let result: u8 = if opcode & 0x80 != 0 { data | mask } else { data & !mask };
+        self.mem_write(addr, result);
+        Ok(())
+    }
+
+    fn set_carry(&mut self, carry: bool) {
+        if carry {
+            self.status = self.status | 0b0000_0001;
+        } else {
+            self.status = self.status & 0b1111_1110;
+        }
+    }
+    fn set_zero(&mut self, zero: bool) {
+        if zero {
+            self.status |= 0b0000_0010;
+        } else {
+            self.status &= 0b1111_1101;
+        }
+    }
+
     fn update_zero_and_negative_flags(&mut self, result: u8) {
         // Set flags depending on Accumulator value
         // Check if Accumulator is 0
         if result == 0 {
             self.status = self.status | 0b0000_0010; // Set Zero
         } else {
-            self.status = self.status & 0b1111_1011; // Unset Zero
+            self.status = self.status & 0b1111_1101; // Unset Zero
         }
         // Check if Accumulator is negative (negative bit is set)
         if result & 0b1000_0000 != 0 {
@@ -693,13 +1495,13 @@ mod test {
     fn test_lda_from_memory() {
         let mut cpu: CPU = CPU::new();
         cpu.mem_write(0x10, 0x55);
-        cpu.load_and_run(vec![0xa5, 0x10, 0x00]);
+        cpu.load_and_run(vec![0xa5, 0x10, 0x00]).unwrap();
         assert_eq!(cpu.register_a, 0x55);
     }
     #[test]
     fn test_0xa9_lda_immediate_load() {
         let mut cpu: CPU = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0x05, 0x00]); // LDA 0x05 BRK
+        cpu.load_and_run(vec![0xa9, 0x05, 0x00]).unwrap(); // LDA 0x05 BRK
         assert_eq!(cpu.register_a, 0x05); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -707,10 +1509,10 @@ mod test {
     #[test]
     fn test_0xad_lda_absolute_load() {
         let mut cpu: CPU = CPU::new();
-        cpu.memory[0x2805] = 22;
-        cpu.load(vec![0xad, 0x05, 0x28, 0x00]); // LDA Absolute 0x0005 BRK
+        cpu.bus.memory[0x2805] = 22;
+        cpu.load(vec![0xad, 0x05, 0x28, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, 22); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -720,9 +1522,9 @@ mod test {
         let mut cpu: CPU = CPU::new();
         cpu.register_x = 1;
         cpu.mem_write((0x2805 as u16).wrapping_add(cpu.register_x as u16), 0x16);
-        cpu.load(vec![0xbd, 0x05, 0x28, 0x00]); // LDA Absolute 0x0005 BRK
+        cpu.load(vec![0xbd, 0x05, 0x28, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, 0x16); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -732,9 +1534,9 @@ mod test {
         let mut cpu: CPU = CPU::new();
         cpu.register_y = 1;
         cpu.mem_write((0x2805 as u16).wrapping_add(cpu.register_y as u16), 0x16);
-        cpu.load(vec![0xb9, 0x05, 0x28, 0x00]); // LDA Absolute 0x0005 BRK
+        cpu.load(vec![0xb9, 0x05, 0x28, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, 0x16); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -743,7 +1545,7 @@ mod test {
     fn test_0xa5_lda_zero_page_load() {
         let mut cpu: CPU = CPU::new();
         cpu.mem_write(0x05, 0x16);
-        cpu.load_and_run(vec![0xa5, 0x05, 0x00]); // LDA Absolute 0x0005 BRK
+        cpu.load_and_run(vec![0xa5, 0x05, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
 
         assert_eq!(cpu.register_a, 0x16); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
@@ -754,9 +1556,9 @@ mod test {
         let mut cpu: CPU = CPU::new();
         cpu.register_x = 1;
         cpu.mem_write((0x05 as u8).wrapping_add(cpu.register_x) as u16, 0x16);
-        cpu.load(vec![0xb5, 0x05, 0x00]); // LDA Absolute 0x0005 BRK
+        cpu.load(vec![0xb5, 0x05, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, 0x16); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -766,10 +1568,11 @@ mod test {
         let mut cpu: CPU = CPU::new();
         cpu.register_x = 1;
 
-        cpu.load(vec![0xa1, 0x05, 0x00]); // LDA Absolute 0x0005 BRK
-        cpu.mem_write(cpu.get_operand_address(&AddressingMode::Indirect_X), 0x16);
+        cpu.load(vec![0xa1, 0x05, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
+        let addr: u16 = cpu.get_operand_address(&AddressingMode::Indirect_X).unwrap();
+        cpu.mem_write(addr, 0x16);
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, 0x16); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -778,10 +1581,11 @@ mod test {
     fn test_0xb1_lda_zero_page_y_indirect_load() {
         let mut cpu: CPU = CPU::new();
         cpu.register_y = 1;
-        cpu.load(vec![0xb1, 0x05, 0x00]); // LDA Absolute 0x0005 BRK
-        cpu.mem_write(cpu.get_operand_address(&AddressingMode::Indirect_Y), 0x16);
+        cpu.load(vec![0xb1, 0x05, 0x00]).unwrap(); // LDA Absolute 0x0005 BRK
+        let addr: u16 = cpu.get_operand_address(&AddressingMode::Indirect_Y).unwrap();
+        cpu.mem_write(addr, 0x16);
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, 0x16); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -789,14 +1593,14 @@ mod test {
     #[test]
     fn test_0xa9_lda_zero_flag() {
         let mut cpu: CPU = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0x00, 0x00]); // LDA 0X00 BRK
+        cpu.load_and_run(vec![0xa9, 0x00, 0x00]).unwrap(); // LDA 0X00 BRK
         assert_eq!(cpu.register_a, 0x00); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0b0000_0010); // Zero flag set
     }
     #[test]
     fn test_0xa9_lda_negative_flag() {
         let mut cpu: CPU = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0b1000_0001, 0x00]); // LDA 0X41 BRK
+        cpu.load_and_run(vec![0xa9, 0b1000_0001, 0x00]).unwrap(); // LDA 0X41 BRK
         assert_eq!(cpu.register_a, 0b1000_0001); // Value loaded onto Accumulator
         assert!(cpu.status & 0b1000_0000 == 0b1000_0000); // Negative flag set
     }
@@ -804,9 +1608,9 @@ mod test {
     // Test for LDX:
     fn test_0xa2_ldx_immediate_load() {
         let mut cpu: CPU = CPU::new();
-        cpu.load(vec![0xa2, 0x05, 0x00]); // LDA 0x05 BRK
+        cpu.load(vec![0xa2, 0x05, 0x00]).unwrap(); // LDA 0x05 BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_x, 0x05); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -817,9 +1621,9 @@ mod test {
     #[test]
     fn test_0xa2_ldy_immediate_load() {
         let mut cpu: CPU = CPU::new();
-        cpu.load(vec![0xa2, 0x05, 0x00]); // LDA 0x05 BRK
+        cpu.load(vec![0xa2, 0x05, 0x00]).unwrap(); // LDA 0x05 BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_x, 0x05); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 == 0); // Negative flag not set
@@ -828,9 +1632,9 @@ mod test {
     fn test_0xaa_tax_move_a_to_x() {
         let mut cpu: CPU = CPU::new();
         cpu.register_a = 10;
-        cpu.load(vec![0xAA, 0x00]); // TAX BRK
+        cpu.load(vec![0xAA, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 10); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
@@ -840,9 +1644,9 @@ mod test {
     fn test_0xaa_tax_move_a_to_x_negative() {
         let mut cpu: CPU = CPU::new();
         cpu.register_a = 0b1000_0001;
-        cpu.load(vec![0xaa, 0x00]); // TAX BRK
+        cpu.load(vec![0xaa, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_x, 0b1000_0001); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
         assert!(cpu.status & 0b1000_0000 != 0); // Negative flag set
@@ -851,9 +1655,9 @@ mod test {
     fn test_0xaa_tax_move_a_to_x_zero() {
         let mut cpu: CPU = CPU::new();
         cpu.register_a = 0;
-        cpu.load(vec![0xAA, 0x00]); // TAX BRK
+        cpu.load(vec![0xAA, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_x, 0); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 != 0); // Zero flag not set
     }
@@ -861,9 +1665,9 @@ mod test {
     fn test_0xa8_tay_move_a_to_y() {
         let mut cpu: CPU = CPU::new();
         cpu.register_a = 10;
-        cpu.load(vec![0xa8, 0x00]); // TAX BRK
+        cpu.load(vec![0xa8, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_y, 10); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
@@ -873,9 +1677,9 @@ mod test {
     fn test_0xba_tsx_move_stack_ptr_to_x() {
         let mut cpu: CPU = CPU::new();
         cpu.stack_ptr = 10;
-        cpu.load(vec![0xba, 0x00]); // TAX BRK
+        cpu.load(vec![0xba, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 10); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
@@ -885,9 +1689,9 @@ mod test {
     fn test_0x8a_txa_move_x_to_a() {
         let mut cpu: CPU = CPU::new();
         cpu.register_x = 10;
-        cpu.load(vec![0x8a, 0x00]); // TAX BRK
+        cpu.load(vec![0x8a, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, 10); // Value loaded onto Accumulator
         assert!(cpu.status & 0b0000_0010 == 0); // Zero flag not set
@@ -897,57 +1701,133 @@ mod test {
     fn test_0x9a_txs_move_x_to_stack_ptr() {
         let mut cpu: CPU = CPU::new();
         cpu.register_x = 10;
-        cpu.load(vec![0x9a, 0x00]); // TAX BRK
+        cpu.load(vec![0x9a, 0x00]).unwrap(); // TAX BRK
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.stack_ptr, 10); // Value loaded onto Accumulator
     }
     #[test]
     fn test_5_ops_working_together() {
         let mut cpu: CPU = CPU::new();
-        cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]);
+        cpu.load_and_run(vec![0xa9, 0xc0, 0xaa, 0xe8, 0x00]).unwrap();
         assert_eq!(cpu.register_x, 0xc1);
     }
     #[test]
+    fn test_assembled_program() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load_and_run(crate::asm!(
+            "TOTAL = $10",
+            "        LDA #0",
+            "        STA TOTAL",
+            "        LDX #10",
+            "loop:   JSR add_x",
+            "        DEX",
+            "        BNE loop",
+            "        BRK",
+            "add_x:  TXA",
+            "        CLC",
+            "        ADC TOTAL",
+            "        STA TOTAL",
+            "        RTS",
+        ))
+        .unwrap();
+        assert_eq!(cpu.mem_read(0x10), 55); // 10 + 9 + ... + 1
+    }
+    #[test]
     fn test_0xee_inc_absolute() {
         let mut cpu: CPU = CPU::new();
         let program: Vec<u8> = vec![0xee, 0x12, 0x34, 0x00];
 
         cpu.mem_write_u16(0x3412, 10);
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0x3412), 10 + 1);
     }
     #[test]
+    fn test_load_at_leaves_the_reset_vector_alone() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load_at(vec![0xea, 0x00], 0x0600).unwrap();
+        assert_eq!(cpu.mem_read_u16(0xFFFC), 0x0000);
+        assert_eq!(cpu.mem_read(0x0600), 0xea);
+    }
+    #[test]
+    fn test_inc_dec_only_touch_one_byte() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0xff);
+        cpu.mem_write(0x11, 0x05);
+        cpu.mem_write(0x20, 0x00);
+        cpu.mem_write(0x21, 0x05);
+        cpu.load_and_run(vec![0xe6, 0x10, 0xc6, 0x20, 0x00]).unwrap(); // INC $10 DEC $20
+        assert_eq!(cpu.bus.peek(0x10), 0x00);
+        assert_eq!(cpu.bus.peek(0x11), 0x05);
+        assert_eq!(cpu.bus.peek(0x20), 0xff);
+        assert_eq!(cpu.bus.peek(0x21), 0x05);
+        assert!(cpu.status & 0b1000_0000 != 0); // N from the DEC result
+    }
+    // Remembers every write that goes past it
+    struct WriteLogBus {
+        ram: FlatRam,
+        writes: Vec<(u16, u8)>,
+    }
+    impl Bus for WriteLogBus {
+        fn mem_read(&mut self, addr: u16) -> u8 {
+            self.ram.mem_read(addr)
+        }
+        fn mem_write(&mut self, addr: u16, data: u8) {
+            self.writes.push((addr, data));
+            self.ram.mem_write(addr, data);
+        }
+        fn peek(&self, addr: u16) -> u8 {
+            self.ram.peek(addr)
+        }
+    }
+    #[test]
+    fn test_read_modify_write_writes_twice() {
+        for (variant, expected) in [
+            (Variant::Ricoh2A03, vec![(0x0010, 0x40), (0x0010, 0x41), (0x0010, 0x41), (0x0010, 0x82)]),
+            (Variant::Wdc65C02, vec![(0x0010, 0x41), (0x0010, 0x82)]),
+        ] {
+            let mut cpu: CPU<WriteLogBus> = CPU::with_bus(WriteLogBus {
+                ram: FlatRam::new(),
+                writes: Vec::new(),
+            });
+            cpu.variant = variant;
+            cpu.mem_write(0x10, 0x40);
+            cpu.load_and_run(vec![0xe6, 0x10, 0x06, 0x10, 0x00]).unwrap(); // INC $10 ASL $10
+            let writes: Vec<(u16, u8)> = cpu.bus.writes.into_iter().filter(|(addr, _)| *addr == 0x10).skip(1).collect();
+            assert_eq!(writes, expected, "{:?}", variant);
+        }
+    }
+    #[test]
     fn test_0xe8_inx_overflow() {
         let mut cpu: CPU = CPU::new();
         cpu.register_x = 0xff;
-        cpu.load(vec![0xe8, 0xe8, 0x00]);
+        cpu.load(vec![0xe8, 0xe8, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_x, 1);
     }
     #[test]
     fn test_0xc8_iny_overflow() {
         let mut cpu: CPU = CPU::new();
         cpu.register_y = 0xff;
-        cpu.load(vec![0xc8, 0xc8, 0x00]);
+        cpu.load(vec![0xc8, 0xc8, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_y, 1);
     }
     #[test]
     fn test_program_load() {
         let mut cpu: CPU = CPU::new();
         let program: Vec<u8> = vec![0xe8, 0xe8, 0xe8, 0xaa, 0x00];
-        cpu.load(program.clone());
+        cpu.load(program.clone()).unwrap();
         let mut i: u16 = 0;
         for data in program.iter() {
-            let data_in_memory: u8 = cpu.memory[(0x8000 + i) as usize];
+            let data_in_memory: u8 = cpu.bus.memory[(0x8000 + i) as usize];
             i += 1;
             assert_eq!(data_in_memory, *data);
         }
@@ -956,7 +1836,7 @@ mod test {
     fn test_mem_read() {
         let mut cpu: CPU = CPU::new();
         let program: Vec<u8> = vec![0xe8, 0xe8, 0xe8, 0xaa, 0x00];
-        cpu.load(program.clone());
+        cpu.load(program.clone()).unwrap();
         let mut i: u16 = 0;
         for data in program.iter() {
             let data_in_memory: u8 = cpu.mem_read(0x8000 + i);
@@ -965,10 +1845,16 @@ mod test {
         }
     }
     #[test]
+    fn test_with_bus_routes_memory_through_bus() {
+        let mut cpu: CPU<FlatRam> = CPU::with_bus(FlatRam::new());
+        cpu.load_and_run(vec![0xa9, 0x05, 0x85, 0x10, 0x00]).unwrap(); // LDA 0x05 STA 0x10 BRK
+        assert_eq!(cpu.bus.memory[0x10], 0x05);
+    }
+    #[test]
     fn test_mem_write() {
         let mut cpu: CPU = CPU::new();
         cpu.mem_write(0xe8, 0xe8);
-        assert_eq!(cpu.memory[0xe8], 0xe8);
+        assert_eq!(cpu.bus.memory[0xe8], 0xe8);
     }
     // Test for AND
     #[test]
@@ -976,9 +1862,9 @@ mod test {
         let mut cpu: CPU = CPU::new();
         cpu.register_a = 0b1111_0001;
         let and_result: u8 = cpu.register_a & 0b1010_1111;
-        cpu.load(vec![0x29, 0b1010_1111, 0x00]);
+        cpu.load(vec![0x29, 0b1010_1111, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, and_result);
         assert!(cpu.status & 0b1000_0000 != 0);
     }
@@ -988,9 +1874,9 @@ mod test {
         cpu.register_a = 0b1111_0001;
         cpu.mem_write(0xaa42, 0b1010_1111);
         let and_result: u8 = cpu.register_a & 0b1010_1111;
-        cpu.load(vec![0x2d, 0x42, 0xaa, 0x00]);
+        cpu.load(vec![0x2d, 0x42, 0xaa, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
         assert_eq!(cpu.register_a, and_result);
         assert!(cpu.status & 0b1000_0000 != 0);
     }
@@ -1000,9 +1886,9 @@ mod test {
         let program: Vec<u8> = vec![0x8d, 0x34, 0x12, 0x00];
 
         cpu.register_a = 10;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read(0x1234), cpu.register_a);
     }
@@ -1012,9 +1898,9 @@ mod test {
         let program: Vec<u8> = vec![0x8e, 0x34, 0x12, 0x00];
 
         cpu.register_x = 10;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read(0x1234), cpu.register_x);
     }
@@ -1024,9 +1910,9 @@ mod test {
         let program: Vec<u8> = vec![0x8c, 0x34, 0x12, 0x00];
 
         cpu.register_y = 10;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read(0x1234), cpu.register_y);
     }
@@ -1036,9 +1922,9 @@ mod test {
         let program: Vec<u8> = vec![0x48, 0x00];
 
         cpu.register_a = 10;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(
             cpu.mem_read(cpu.stack_start + cpu.stack_ptr.wrapping_add(1) as u16),
@@ -1052,9 +1938,9 @@ mod test {
 
         cpu.register_a = 10;
         cpu.stack_ptr = 0;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(
             cpu.mem_read(cpu.stack_start + cpu.stack_ptr.wrapping_add(1) as u16),
@@ -1067,14 +1953,34 @@ mod test {
         let program: Vec<u8> = vec![0x08, 0x00];
 
         cpu.status = 0b1100_0001;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(
             cpu.mem_read(cpu.stack_start + cpu.stack_ptr.wrapping_add(1) as u16),
             0b1111_0001
         );
+        // B is only set in the pushed copy
+        assert_eq!(cpu.status, 0b1100_0001);
+    }
+    #[test]
+    fn test_0x08_php_twice() {
+        let mut cpu: CPU = CPU::new();
+        let program: Vec<u8> = vec![0x08, 0x68, 0x08, 0x00]; // PHP PLA PHP
+
+        cpu.status = 0b0000_0001;
+        cpu.load(program).unwrap();
+        cpu.program_counter = cpu.mem_read_u16(0xFFFC);
+        cpu.run().unwrap();
+
+        // Only PLA's flags changed in between: B and bit 5 never stick in the status
+        assert_eq!(cpu.register_a, 0b0011_0001);
+        assert_eq!(cpu.status, 0b0000_0001);
+        assert_eq!(
+            cpu.mem_read(cpu.stack_start + cpu.stack_ptr.wrapping_add(1) as u16),
+            0b0011_0001
+        );
     }
     #[test]
     fn test_0x08_php_underflow() {
@@ -1083,9 +1989,9 @@ mod test {
 
         cpu.status = 0b1100_0001;
         cpu.stack_ptr = 0;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(
             cpu.mem_read(cpu.stack_start + cpu.stack_ptr.wrapping_add(1) as u16),
@@ -1102,9 +2008,9 @@ mod test {
         cpu.pha();
         cpu.register_a = 0;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, 10);
         assert_eq!(cpu.stack_ptr, 0);
@@ -1119,9 +2025,9 @@ mod test {
         cpu.php();
         cpu.status = 0;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b1110_0001);
         assert_eq!(cpu.stack_ptr, 0);
@@ -1133,9 +2039,9 @@ mod test {
 
         cpu.register_a = 0b1001_0101;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, 0b0010_1010);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1147,9 +2053,9 @@ mod test {
 
         cpu.mem_write_u16(0x2120, 0b1001_0101);
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0x2120), 0b0010_1010);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1161,9 +2067,9 @@ mod test {
 
         cpu.register_a = 0b1001_0101;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, 0b0100_1010);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1175,9 +2081,9 @@ mod test {
 
         cpu.mem_write_u16(0x2120, 0b1001_0101);
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0x2120), 0b0100_1010);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1189,9 +2095,9 @@ mod test {
 
         cpu.register_a = 0b1001_0101;
         cpu.status = 0b0000_0001;
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, 0b0010_1011);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1204,9 +2110,9 @@ mod test {
         cpu.mem_write_u16(0x2120, 0b1001_0101);
         cpu.status = 0b0000_0001;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0x2120), 0b0010_1011);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1219,9 +2125,9 @@ mod test {
         cpu.register_a = 0b1001_0101;
         cpu.status = 0b0000_0001;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, 0b1100_1010);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1234,9 +2140,9 @@ mod test {
         cpu.mem_write_u16(0x2120, 0b1001_0101);
         cpu.status = 0b0000_0001;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0x2120), 0b1100_1010);
         assert!(cpu.status & 0b0000_0001 != 0);
@@ -1249,9 +2155,9 @@ mod test {
         cpu.mem_write_u16(0xf800, 0b1001_0101);
         cpu.register_a = 0b0011_1001;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0xf800), 0b1001_0101);
         assert_eq!(cpu.register_a, 0b0011_1001);
@@ -1261,14 +2167,62 @@ mod test {
         assert!(cpu.status & 0b0100_0000 == ((cpu.mem_read_u16(0xf800) as u8) & 0b0100_0000));
     }
     #[test]
+    fn test_bit_clears_flags() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0b0000_0001);
+        // SEC (to check C survives), then BIT $10 with N, V and Z all set beforehand
+        cpu.load(vec![0x38, 0x24, 0x10, 0x00]).unwrap();
+        cpu.reset();
+        cpu.status = 0b1100_0010;
+        cpu.register_a = 0b0000_0001;
+        cpu.run().unwrap();
+        assert_eq!(cpu.status & 0b1100_0011, 0b0000_0001);
+    }
+    #[test]
+    fn test_nonzero_result_clears_only_zero() {
+        let mut cpu: CPU = CPU::new();
+        // LDA #$00 sets Z, then LDA #$01 has to clear it and leave I alone
+        cpu.load(vec![0xa9, 0x00, 0xa9, 0x01, 0x00]).unwrap();
+        cpu.reset();
+        cpu.status = 0b0000_0100;
+        cpu.run().unwrap();
+        assert_eq!(cpu.status & 0b0000_0110, 0b0000_0100);
+    }
+    #[test]
+    fn test_bit_absolute_copies_n_and_v() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x0200, 0b1100_0000);
+        cpu.mem_write(0x0201, 0b0000_0001);
+        // BIT $0200 with A = 0, then BIT $0201 with A = 1
+        cpu.load(vec![0x2c, 0x00, 0x02, 0x00]).unwrap();
+        cpu.reset();
+        cpu.run().unwrap();
+        assert_eq!(cpu.status & 0b1100_0010, 0b1100_0010);
+        cpu.load(vec![0xa9, 0x01, 0x2c, 0x01, 0x02, 0x00]).unwrap();
+        cpu.reset();
+        cpu.status = 0b1100_0010;
+        cpu.run().unwrap();
+        assert_eq!(cpu.status & 0b1100_0010, 0b0000_0000);
+    }
+    #[test]
+    fn test_zero_page_x_shifts() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x15, 0x01);
+        cpu.mem_write(0x16, 0x80);
+        // LDX #5  ASL $10,X  LSR $11,X
+        cpu.load_and_run(vec![0xa2, 0x05, 0x16, 0x10, 0x56, 0x11, 0x00]).unwrap();
+        assert_eq!(cpu.bus.peek(0x15), 0x02);
+        assert_eq!(cpu.bus.peek(0x16), 0x40);
+    }
+    #[test]
     fn test_0x49_eor_immediate() {
         let mut cpu: CPU = CPU::new();
         cpu.register_a = 0b1111_0001;
         let eor_result: u8 = cpu.register_a ^ 0b1010_1111;
         
-        cpu.load(vec![0x49, 0b1010_1111, 0x00]);
+        cpu.load(vec![0x49, 0b1010_1111, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, eor_result);
     }
@@ -1279,9 +2233,9 @@ mod test {
         cpu.mem_write(0x2a42, 0b1010_1111);
         let eor_result: u8 = cpu.register_a ^ 0b1010_1111;
 
-        cpu.load(vec![0x4d, 0x42, 0x2a, 0x00]);
+        cpu.load(vec![0x4d, 0x42, 0x2a, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, eor_result);
     }
@@ -1291,9 +2245,9 @@ mod test {
         cpu.register_a = 0b1111_0001;
         let or_result: u8 = cpu.register_a | 0b1010_1111;
         
-        cpu.load(vec![0x09, 0b1010_1111, 0x00]);
+        cpu.load(vec![0x09, 0b1010_1111, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, or_result);
     }
@@ -1304,9 +2258,9 @@ mod test {
         cpu.mem_write(0x2a42, 0b1010_1111);
         let or_result: u8 = cpu.register_a | 0b1010_1111;
 
-        cpu.load(vec![0x0d, 0x42, 0x2a, 0x00]);
+        cpu.load(vec![0x0d, 0x42, 0x2a, 0x00]).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xFFFC);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_a, or_result);
     }
@@ -1317,9 +2271,9 @@ mod test {
 
         cpu.mem_write_u16(0x3412, 10);
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.mem_read_u16(0x3412), 10 -1);
     }
@@ -1330,9 +2284,9 @@ mod test {
 
         cpu.register_x = 10;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 10 -1);
     }
@@ -1343,9 +2297,9 @@ mod test {
 
         cpu.register_y = 10;
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_y, 10 - 1);
     }
@@ -1356,9 +2310,9 @@ mod test {
         
         cpu.mem_write_u16(0x7957, 0x00);
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         // -1 since it moves +1 to get BRK instruction
         assert_eq!(cpu.program_counter - 1, 0x7957);
@@ -1373,9 +2327,9 @@ mod test {
         
         
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         // -1 since it moves +1 to get BRK instruction
         assert_eq!(cpu.program_counter - 1, 0x2222);        
@@ -1391,9 +2345,9 @@ mod test {
         cpu.mem_write(0x4080, 0x00);
         
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         // -1 since it moves +1 to get BRK instruction
         assert_eq!(cpu.program_counter - 1, 0x4080);        
@@ -1405,10 +2359,10 @@ mod test {
         
         cpu.mem_write(0x2120, 0xe8);
 
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
         let curr_pc: u16 = cpu.mem_read_u16(0xfffc) + 2;  
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
         assert_eq!(cpu.stack_pop_u16(), curr_pc);
@@ -1421,12 +2375,12 @@ mod test {
         cpu.stack_push_u16(0x8523); 
         cpu.stack_push(0b1100_0101);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.program_counter, 0x8523 + 1);
-        assert_eq!(cpu.status, 0b1100_0101);
+        assert_eq!(cpu.status, 0b1110_0101); // B is dropped, bit 5 always reads back set
     }
     #[test]
     fn test_0x60_rts(){
@@ -1435,9 +2389,9 @@ mod test {
 
         cpu.stack_push_u16(0x8523);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         // + 1 from RTS, +1 from reading next instruction
         assert_eq!(cpu.program_counter, 0x8523 + 1 + 1);
@@ -1450,9 +2404,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1464,9 +2418,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1478,9 +2432,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1492,9 +2446,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1506,9 +2460,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1520,9 +2474,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1534,9 +2488,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     }
@@ -1548,9 +2502,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.register_x, 1);
     } 
@@ -1562,9 +2516,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b1111_1110);
     } 
@@ -1576,9 +2530,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b1111_0111);
     } 
@@ -1590,9 +2544,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b1111_1011);
     } 
@@ -1604,9 +2558,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b1011_1111);
     } 
@@ -1618,9 +2572,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b0000_0001);
     }
@@ -1632,9 +2586,9 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b0000_1000);
     }  
@@ -1646,10 +2600,653 @@ mod test {
 
         cpu.mem_write(cpu.program_counter.wrapping_add(0x8047), 0xe8);
         
-        cpu.load(program);
+        cpu.load(program).unwrap();
         cpu.program_counter = cpu.mem_read_u16(0xfffc);
-        cpu.run();
+        cpu.run().unwrap();
 
         assert_eq!(cpu.status, 0b0000_0100);
     } 
+
+    // Bus that raises NMI once a given number of cycles have been ticked
+    struct InterruptTestBus {
+        ram: FlatRam,
+        cycles: u64,
+        nmi_at: Option<u64>,
+    }
+    impl Bus for InterruptTestBus {
+        fn mem_read(&mut self, addr: u16) -> u8 {
+            self.ram.mem_read(addr)
+        }
+        fn mem_write(&mut self, addr: u16, data: u8) {
+            self.ram.mem_write(addr, data);
+        }
+        fn peek(&self, addr: u16) -> u8 {
+            self.ram.peek(addr)
+        }
+        fn tick(&mut self, cycles: u8) {
+            self.cycles += cycles as u64;
+        }
+        fn nmi_line(&self) -> bool {
+            self.nmi_at.is_some_and(|at| self.cycles >= at)
+        }
+    }
+    // Runs until PC reaches `stop_at`, where a BRK ends the run
+    fn run_until_pc<B: Bus>(cpu: &mut CPU<B>, stop_at: u16) {
+        cpu.mem_write(stop_at, 0x00);
+        cpu.run_with_callback(|cpu| {
+            if cpu.program_counter == stop_at {
+                cpu.halt_on_brk = true;
+            }
+        }).unwrap();
+    }
+    #[test]
+    fn test_0x00_brk_interrupt() {
+        let mut cpu: CPU = CPU::new();
+        cpu.halt_on_brk = false;
+        cpu.load(vec![0xa9, 0x01, 0x00, 0xff, 0xe8]).unwrap(); // LDA #1 BRK (padding) INX
+        cpu.reset();
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        cpu.load_at(vec![0xa0, 0x07, 0x40], 0x9000).unwrap(); // LDY #7 RTI
+        cpu.mem_write_u16(0xFFFC, 0x8000);
+        run_until_pc(&mut cpu, 0x8005);
+
+        assert_eq!(cpu.register_y, 7);
+        assert_eq!(cpu.register_x, 1); // Returned past the padding byte
+        assert_eq!(cpu.stack_ptr, 0xff);
+        // Pushed PC+2 and status with B and bit 5 set
+        assert_eq!(cpu.bus.memory[0x01ff], 0x80);
+        assert_eq!(cpu.bus.memory[0x01fe], 0x04);
+        assert_eq!(cpu.bus.memory[0x01fd], 0b0011_0000);
+    }
+    #[test]
+    fn test_nmi_is_edge_triggered() {
+        let mut cpu: CPU = CPU::new();
+        cpu.halt_on_brk = false;
+        cpu.load(vec![0xea, 0xea, 0xea, 0xea]).unwrap(); // NOP x4
+        cpu.reset();
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.load_at(vec![0xe8, 0x40], 0x9000).unwrap(); // INX RTI
+        cpu.mem_write_u16(0xFFFC, 0x8000);
+        cpu.set_nmi_line(true);
+        cpu.sei(); // NMI ignores the I flag
+        run_until_pc(&mut cpu, 0x8004);
+
+        // Held high the whole time, but only the rising edge counts
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.bus.memory[0x01fd] & 0b0001_0000, 0); // No B flag
+    }
+    #[test]
+    fn test_irq_is_level_triggered_and_masked() {
+        let mut cpu: CPU = CPU::new();
+        cpu.halt_on_brk = false;
+        cpu.load(vec![0xea, 0x58, 0xea, 0xea]).unwrap(); // NOP CLI NOP NOP
+        cpu.reset();
+        cpu.sei();
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        // NOP, then acknowledge the IRQ by dropping the line from the test callback
+        cpu.load_at(vec![0xea, 0x40], 0x9000).unwrap();
+        cpu.mem_write_u16(0xFFFC, 0x8000);
+        cpu.set_irq_line(true);
+        let mut irq_count: u8 = 0;
+        let mut irq_return: u16 = 0;
+        cpu.mem_write(0x8004, 0x00);
+        cpu.run_with_callback(|cpu| {
+            if cpu.program_counter == 0x9001 {
+                irq_count += 1;
+                irq_return = cpu.mem_read_u16(0x01fe);
+                cpu.set_irq_line(false);
+            }
+            if cpu.program_counter == 0x8004 {
+                cpu.halt_on_brk = true;
+            }
+        }).unwrap();
+
+        assert_eq!(irq_count, 1);
+        // CLI's effect is delayed by one instruction: the NOP after it runs first
+        assert_eq!(irq_return, 0x8003);
+        assert_eq!(cpu.status & 0b0000_0100, 0); // RTI restored I
+    }
+    #[test]
+    fn test_nmi_hijacks_brk() {
+        let bus: InterruptTestBus = InterruptTestBus { ram: FlatRam::new(), cycles: 0, nmi_at: Some(3) };
+        let mut cpu: CPU<InterruptTestBus> = CPU::with_bus(bus);
+        cpu.halt_on_brk = false;
+        cpu.load(vec![0x00, 0xff, 0xea]).unwrap(); // BRK (padding) NOP
+        cpu.reset();
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.mem_write_u16(0xFFFE, 0xA000);
+        cpu.load_at(vec![0xe8, 0x40], 0x9000).unwrap(); // NMI: INX RTI
+        cpu.load_at(vec![0xc8, 0x40], 0xA000).unwrap(); // IRQ/BRK: INY RTI
+        cpu.mem_write_u16(0xFFFC, 0x8000);
+        run_until_pc(&mut cpu, 0x8003);
+
+        // NMI rose during the BRK sequence: the NMI handler runs once and BRK's handler never does
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.register_y, 0);
+        assert_eq!(cpu.bus.ram.memory[0x01fd] & 0b0001_0000, 0b0001_0000);
+    }
+
+    // Cycles taken by a program ending in BRK, not counting reset or the BRK itself
+    fn cycles_for(program: Vec<u8>, setup: fn(&mut CPU)) -> u64 {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(program).unwrap();
+        cpu.reset();
+        setup(&mut cpu);
+        cpu.run().unwrap();
+        cpu.cycles() - 7
+    }
+    #[test]
+    fn test_reset_takes_7_cycles() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0x00]).unwrap();
+        cpu.reset();
+        assert_eq!(cpu.cycles(), 7);
+    }
+    #[test]
+    fn test_base_cycle_counts() {
+        assert_eq!(cycles_for(vec![0xa9, 0x01, 0x00], |_| {}), 2); // LDA #
+        assert_eq!(cycles_for(vec![0xee, 0x00, 0x02, 0x00], |_| {}), 6); // INC abs
+        assert_eq!(cycles_for(vec![0x20, 0x04, 0x80, 0x00, 0x60], |_| {}), 12); // JSR RTS
+        assert_eq!(cycles_for(vec![0x48, 0x68, 0xea, 0x00], |_| {}), 9); // PHA PLA NOP
+    }
+    #[test]
+    fn test_page_cross_penalty_on_reads() {
+        // LDA $20F0,X
+        assert_eq!(cycles_for(vec![0xbd, 0xf0, 0x20, 0x00], |cpu| cpu.register_x = 0x0f), 4);
+        assert_eq!(cycles_for(vec![0xbd, 0xf0, 0x20, 0x00], |cpu| cpu.register_x = 0x10), 5);
+        // LDX $20F0,Y
+        assert_eq!(cycles_for(vec![0xbe, 0xf0, 0x20, 0x00], |cpu| cpu.register_y = 0x10), 5);
+        // LDA ($10),Y with $10 pointing at $20F0
+        let setup: fn(&mut CPU) = |cpu| {
+            cpu.mem_write_u16(0x10, 0x20f0);
+            cpu.register_y = 0x10;
+        };
+        assert_eq!(cycles_for(vec![0xb1, 0x10, 0x00], setup), 6);
+        // ($10,X) never pays the penalty
+        assert_eq!(cycles_for(vec![0xa1, 0x10, 0x00], |cpu| cpu.register_x = 0xff), 6);
+    }
+    #[test]
+    fn test_no_page_cross_penalty_on_writes() {
+        // STA $20F0,X and INC $20F0,X always take their full time
+        assert_eq!(cycles_for(vec![0x9d, 0xf0, 0x20, 0x00], |cpu| cpu.register_x = 0x01), 5);
+        assert_eq!(cycles_for(vec![0x9d, 0xf0, 0x20, 0x00], |cpu| cpu.register_x = 0x10), 5);
+        assert_eq!(cycles_for(vec![0xfe, 0xf0, 0x20, 0x00], |cpu| cpu.register_x = 0x10), 7);
+    }
+    #[test]
+    fn test_branch_cycles() {
+        // BNE +0, not taken (Z set) / taken (Z clear)
+        assert_eq!(cycles_for(vec![0xd0, 0x00, 0x00], |cpu| cpu.status = 0b0000_0010), 2);
+        assert_eq!(cycles_for(vec![0xd0, 0x00, 0x00], |_| {}), 3);
+        // Branching backwards from $8002 to $7FFF lands on a different page
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0xd0, 0xfd]).unwrap(); // BNE -3
+        cpu.mem_write(0x7fff, 0x00);
+        cpu.reset();
+        cpu.run().unwrap();
+        assert_eq!(cpu.program_counter, 0x8000);
+        assert_eq!(cpu.cycles() - 7, 4);
+    }
+    #[test]
+    fn test_interrupt_takes_7_cycles() {
+        let mut cpu: CPU = CPU::new();
+        cpu.halt_on_brk = false;
+        cpu.load(vec![0x00, 0xff]).unwrap();
+        cpu.reset();
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        run_until_pc(&mut cpu, 0x9000);
+        assert_eq!(cpu.cycles() - 7, 7);
+    }
+
+    #[test]
+    fn test_every_opcode_is_decoded() {
+        for opcode in 0..=0xff_u8 {
+            assert_eq!(OPCODES[opcode as usize].instruction, opcode, "misplaced opcode {:#04x}", opcode);
+        }
+        assert_eq!(OPCODES.iter().filter(|op| op.unofficial).count(), 105);
+        for opcode in 0..=0xff_u8 {
+            assert_eq!(OPCODES_65C02[opcode as usize].instruction, opcode, "misplaced opcode {:#04x}", opcode);
+        }
+    }
+    #[test]
+    fn test_0xe9_sbc_with_borrow() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load_and_run(vec![0x38, 0xa9, 0x05, 0xe9, 0x03, 0x00]).unwrap(); // SEC LDA #5 SBC #3
+        assert_eq!(cpu.register_a, 2);
+        assert!(cpu.status & 0b0000_0001 != 0); // No borrow
+        cpu.load_and_run(vec![0x18, 0xa9, 0x05, 0xe9, 0x05, 0x00]).unwrap(); // CLC LDA #5 SBC #5
+        assert_eq!(cpu.register_a, 0xff);
+        assert!(cpu.status & 0b0000_0001 == 0); // Borrowed
+    }
+    // SED, then SEC or CLC, LDA #a, then ADC or SBC #b
+    fn run_decimal(variant: Variant, carry: bool, a: u8, opcode: u8, b: u8) -> CPU {
+        let mut cpu: CPU = CPU::new();
+        cpu.variant = variant;
+        let set_carry: u8 = if carry { 0x38 } else { 0x18 };
+        cpu.load_and_run(vec![0xf8, set_carry, 0xa9, a, opcode, b, 0x00]).unwrap();
+        cpu
+    }
+    #[test]
+    fn test_2a03_ignores_decimal_flag() {
+        let cpu: CPU = run_decimal(Variant::Ricoh2A03, false, 0x09, 0x69, 0x01);
+        assert_eq!(cpu.register_a, 0x0a);
+        let cpu: CPU = run_decimal(Variant::Ricoh2A03, true, 0x10, 0xe9, 0x01);
+        assert_eq!(cpu.register_a, 0x0f);
+    }
+    #[test]
+    fn test_nmos_decimal_adc() {
+        let cpu: CPU = run_decimal(Variant::Nmos6502, false, 0x12, 0x69, 0x34);
+        assert_eq!(cpu.register_a, 0x46);
+        assert!(cpu.status & 0b0000_0001 == 0);
+        let cpu: CPU = run_decimal(Variant::Nmos6502, true, 0x58, 0x69, 0x46);
+        assert_eq!(cpu.register_a, 0x05);
+        assert!(cpu.status & 0b0000_0001 != 0);
+        // 81 + 92 = 173: decimal carry, and V from the signed intermediate -128 + -112 + 3
+        let cpu: CPU = run_decimal(Variant::Nmos6502, false, 0x81, 0x69, 0x92);
+        assert_eq!(cpu.register_a, 0x73);
+        assert_eq!(cpu.status & 0b1100_0011, 0b0100_0001);
+    }
+    #[test]
+    fn test_nmos_decimal_adc_flag_quirks() {
+        // 99 + 01 = 00 with carry, but Z follows the binary sum $9A and N the intermediate $A0
+        let cpu: CPU = run_decimal(Variant::Nmos6502, false, 0x99, 0x69, 0x01);
+        assert_eq!(cpu.register_a, 0x00);
+        assert_eq!(cpu.status & 0b1100_0011, 0b1000_0001);
+    }
+    #[test]
+    fn test_nmos_decimal_sbc() {
+        let cpu: CPU = run_decimal(Variant::Nmos6502, true, 0x46, 0xe9, 0x12);
+        assert_eq!(cpu.register_a, 0x34);
+        assert!(cpu.status & 0b0000_0001 != 0);
+        let cpu: CPU = run_decimal(Variant::Nmos6502, true, 0x40, 0xe9, 0x13);
+        assert_eq!(cpu.register_a, 0x27);
+        // 00 - 01 borrows and wraps to 99; N comes from the binary result $FF
+        let cpu: CPU = run_decimal(Variant::Nmos6502, true, 0x00, 0xe9, 0x01);
+        assert_eq!(cpu.register_a, 0x99);
+        assert_eq!(cpu.status & 0b1000_0011, 0b1000_0000);
+        let cpu: CPU = run_decimal(Variant::Nmos6502, false, 0x21, 0xe9, 0x01);
+        assert_eq!(cpu.register_a, 0x19);
+    }
+    fn run_65c02(program: Vec<u8>, setup: fn(&mut CPU)) -> CPU {
+        let mut cpu: CPU = CPU::new();
+        cpu.variant = Variant::Wdc65C02;
+        cpu.load(program).unwrap();
+        cpu.reset();
+        setup(&mut cpu);
+        cpu.run().unwrap();
+        cpu
+    }
+    #[test]
+    fn test_65c02_stack_and_stz() {
+        // LDX #$11 PHX LDY #$22 PHY PLX PLY STZ $10 LDA #5 INC A
+        let cpu: CPU = run_65c02(
+            vec![0xa2, 0x11, 0xda, 0xa0, 0x22, 0x5a, 0xfa, 0x7a, 0x64, 0x10, 0xa9, 0x05, 0x1a, 0x00],
+            |cpu| cpu.mem_write(0x10, 0xff),
+        );
+        assert_eq!(cpu.register_x, 0x22);
+        assert_eq!(cpu.register_y, 0x11);
+        assert_eq!(cpu.register_a, 0x06);
+        assert_eq!(cpu.bus.peek(0x10), 0x00);
+    }
+    #[test]
+    fn test_65c02_tsb_trb() {
+        // LDA #$0C TSB $10
+        let cpu: CPU = run_65c02(vec![0xa9, 0x0c, 0x04, 0x10, 0x00], |cpu| cpu.mem_write(0x10, 0x03));
+        assert_eq!(cpu.bus.peek(0x10), 0x0f);
+        assert!(cpu.status & 0b0000_0010 != 0); // No bits in common
+        // LDA #$0C TRB $0210
+        let cpu: CPU = run_65c02(vec![0xa9, 0x0c, 0x1c, 0x10, 0x02, 0x00], |cpu| cpu.mem_write(0x0210, 0x0f));
+        assert_eq!(cpu.bus.peek(0x0210), 0x03);
+        assert!(cpu.status & 0b0000_0010 == 0);
+    }
+    #[test]
+    fn test_65c02_bit_branches() {
+        let cpu: CPU = run_65c02(
+            vec![
+                0xb7, 0x10, //       SMB3 $10
+                0xbf, 0x10, 0x01, // BBS3 $10,+1 (taken)
+                0xe8, //             INX
+                0x37, 0x10, //       RMB3 $10
+                0x3f, 0x10, 0x01, // BBR3 $10,+1 (taken)
+                0xe8, //             INX
+                0x3f, 0x11, 0x01, // BBR3 $11,+1 (not taken)
+                0xc8, //             INY
+                0x80, 0x01, //       BRA +1
+                0xe8, //             INX
+                0x00,
+            ],
+            |cpu| cpu.mem_write(0x11, 0x08),
+        );
+        assert_eq!(cpu.register_x, 0);
+        assert_eq!(cpu.register_y, 1);
+        assert_eq!(cpu.bus.peek(0x10), 0x00);
+    }
+    #[test]
+    fn test_65c02_zero_page_indirect() {
+        // LDA ($10) STA ($12)
+        let cpu: CPU = run_65c02(vec![0xb2, 0x10, 0x92, 0x12, 0x00], |cpu| {
+            cpu.mem_write_u16(0x10, 0x0400);
+            cpu.mem_write_u16(0x12, 0x0500);
+            cpu.mem_write(0x0400, 0x5a);
+        });
+        assert_eq!(cpu.register_a, 0x5a);
+        assert_eq!(cpu.bus.peek(0x0500), 0x5a);
+    }
+    #[test]
+    fn test_65c02_jmp_indirect() {
+        // JMP ($02FF) reads the high byte from $0300, not $0200
+        let cpu: CPU = run_65c02(vec![0x6c, 0xff, 0x02], |cpu| {
+            cpu.mem_write(0x02ff, 0x00);
+            cpu.mem_write(0x0300, 0x90);
+            cpu.mem_write(0x0200, 0x70);
+        });
+        assert_eq!(cpu.program_counter, 0x9001);
+        // JMP ($1000,X)
+        let cpu: CPU = run_65c02(vec![0xa2, 0x02, 0x7c, 0x00, 0x10], |cpu| cpu.mem_write_u16(0x1002, 0xa000));
+        assert_eq!(cpu.program_counter, 0xa001);
+    }
+    #[test]
+    fn test_65c02_reserved_opcodes_are_nops() {
+        // 1, 2 and 3 byte NOPs, then LAX $10 on the NMOS chip but SMB2 $10 here
+        let cpu: CPU = run_65c02(vec![0x03, 0x02, 0xff, 0x5c, 0x34, 0x12, 0xa7, 0x10, 0x00], |_| {});
+        assert_eq!(cpu.program_counter, 0x8009);
+        assert_eq!(cpu.cycles() - 7, 1 + 2 + 8 + 5);
+        assert_eq!(cpu.register_a, 0);
+        assert_eq!(cpu.bus.peek(0x10), 0x04);
+    }
+    #[test]
+    fn test_65c02_cycle_counts() {
+        let cycles = |program: Vec<u8>, setup: fn(&mut CPU)| run_65c02(program, setup).cycles() - 7;
+        assert_eq!(cycles(vec![0x1e, 0x10, 0x00, 0x00], |_| {}), 6); // ASL $0010,X
+        assert_eq!(cycles(vec![0x1e, 0x10, 0x00, 0x00], |cpu| cpu.register_x = 0xff), 7);
+        assert_eq!(cycles(vec![0xfe, 0x10, 0x00, 0x00], |_| {}), 7); // INC $0010,X
+        assert_eq!(cycles(vec![0x6c, 0x00, 0x02], |cpu| cpu.mem_write_u16(0x0200, 0x9000)), 6);
+        assert_eq!(cycles(vec![0x80, 0x00, 0x00], |_| {}), 3); // BRA
+        assert_eq!(cycles(vec![0xf8, 0x69, 0x01, 0x00], |_| {}), 2 + 3); // SED ADC #1
+    }
+    #[test]
+    fn test_65c02_decimal_flags_are_valid() {
+        let cpu: CPU = run_decimal(Variant::Wdc65C02, false, 0x99, 0x69, 0x01);
+        assert_eq!(cpu.register_a, 0x00);
+        assert_eq!(cpu.status & 0b1000_0011, 0b0000_0011); // Z now follows the result
+        let cpu: CPU = run_decimal(Variant::Wdc65C02, true, 0x00, 0xe9, 0x01);
+        assert_eq!(cpu.register_a, 0x99);
+        assert_eq!(cpu.status & 0b1000_0011, 0b1000_0000);
+    }
+    #[test]
+    fn test_65c02_brk_clears_decimal() {
+        let mut cpu: CPU = CPU::new();
+        cpu.variant = Variant::Wdc65C02;
+        cpu.halt_on_brk = false;
+        cpu.load(vec![0xf8, 0x00, 0xff]).unwrap(); // SED BRK
+        cpu.reset();
+        cpu.mem_write_u16(0xFFFE, 0x9000);
+        run_until_pc(&mut cpu, 0x9000);
+        assert_eq!(cpu.status & 0b0000_1000, 0);
+        assert_eq!(cpu.bus.peek(0x01fd) & 0b0000_1000, 0b0000_1000); // Pushed with D still set
+    }
+    #[test]
+    fn test_65c02_wai_waits_for_interrupt() {
+        let mut cpu: CPU = CPU::new();
+        cpu.variant = Variant::Wdc65C02;
+        cpu.load(vec![0x78, 0xcb, 0xe8, 0x00]).unwrap(); // SEI WAI INX
+        cpu.reset();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        for _ in 0..3 {
+            let report: StepReport = cpu.step().unwrap();
+            assert_eq!(report.cycles, 1);
+            assert_eq!(cpu.program_counter, 0x8002);
+        }
+        // A masked IRQ still wakes the CPU up, which carries on without taking it
+        cpu.set_irq_line(true);
+        let report: StepReport = cpu.step().unwrap();
+        assert_eq!(report.interrupt, None);
+        assert_eq!(cpu.register_x, 1);
+    }
+    #[test]
+    fn test_65c02_stp_stops_the_cpu() {
+        let mut cpu: CPU = CPU::new();
+        cpu.variant = Variant::Wdc65C02;
+        assert_eq!(cpu.load_and_run(vec![0xe8, 0xdb, 0xe8]), Err(CpuError::Stopped { pc: 0x8001 }));
+        assert_eq!(cpu.run(), Err(CpuError::Stopped { pc: 0x8001 }));
+        assert_eq!(cpu.register_x, 1);
+    }
+    #[test]
+    fn test_0xa7_lax_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0x85);
+        cpu.load_and_run(vec![0xa7, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.register_a, 0x85);
+        assert_eq!(cpu.register_x, 0x85);
+        assert!(cpu.status & 0b1000_0000 != 0);
+    }
+    #[test]
+    fn test_0x87_sax_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load_and_run(vec![0xa9, 0b1100_1100, 0xa2, 0b1010_1010, 0x87, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.bus.memory[0x10], 0b1000_1000);
+    }
+    #[test]
+    fn test_0xc7_dcp_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0x06);
+        cpu.load_and_run(vec![0xa9, 0x05, 0xc7, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.bus.memory[0x10], 0x05);
+        assert!(cpu.status & 0b0000_0010 != 0); // A == M
+        assert!(cpu.status & 0b0000_0001 != 0);
+    }
+    #[test]
+    fn test_0xe7_isb_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0x02);
+        cpu.load_and_run(vec![0x38, 0xa9, 0x05, 0xe7, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.bus.memory[0x10], 0x03);
+        assert_eq!(cpu.register_a, 0x02);
+    }
+    #[test]
+    fn test_0x07_slo_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0b1000_0001);
+        cpu.load_and_run(vec![0xa9, 0b0000_0100, 0x07, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.bus.memory[0x10], 0b0000_0010);
+        assert_eq!(cpu.register_a, 0b0000_0110);
+        assert!(cpu.status & 0b0000_0001 != 0);
+    }
+    #[test]
+    fn test_0x27_rla_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0b0100_0001);
+        cpu.load_and_run(vec![0x38, 0xa9, 0b1000_0011, 0x27, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.bus.memory[0x10], 0b1000_0011);
+        assert_eq!(cpu.register_a, 0b1000_0011);
+        assert!(cpu.status & 0b0000_0001 == 0);
+    }
+    #[test]
+    fn test_0x47_sre_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0b0000_0011);
+        cpu.load_and_run(vec![0xa9, 0b0000_0001, 0x47, 0x10, 0x00]).unwrap();
+        assert_eq!(cpu.bus.memory[0x10], 0b0000_0001);
+        assert_eq!(cpu.register_a, 0);
+        assert!(cpu.status & 0b0000_0011 == 0b0000_0011); // Carry and Zero
+    }
+    #[test]
+    fn test_0x67_rra_zero_page() {
+        let mut cpu: CPU = CPU::new();
+        cpu.mem_write(0x10, 0b0000_0101);
+        cpu.load_and_run(vec![0xa9, 0x10, 0x67, 0x10, 0x00]).unwrap();
+        // ROR gives 2 with carry out, ADC adds 0x10 + 2 + 1
+        assert_eq!(cpu.bus.memory[0x10], 0x02);
+        assert_eq!(cpu.register_a, 0x13);
+    }
+    #[test]
+    fn test_immediate_unofficial_opcodes() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load_and_run(vec![0xa9, 0xf0, 0x0b, 0x80, 0x00]).unwrap(); // ANC #$80
+        assert_eq!(cpu.register_a, 0x80);
+        assert!(cpu.status & 0b0000_0001 != 0);
+
+        cpu.load_and_run(vec![0xa9, 0xff, 0x4b, 0x03, 0x00]).unwrap(); // ALR #$03
+        assert_eq!(cpu.register_a, 0x01);
+        assert!(cpu.status & 0b0000_0001 != 0);
+
+        cpu.load_and_run(vec![0x38, 0xa9, 0xff, 0x6b, 0x40, 0x00]).unwrap(); // ARR #$40
+        assert_eq!(cpu.register_a, 0xa0);
+        assert!(cpu.status & 0b0000_0001 == 0); // Bit 6 clear
+        assert!(cpu.status & 0b0100_0000 != 0); // Bit 6 XOR bit 5
+
+        cpu.load_and_run(vec![0xa9, 0x0f, 0xa2, 0x3c, 0xcb, 0x02, 0x00]).unwrap(); // AXS #$02
+        assert_eq!(cpu.register_x, 0x0a);
+        assert!(cpu.status & 0b0000_0001 != 0);
+    }
+    #[test]
+    fn test_unofficial_nops() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0x1a, 0x80, 0xff, 0x04, 0x10, 0x14, 0x10, 0x0c, 0x00, 0x20, 0x1c, 0xff, 0x20, 0xe8, 0x00]).unwrap();
+        cpu.reset();
+        cpu.register_x = 1;
+        cpu.run().unwrap();
+        // Every NOP is skipped with its operand, and the abs,X one pays for the page cross
+        assert_eq!(cpu.register_x, 2);
+        assert_eq!(cpu.cycles() - 7, 2 + 2 + 3 + 4 + 4 + 5 + 2);
+    }
+    #[test]
+    fn test_kil_jams_the_cpu() {
+        let mut cpu: CPU = CPU::new();
+        assert_eq!(cpu.load_and_run(vec![0xe8, 0x02, 0xe8, 0x00]), Err(CpuError::Jammed { pc: 0x8001 }));
+        assert!(cpu.is_jammed());
+        assert_eq!(cpu.register_x, 1);
+        assert_eq!(cpu.program_counter, 0x8001);
+        // Reset, INX, then the two fetch cycles of the KIL
+        assert_eq!(cpu.cycles(), 7 + 2 + 2);
+        // Stays stuck until reset
+        assert_eq!(cpu.run(), Err(CpuError::Jammed { pc: 0x8001 }));
+        assert_eq!(cpu.program_counter, 0x8001);
+        cpu.reset();
+        assert!(!cpu.is_jammed());
+    }
+    #[test]
+    fn test_run_reports_halt_on_brk() {
+        let mut cpu: CPU = CPU::new();
+        assert_eq!(cpu.load_and_run(vec![0xe8, 0x00]), Ok(StepOutcome::Halted));
+    }
+    #[test]
+    fn test_load_out_of_range() {
+        let mut cpu: CPU = CPU::new();
+        assert_eq!(
+            cpu.load_at(vec![0xea; 0x10], 0xfff8),
+            Err(CpuError::InvalidLoadRange { start: 0xfff8, len: 0x10 })
+        );
+        assert!(cpu.load_at(vec![0xea; 0x08], 0xfff8).is_ok());
+    }
+    #[test]
+    fn test_operand_address_without_operand() {
+        let mut cpu: CPU = CPU::new();
+        cpu.program_counter = 0x1234;
+        assert_eq!(
+            cpu.get_operand_address(&AddressingMode::NoneAddressing),
+            Err(CpuError::UnsupportedAddressingMode {
+                mode: AddressingMode::NoneAddressing,
+                pc: 0x1234
+            })
+        );
+    }
+    #[test]
+    fn test_cpu_error_messages() {
+        assert_eq!(
+            CpuError::UnknownOpcode { opcode: 0x02, pc: 0xc000 }.to_string(),
+            "unknown opcode 0x02 at 0xc000"
+        );
+        assert_eq!(CpuError::Jammed { pc: 0x8001 }.to_string(), "CPU jammed by KIL opcode at 0x8001");
+        assert_eq!(CpuError::Stopped { pc: 0x8001 }.to_string(), "CPU stopped by STP opcode at 0x8001");
+    }
+    #[test]
+    fn test_step_reports_one_instruction() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0xa9, 0x42, 0x8d, 0x00, 0x02, 0x00]).unwrap(); // LDA #$42 STA $0200 BRK
+        cpu.reset();
+
+        let report: StepReport = cpu.step().unwrap();
+        assert_eq!(report.pc, 0x8000);
+        assert_eq!(report.opcode, 0xa9);
+        assert_eq!(report.cycles, 2);
+        assert_eq!(report.interrupt, None);
+        assert_eq!(report.outcome, StepOutcome::Continue);
+        assert_eq!(cpu.program_counter, 0x8002);
+
+        let report: StepReport = cpu.step().unwrap();
+        assert_eq!(report.cycles, 4);
+        assert_eq!(
+            report.accesses,
+            vec![
+                MemoryAccess { kind: AccessKind::Read, addr: 0x8002, data: 0x8d },
+                MemoryAccess { kind: AccessKind::Read, addr: 0x8003, data: 0x00 },
+                MemoryAccess { kind: AccessKind::Read, addr: 0x8004, data: 0x02 },
+                MemoryAccess { kind: AccessKind::Write, addr: 0x0200, data: 0x42 },
+            ]
+        );
+
+        assert_eq!(cpu.step().unwrap().outcome, StepOutcome::Halted);
+    }
+    #[test]
+    fn test_step_reports_serviced_interrupt() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0xea, 0xea]).unwrap();
+        cpu.reset();
+        cpu.mem_write_u16(0xFFFA, 0x9000);
+        cpu.mem_write(0x9000, 0xe8); // INX
+        cpu.step().unwrap();
+        cpu.set_nmi_line(true);
+        cpu.step().unwrap(); // NMI is noticed at the end of this instruction
+
+        let report: StepReport = cpu.step().unwrap();
+        assert_eq!(report.interrupt, Some(Interrupt::Nmi));
+        assert_eq!(report.pc, 0x9000);
+        assert_eq!(report.opcode, 0xe8);
+        assert_eq!(report.cycles, 7 + 2);
+        assert_eq!(cpu.register_x, 1);
+    }
+    #[test]
+    fn test_step_on_jammed_cpu() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0x02]).unwrap();
+        cpu.reset();
+        assert_eq!(cpu.step(), Err(CpuError::Jammed { pc: 0x8000 }));
+        assert_eq!(cpu.step(), Err(CpuError::Jammed { pc: 0x8000 }));
+    }
+    #[test]
+    fn test_run_for_cycles() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0xe8, 0x4c, 0x00, 0x80]).unwrap(); // INX JMP $8000
+        cpu.reset();
+        let start: u64 = cpu.cycles();
+        // Each loop is 2 + 3 cycles
+        assert_eq!(cpu.run_for_cycles(50), Ok(StepOutcome::Continue));
+        assert_eq!(cpu.cycles() - start, 50);
+        assert_eq!(cpu.register_x, 10);
+        // Overshoots to the end of the instruction
+        cpu.run_for_cycles(1).unwrap();
+        assert_eq!(cpu.cycles() - start, 52);
+    }
+    #[test]
+    fn test_run_for_cycles_stops_on_brk() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0xe8, 0x00]).unwrap();
+        cpu.reset();
+        assert_eq!(cpu.run_for_cycles(1000), Ok(StepOutcome::Halted));
+        assert_eq!(cpu.register_x, 1);
+    }
+    #[test]
+    fn test_run_until() {
+        let mut cpu: CPU = CPU::new();
+        cpu.load(vec![0xe8, 0x4c, 0x00, 0x80]).unwrap(); // INX JMP $8000
+        cpu.reset();
+        assert_eq!(cpu.run_until(|cpu| cpu.register_x == 5), Ok(StepOutcome::Continue));
+        assert_eq!(cpu.register_x, 5);
+        assert_eq!(cpu.program_counter, 0x8001);
+        // Already true: nothing runs
+        let cycles: u64 = cpu.cycles();
+        cpu.run_until(|_| true).unwrap();
+        assert_eq!(cpu.cycles(), cycles);
+    }
 }
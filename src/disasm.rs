@@ -0,0 +1,247 @@
+use std::collections::BTreeMap;
+
+use crate::cpu::AddressingMode;
+use crate::cpu::Variant;
+use crate::opcodes::OpCode;
+
+// Static disassembler: decodes a block of code with the same opcode tables the CPU runs on.
+// Unlike the tracer it knows nothing about register or memory contents, so operands are printed
+// the way they'd be written in the source, and branch targets are resolved to absolute
+// addresses (or to labels, when asked for).
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    // None for the leftover bytes of an instruction cut off by the end of the block
+    pub opcode: Option<&'static OpCode>,
+}
+
+impl Instruction {
+    fn byte(&self) -> u8 {
+        self.bytes.get(1).copied().unwrap_or(0)
+    }
+    fn word(&self) -> u16 {
+        (self.bytes.get(2).copied().unwrap_or(0) as u16) << 8 | self.byte() as u16
+    }
+
+    // Where a branch, JMP or JSR goes. None for everything else, including indirect jumps.
+    pub fn target(&self) -> Option<u16> {
+        let operation: &OpCode = self.opcode?;
+        match (operation.mode, operation.num_bytes) {
+            (AddressingMode::Absolute, _) if operation.instruction == 0x4c => Some(self.word()),
+            (AddressingMode::NoneAddressing, 2) => Some(branch_target(self.address, 2, self.byte())),
+            // BBRn/BBSn: the offset is the second operand byte
+            (AddressingMode::NoneAddressing, 3) if operation.name.starts_with("BB") => {
+                Some(branch_target(self.address, 3, self.bytes[2]))
+            }
+            // JSR
+            (AddressingMode::NoneAddressing, 3) if operation.instruction == 0x20 => Some(self.word()),
+            _ => None,
+        }
+    }
+
+    // The mnemonic and operand, e.g. "LDA ($10),Y". Targets found in labels are printed by name.
+    pub fn format(&self, labels: &BTreeMap<u16, String>) -> String {
+        let operation: &OpCode = match self.opcode {
+            Some(operation) => operation,
+            None => {
+                let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("${:02X}", byte)).collect();
+                return format!(".byte {}", bytes.join(", "));
+            }
+        };
+        let target: String = match self.target() {
+            Some(addr) => labels.get(&addr).cloned().unwrap_or_else(|| format!("${:04X}", addr)),
+            None => String::new(),
+        };
+        let byte: u8 = self.byte();
+        let word: u16 = self.word();
+
+        let operand: String = match operation.mode {
+            AddressingMode::Immediate => format!("#${:02X}", byte),
+            AddressingMode::ZeroPage => format!("${:02X}", byte),
+            AddressingMode::ZeroPage_X => format!("${:02X},X", byte),
+            AddressingMode::ZeroPage_Y => format!("${:02X},Y", byte),
+            AddressingMode::Absolute if operation.instruction == 0x4c => target,
+            AddressingMode::Absolute => format!("${:04X}", word),
+            AddressingMode::Absolute_X => format!("${:04X},X", word),
+            AddressingMode::Absolute_Y => format!("${:04X},Y", word),
+            AddressingMode::Indirect_X => format!("(${:02X},X)", byte),
+            AddressingMode::Indirect_Y => format!("(${:02X}),Y", byte),
+            AddressingMode::ZeroPage_Indirect => format!("(${:02X})", byte),
+            AddressingMode::NoneAddressing => match (operation.name, operation.num_bytes) {
+                ("ASL" | "LSR" | "ROL" | "ROR" | "INC" | "DEC", 1) => "A".to_string(),
+                (_, 1) => String::new(),
+                (_, 2) => target,
+                (name, _) if name.starts_with("BB") => format!("${:02X},{}", byte, target),
+                _ if operation.instruction == 0x6c => format!("(${:04X})", word),
+                _ if operation.instruction == 0x7c => format!("(${:04X},X)", word),
+                _ if operation.instruction == 0x20 => target,
+                // The 65C02's 3-byte NOP
+                _ => format!("${:04X}", word),
+            },
+        };
+        if operand.is_empty() {
+            operation.name.to_string()
+        } else {
+            format!("{} {}", operation.name, operand)
+        }
+    }
+}
+
+fn branch_target(address: u16, num_bytes: u16, offset: u8) -> u16 {
+    address.wrapping_add(num_bytes).wrapping_add(offset as i8 as u16)
+}
+
+// Decodes the whole block as code, as if it were loaded at origin
+pub fn disassemble(code: &[u8], origin: u16, variant: Variant) -> Vec<Instruction> {
+    let opcodes: &'static [OpCode; 256] = variant.opcodes();
+    let mut instructions: Vec<Instruction> = Vec::new();
+    let mut offset: usize = 0;
+    while offset < code.len() {
+        let operation: &'static OpCode = &opcodes[code[offset] as usize];
+        let end: usize = offset + operation.num_bytes as usize;
+        let (end, opcode) = if end <= code.len() {
+            (end, Some(operation))
+        } else {
+            (code.len(), None)
+        };
+        instructions.push(Instruction {
+            address: origin.wrapping_add(offset as u16),
+            bytes: code[offset..end].to_vec(),
+            opcode,
+        });
+        offset = end;
+    }
+    instructions
+}
+
+// Names every branch, JMP and JSR target that lands on the start of a decoded instruction.
+// Targets outside the block, or in the middle of an instruction, stay as plain addresses.
+pub fn make_labels(instructions: &[Instruction]) -> BTreeMap<u16, String> {
+    let starts: Vec<u16> = instructions.iter().map(|instruction| instruction.address).collect();
+    instructions
+        .iter()
+        .filter_map(|instruction| instruction.target())
+        .filter(|target| starts.contains(target))
+        .map(|target| (target, format!("L{:04X}", target)))
+        .collect()
+}
+
+// A full listing, one instruction per line:
+// 0600  20 06 06  JSR L0606
+// With labels on, each target gets a "L0606:" line of its own before it.
+pub fn listing(code: &[u8], origin: u16, variant: Variant, labels: bool) -> String {
+    let instructions: Vec<Instruction> = disassemble(code, origin, variant);
+    let labels: BTreeMap<u16, String> = if labels {
+        make_labels(&instructions)
+    } else {
+        BTreeMap::new()
+    };
+    let mut out: String = String::new();
+    for instruction in &instructions {
+        if let Some(label) = labels.get(&instruction.address) {
+            out.push_str(&format!("{}:\n", label));
+        }
+        let hex: Vec<String> = instruction.bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+        out.push_str(&format!(
+            "{:04X}  {:8}  {}\n",
+            instruction.address,
+            hex.join(" "),
+            instruction.format(&labels)
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::snake;
+
+    fn line(code: &[u8], origin: u16, variant: Variant) -> String {
+        disassemble(code, origin, variant)[0].format(&BTreeMap::new())
+    }
+
+    #[test]
+    fn test_operand_syntax() {
+        let cases: [(&[u8], &str); 16] = [
+            (&[0xa9, 0x10], "LDA #$10"),
+            (&[0xa5, 0x10], "LDA $10"),
+            (&[0xb5, 0x10], "LDA $10,X"),
+            (&[0xb6, 0x10], "LDX $10,Y"),
+            (&[0xad, 0x34, 0x12], "LDA $1234"),
+            (&[0xbd, 0x34, 0x12], "LDA $1234,X"),
+            (&[0xb9, 0x34, 0x12], "LDA $1234,Y"),
+            (&[0xa1, 0x10], "LDA ($10,X)"),
+            (&[0xb1, 0x10], "LDA ($10),Y"),
+            (&[0x0a], "ASL A"),
+            (&[0xea], "NOP"),
+            (&[0x4c, 0x34, 0x12], "JMP $1234"),
+            (&[0x6c, 0x34, 0x12], "JMP ($1234)"),
+            (&[0x20, 0x34, 0x12], "JSR $1234"),
+            (&[0xd0, 0xfe], "BNE $8000"),
+            (&[0xa7, 0x10], "LAX $10"),
+        ];
+        for (code, expected) in cases {
+            assert_eq!(line(code, 0x8000, Variant::Ricoh2A03), expected);
+        }
+    }
+    #[test]
+    fn test_65c02_operand_syntax() {
+        assert_eq!(line(&[0xb2, 0x10], 0x8000, Variant::Wdc65C02), "LDA ($10)");
+        assert_eq!(line(&[0x7c, 0x34, 0x12], 0x8000, Variant::Wdc65C02), "JMP ($1234,X)");
+        assert_eq!(line(&[0x1a], 0x8000, Variant::Wdc65C02), "INC A");
+        assert_eq!(line(&[0x80, 0x10], 0x8000, Variant::Wdc65C02), "BRA $8012");
+        assert_eq!(line(&[0x0f, 0x10, 0xfd], 0x8000, Variant::Wdc65C02), "BBR0 $10,$8000");
+    }
+    #[test]
+    fn test_branch_targets() {
+        // Backwards across a page and forwards
+        assert_eq!(line(&[0x10, 0x80], 0x8010, Variant::Ricoh2A03), "BPL $7F92");
+        assert_eq!(line(&[0xf0, 0x7f], 0x8000, Variant::Ricoh2A03), "BEQ $8081");
+        // Wraps around the top of memory
+        assert_eq!(line(&[0x90, 0x10], 0xfff0, Variant::Ricoh2A03), "BCC $0002");
+    }
+    #[test]
+    fn test_truncated_instruction() {
+        let instructions: Vec<Instruction> = disassemble(&[0xea, 0xad, 0x34], 0x8000, Variant::Ricoh2A03);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[1].address, 0x8001);
+        assert!(instructions[1].opcode.is_none());
+        assert_eq!(instructions[1].format(&BTreeMap::new()), ".byte $AD, $34");
+    }
+    #[test]
+    fn test_snake_listing() {
+        let plain: String = listing(&snake::GAME_CODE, snake::LOAD_ADDRESS, Variant::Ricoh2A03, false);
+        let lines: Vec<&str> = plain.lines().collect();
+        assert_eq!(lines[0], "0600  20 06 06  JSR $0606");
+        assert_eq!(lines[4], "060C  60        RTS");
+        assert_eq!(lines[5], "060D  A9 02     LDA #$02");
+        assert_eq!(lines.last().unwrap(), &"0734  60        RTS");
+
+        let labelled: String = listing(&snake::GAME_CODE, snake::LOAD_ADDRESS, Variant::Ricoh2A03, true);
+        let lines: Vec<&str> = labelled.lines().collect();
+        assert_eq!(lines[0], "0600  20 06 06  JSR L0606");
+        assert_eq!(lines[1], "0603  20 38 06  JSR L0638");
+        assert_eq!(lines[2], "L0606:");
+        assert_eq!(lines[3], "0606  20 0D 06  JSR L060D");
+        // The delay loop at the end
+        assert!(labelled.ends_with(
+            "L072F:\n\
+             072F  EA        NOP\n\
+             0730  EA        NOP\n\
+             0731  CA        DEX\n\
+             0732  D0 FB     BNE L072F\n\
+             0734  60        RTS\n"
+        ));
+    }
+    #[test]
+    fn test_labels_only_for_instruction_starts() {
+        // JMP into the middle of the LDA, and a JSR out of the block
+        let code: [u8; 8] = [0xa9, 0x00, 0x4c, 0x01, 0x80, 0x20, 0x00, 0x90];
+        let labels: BTreeMap<u16, String> = make_labels(&disassemble(&code, 0x8000, Variant::Ricoh2A03));
+        assert!(labels.is_empty());
+        let listing: String = listing(&code, 0x8000, Variant::Ricoh2A03, true);
+        assert!(listing.contains("JMP $8001"));
+        assert!(listing.contains("JSR $9000"));
+    }
+}
@@ -0,0 +1,13 @@
+pub mod assembler;
+pub mod bus;
+pub mod cartridge;
+pub mod cpu;
+pub mod disasm;
+pub mod frame;
+pub mod mapper;
+pub mod opcodes;
+pub mod palette;
+pub mod ppu;
+pub mod snake;
+pub mod test_rom;
+pub mod trace;
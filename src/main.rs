@@ -1,6 +1,10 @@
-pub mod cpu;
-pub mod opcodes;
-use cpu::*;
+use nes_emulator::bus::NesBus;
+use nes_emulator::cartridge::Rom;
+use nes_emulator::cpu::*;
+use nes_emulator::frame::Frame;
+use nes_emulator::snake;
+use nes_emulator::test_rom;
+use nes_emulator::trace::Tracer;
 use rand::Rng;
 use sdl2::VideoSubsystem;
 use sdl2::event::Event;
@@ -11,6 +15,21 @@ use sdl2::pixels::PixelFormatEnum;
 
 
 fn main() {
+    // Play a .nes file if one was given, otherwise the built-in snake game.
+    // --trace logs every instruction to stdout in nestest.log format.
+    // --test-rom runs a blargg test ROM without a window and exits with its result code.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let trace: bool = args.iter().any(|arg| arg == "--trace");
+    let headless_test: bool = args.iter().any(|arg| arg == "--test-rom");
+    if let Some(path) = args.iter().find(|arg| !arg.starts_with("--")) {
+        if headless_test {
+            run_test_rom(path);
+        } else {
+            run_rom(path, trace);
+        }
+        return;
+    }
+
     // init sdl2, use unwrap because it's outer layer
     // No other layers can potentially handle errors and do something about it.
     let sdl_context: sdl2::Sdl = sdl2::init().unwrap(); // Get our context
@@ -32,40 +51,20 @@ fn main() {
 
 
 
-    let game_code = vec![
-        0x20, 0x06, 0x06, 0x20, 0x38, 0x06, 0x20, 0x0d, 0x06, 0x20, 0x2a, 0x06, 0x60, 0xa9, 0x02, 0x85,
-    0x02, 0xa9, 0x04, 0x85, 0x03, 0xa9, 0x11, 0x85, 0x10, 0xa9, 0x10, 0x85, 0x12, 0xa9, 0x0f, 0x85,
-    0x14, 0xa9, 0x04, 0x85, 0x11, 0x85, 0x13, 0x85, 0x15, 0x60, 0xa5, 0xfe, 0x85, 0x00, 0xa5, 0xfe,
-    0x29, 0x03, 0x18, 0x69, 0x02, 0x85, 0x01, 0x60, 0x20, 0x4d, 0x06, 0x20, 0x8d, 0x06, 0x20, 0xc3,
-    0x06, 0x20, 0x19, 0x07, 0x20, 0x20, 0x07, 0x20, 0x2d, 0x07, 0x4c, 0x38, 0x06, 0xa5, 0xff, 0xc9,
-    0x77, 0xf0, 0x0d, 0xc9, 0x64, 0xf0, 0x14, 0xc9, 0x73, 0xf0, 0x1b, 0xc9, 0x61, 0xf0, 0x22, 0x60,
-    0xa9, 0x04, 0x24, 0x02, 0xd0, 0x26, 0xa9, 0x01, 0x85, 0x02, 0x60, 0xa9, 0x08, 0x24, 0x02, 0xd0,
-    0x1b, 0xa9, 0x02, 0x85, 0x02, 0x60, 0xa9, 0x01, 0x24, 0x02, 0xd0, 0x10, 0xa9, 0x04, 0x85, 0x02,
-    0x60, 0xa9, 0x02, 0x24, 0x02, 0xd0, 0x05, 0xa9, 0x08, 0x85, 0x02, 0x60, 0x60, 0x20, 0x94, 0x06,
-    0x20, 0xa8, 0x06, 0x60, 0xa5, 0x00, 0xc5, 0x10, 0xd0, 0x0d, 0xa5, 0x01, 0xc5, 0x11, 0xd0, 0x07,
-    0xe6, 0x03, 0xe6, 0x03, 0x20, 0x2a, 0x06, 0x60, 0xa2, 0x02, 0xb5, 0x10, 0xc5, 0x10, 0xd0, 0x06,
-    0xb5, 0x11, 0xc5, 0x11, 0xf0, 0x09, 0xe8, 0xe8, 0xe4, 0x03, 0xf0, 0x06, 0x4c, 0xaa, 0x06, 0x4c,
-    0x35, 0x07, 0x60, 0xa6, 0x03, 0xca, 0x8a, 0xb5, 0x10, 0x95, 0x12, 0xca, 0x10, 0xf9, 0xa5, 0x02,
-    0x4a, 0xb0, 0x09, 0x4a, 0xb0, 0x19, 0x4a, 0xb0, 0x1f, 0x4a, 0xb0, 0x2f, 0xa5, 0x10, 0x38, 0xe9,
-    0x20, 0x85, 0x10, 0x90, 0x01, 0x60, 0xc6, 0x11, 0xa9, 0x01, 0xc5, 0x11, 0xf0, 0x28, 0x60, 0xe6,
-    0x10, 0xa9, 0x1f, 0x24, 0x10, 0xf0, 0x1f, 0x60, 0xa5, 0x10, 0x18, 0x69, 0x20, 0x85, 0x10, 0xb0,
-    0x01, 0x60, 0xe6, 0x11, 0xa9, 0x06, 0xc5, 0x11, 0xf0, 0x0c, 0x60, 0xc6, 0x10, 0xa5, 0x10, 0x29,
-    0x1f, 0xc9, 0x1f, 0xf0, 0x01, 0x60, 0x4c, 0x35, 0x07, 0xa0, 0x00, 0xa5, 0xfe, 0x91, 0x00, 0x60,
-    0xa6, 0x03, 0xa9, 0x00, 0x81, 0x10, 0xa2, 0x00, 0xa9, 0x01, 0x81, 0x10, 0x60, 0xa2, 0x00, 0xea,
-    0xea, 0xca, 0xd0, 0xfb, 0x60
-    ];
     // load the game
     let mut cpu = CPU::new();
-    cpu.load_at(game_code, 0x600);
+    cpu.load_at(snake::GAME_CODE.to_vec(), snake::LOAD_ADDRESS).unwrap();
+    cpu.mem_write_u16(0xFFFC, snake::LOAD_ADDRESS);
     cpu.reset();
 
     // cpu.print_memory();
     
+    #[allow(clippy::unnecessary_cast)]
     let mut screen_state = [0 as u8; 32 * 3 * 32];
     let mut rng = rand::thread_rng();
 
     // run the game cycle
-    cpu.run_with_callback( move |cpu| {
+    let result = cpu.run_with_callback( move |cpu| {
         // read user input and write it to mem[0xFF]
         handle_user_input(cpu, &mut event_pump);
         cpu.mem_write(0xfe, rng.gen_range(1, 16));
@@ -81,6 +80,109 @@ fn main() {
         // render screen state
 
     });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn load_rom(path: &str) -> Rom {
+    match std::fs::read(path).map_err(|e| e.to_string()).and_then(|raw| {
+        Rom::new(&raw).map_err(|e| e.to_string())
+    }) {
+        Ok(rom) => rom,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn run_test_rom(path: &str) {
+    match test_rom::run_test_rom(load_rom(path), test_rom::DEFAULT_CYCLE_LIMIT) {
+        Ok(result) => {
+            print!("{}", result.message);
+            std::process::exit(result.status as i32);
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(0xff);
+        }
+    }
+}
+
+fn run_rom(path: &str, trace: bool) {
+    let rom: Rom = load_rom(path);
+
+    let sdl_context: sdl2::Sdl = sdl2::init().unwrap();
+    let video_subsystem: VideoSubsystem = sdl_context.video().unwrap();
+    let window = video_subsystem
+        .window("NES", (Frame::WIDTH * 3) as u32, (Frame::HEIGHT * 3) as u32)
+        .position_centered()
+        .build().unwrap();
+    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    canvas.set_scale(3.0, 3.0).unwrap();
+    let creator = canvas.texture_creator();
+    let mut texture = creator
+        .create_texture_target(PixelFormatEnum::RGB24, Frame::WIDTH as u32, Frame::HEIGHT as u32)
+        .unwrap();
+
+    let mut bus: NesBus = match NesBus::new(rom) {
+        Ok(bus) => bus,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    // Battery backed RAM lives next to the ROM as game.sav
+    let save_path: std::path::PathBuf = std::path::Path::new(path).with_extension("sav");
+    if bus.battery_ram().is_some() {
+        if let Ok(save) = std::fs::read(&save_path) {
+            bus.load_battery_ram(&save);
+        }
+    }
+    let mut cpu = CPU::with_bus(bus);
+    cpu.halt_on_brk = false; // Real cartridges use BRK as a software interrupt
+    cpu.reset();
+
+    let mut tracer: Option<Tracer<std::io::Stdout>> = trace.then(|| Tracer::new(std::io::stdout()));
+    let mut last_frame: u64 = 0;
+    let result = cpu.run_with_callback(move |cpu| {
+        if let Some(tracer) = tracer.as_mut() {
+            tracer.trace(cpu).unwrap();
+        }
+        // Only redraw once the PPU has finished a new frame
+        if cpu.bus.ppu.frame_count() == last_frame {
+            return;
+        }
+        last_frame = cpu.bus.ppu.frame_count();
+        texture.update(None, &cpu.bus.ppu.frame.data, Frame::WIDTH * 3).unwrap();
+        canvas.copy(&texture, None, None).unwrap();
+        canvas.present();
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), ..} => {
+                    save_battery_ram(&cpu.bus, &save_path);
+                    std::process::exit(0)
+                },
+                _ => {/* Do nothing */}
+            }
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("{}: {}", path, e);
+        std::process::exit(1);
+    }
+}
+
+fn save_battery_ram(bus: &NesBus, save_path: &std::path::Path) {
+    if let Some(ram) = bus.battery_ram() {
+        if let Err(e) = std::fs::write(save_path, ram) {
+            eprintln!("{}: {}", save_path.display(), e);
+        }
+    }
 }
 
 fn handle_user_input(cpu: &mut CPU, event_pump: &mut EventPump) {
@@ -120,7 +222,7 @@ fn color(byte: u8) -> Color {
     }
 }
 
-fn read_screen_state(cpu: &CPU, frame: &mut [u8; 32 * 3 * 32]) -> bool {
+fn read_screen_state(cpu: &mut CPU, frame: &mut [u8; 32 * 3 * 32]) -> bool {
    let mut frame_idx = 0;
    let mut update = false;
    for i in 0x0200..0x600 {
@@ -0,0 +1,213 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+
+mod axrom;
+mod cnrom;
+mod mmc1;
+mod mmc3;
+mod nrom;
+mod uxrom;
+mod vrc4;
+mod vrc6;
+mod vrc7;
+mod vrc_irq;
+
+pub use axrom::Axrom;
+pub use cnrom::Cnrom;
+pub use mmc1::Mmc1;
+pub use mmc3::Mmc3;
+pub use nrom::Nrom;
+pub use uxrom::Uxrom;
+pub use vrc4::Vrc4;
+pub use vrc6::Vrc6;
+pub use vrc7::Vrc7;
+
+// CPU addresses every board decodes the same way
+const PRG_RAM: u16 = 0x6000;
+const PRG_RAM_END: u16 = 0x7FFF;
+const PRG_ROM: u16 = 0x8000;
+
+// The cartridge board: everything between the ROM chips and the two buses it's wired to.
+// The CPU sees it at $4020-$FFFF, the PPU at $0000-$1FFF (the pattern tables), and many
+// boards also decide how the console's 2 KiB of nametable RAM is mirrored.
+pub trait Mapper {
+    // CPU $4020-$FFFF. None where the board doesn't drive the bus, so the read sees open bus.
+    // Has to be free of side effects, it's also what Bus::peek shows.
+    fn cpu_read(&self, addr: u16) -> Option<u8>;
+    // CPU $4020-$FFFF: PRG RAM, and on most boards the mapper's own registers
+    fn cpu_write(&mut self, addr: u16, data: u8);
+    // PPU $0000-$1FFF
+    fn ppu_read(&self, addr: u16) -> u8;
+    // PPU $0000-$1FFF, ignored unless the board has CHR RAM
+    fn ppu_write(&mut self, addr: u16, data: u8);
+    fn mirroring(&self) -> Mirroring;
+    // The cartridge's /IRQ output, held until the game acknowledges it
+    fn irq_line(&self) -> bool {
+        false
+    }
+    // Called after every instruction with the CPU cycles it took
+    fn cpu_tick(&mut self, _cycles: u8) {}
+    // PPU address line A12 (which pattern table the PPU is on) changed level, `dot` being the
+    // PPU dots since power on. Boards that count scanlines watch it.
+    fn ppu_a12(&mut self, _high: bool, _dot: u64) {}
+    // PRG RAM that keeps its contents with the power off, for saving to disk
+    fn battery_ram(&self) -> Option<&[u8]> {
+        None
+    }
+    // Restores a save made from battery_ram. A save of the wrong size is cut or padded to fit.
+    fn load_battery_ram(&mut self, _data: &[u8]) {}
+}
+
+// The CPU bus and the PPU each hold a handle to the same board
+pub type SharedMapper = Rc<RefCell<dyn Mapper>>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapperError {
+    // iNES mapper number nothing here implements
+    Unsupported { mapper: u16, submapper: u8 },
+}
+
+impl fmt::Display for MapperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MapperError::Unsupported { mapper, submapper: 0 } => {
+                write!(f, "mapper {} is not supported", mapper)
+            }
+            MapperError::Unsupported { mapper, submapper } => {
+                write!(f, "mapper {}.{} is not supported", mapper, submapper)
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapperError {}
+
+// Builds the board the header asks for
+pub fn for_rom(rom: Rom) -> Result<SharedMapper, MapperError> {
+    match rom.mapper {
+        0 => Ok(Rc::new(RefCell::new(Nrom::new(rom)))),
+        1 => Ok(Rc::new(RefCell::new(Mmc1::new(rom)))),
+        2 => Ok(Rc::new(RefCell::new(Uxrom::new(rom)))),
+        3 => Ok(Rc::new(RefCell::new(Cnrom::new(rom)))),
+        // MMC6 (submapper 1) and the MC-ACC (3) differ in ways not emulated here
+        4 if !matches!(rom.submapper, 1 | 3) => Ok(Rc::new(RefCell::new(Mmc3::new(rom)))),
+        7 => Ok(Rc::new(RefCell::new(Axrom::new(rom)))),
+        21 | 22 | 23 | 25 => Ok(Rc::new(RefCell::new(Vrc4::new(rom)))),
+        24 | 26 => Ok(Rc::new(RefCell::new(Vrc6::new(rom)))),
+        85 => Ok(Rc::new(RefCell::new(Vrc7::new(rom)))),
+        mapper => Err(MapperError::Unsupported {
+            mapper,
+            submapper: rom.submapper,
+        }),
+    }
+}
+
+// PRG RAM as the header describes it, battery backed or not, with the trainer (if any)
+// already copied to $7000
+fn prg_ram(rom: &Rom) -> Vec<u8> {
+    let mut ram: Vec<u8> = vec![0; rom.prg_ram_size + rom.prg_nvram_size];
+    if let Some(trainer) = &rom.trainer {
+        if ram.len() < 0x2000 {
+            ram.resize(0x2000, 0);
+        }
+        ram[0x1000..0x1000 + trainer.len()].copy_from_slice(trainer);
+    }
+    ram
+}
+
+fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len: usize = data.len().min(ram.len());
+    ram[..len].copy_from_slice(&data[..len]);
+    ram[len..].fill(0);
+}
+
+// Mapper::battery_ram and load_battery_ram for boards whose save is their PRG RAM
+fn battery_ram(battery: bool, prg_ram: &[u8]) -> Option<&[u8]> {
+    battery.then_some(prg_ram)
+}
+fn load_battery_ram(battery: bool, prg_ram: &mut [u8], data: &[u8]) {
+    if battery {
+        load_ram(prg_ram, data);
+    }
+}
+
+// CHR ROM, or CHR RAM of the size the header gives (8 KiB for iNES files) when there's none.
+// The flag says whether it's RAM.
+fn chr_memory(rom: &Rom) -> (Vec<u8>, bool) {
+    if rom.chr_rom.is_empty() {
+        let size: usize = rom.chr_ram_size + rom.chr_nvram_size;
+        (vec![0; if size == 0 { 0x2000 } else { size }], true)
+    } else {
+        (rom.chr_rom.clone(), false)
+    }
+}
+
+// Mapper::ppu_write once the board has mapped the address: CHR ROM ignores the write
+fn write_chr(chr: &mut [u8], chr_is_ram: bool, offset: usize, data: u8) {
+    if chr_is_ram {
+        chr[offset] = data;
+    }
+}
+
+// Discrete-logic boards latch whatever is on the data bus when $8000-$FFFF is written, but
+// the PRG ROM drives the bus too, so without a buffer chip the latch sees both ANDed together.
+// NES 2.0 submapper 1 means no conflicts and 2 means conflicts; plain iNES files get what the
+// usual board for that mapper did.
+fn bus_conflicts(rom: &Rom, default: bool) -> bool {
+    match rom.submapper {
+        1 => false,
+        2 => true,
+        _ => default,
+    }
+}
+
+// The value a discrete-logic latch ends up with for a write, given what the ROM drives there
+fn with_bus_conflict(conflicts: bool, rom_byte: Option<u8>, data: u8) -> u8 {
+    match rom_byte {
+        Some(rom_byte) if conflicts => data & rom_byte,
+        _ => data,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::test_rom;
+
+    #[test]
+    fn test_unsupported_mapper() {
+        let mut rom: Rom = test_rom(vec![0; 0x4000]);
+        rom.mapper = 255;
+        assert_eq!(
+            for_rom(rom).err(),
+            Some(MapperError::Unsupported {
+                mapper: 255,
+                submapper: 0
+            })
+        );
+        assert_eq!(
+            MapperError::Unsupported {
+                mapper: 4,
+                submapper: 1
+            }
+            .to_string(),
+            "mapper 4.1 is not supported"
+        );
+    }
+    #[test]
+    fn test_trainer_goes_to_7000() {
+        let mut rom: Rom = test_rom(vec![0; 0x4000]);
+        rom.prg_ram_size = 0;
+        rom.trainer = Some(vec![0xAB; 512]);
+        let ram: Vec<u8> = prg_ram(&rom);
+        assert_eq!(ram.len(), 0x2000);
+        assert_eq!(ram[0x0FFF], 0x00);
+        assert_eq!(ram[0x1000], 0xAB);
+        assert_eq!(ram[0x11FF], 0xAB);
+        assert_eq!(ram[0x1200], 0x00);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_ROM;
+
+const PRG_BANK_SIZE: usize = 0x8000;
+
+// Register bits, written anywhere in $8000-$FFFF
+const PRG_BANK: u8 = 0b0000_0111;
+const NAMETABLE_SELECT: u8 = 0b0001_0000;
+
+// Mapper 7, AxROM (ANROM, AMROM, AOROM: Battletoads, Marble Madness, R.C. Pro-Am, ...).
+// One register switches the whole of $8000-$FFFF in 32 KiB banks and picks which 1 KiB
+// of nametable RAM all four nametables show. CHR is 8 KiB of unbanked RAM.
+pub struct Axrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    bus_conflicts: bool,
+    register: u8,
+}
+
+impl Axrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Axrom {
+            // ANROM and most AOROM boards have no conflicts, and some games write without
+            // caring about them, so only NES 2.0 submapper 2 (AMROM) gets them
+            bus_conflicts: super::bus_conflicts(&rom, false),
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            register: 0,
+        }
+    }
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank: usize = (self.register & PRG_BANK) as usize;
+        (bank * PRG_BANK_SIZE + (addr - PRG_ROM) as usize) % self.prg_rom.len()
+    }
+}
+
+impl Mapper for Axrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if addr >= PRG_ROM {
+            self.register = super::with_bus_conflict(self.bus_conflicts, self.cpu_read(addr), data);
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = addr as usize % self.chr.len();
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        if self.register & NAMETABLE_SELECT == 0 {
+            Mirroring::SingleScreenLower
+        } else {
+            Mirroring::SingleScreenUpper
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    fn axrom(banks: usize, submapper: u8) -> Axrom {
+        let mut rom: Rom = test_rom(numbered_banks(banks, PRG_BANK_SIZE));
+        rom.mapper = 7;
+        rom.submapper = submapper;
+        rom.chr_rom = vec![];
+        Axrom::new(rom)
+    }
+
+    #[test]
+    fn test_axrom_banks() {
+        // A 128 KiB board mirrors its four banks
+        let mut small: Axrom = axrom(4, 0);
+        small.cpu_write(0x8000, 6);
+        assert_eq!(small.cpu_read(0x8000), Some(2));
+        let mut axrom: Axrom = axrom(8, 0);
+        assert_eq!(axrom.cpu_read(0x8000), Some(0));
+        axrom.cpu_write(0x8000, 5);
+        assert_eq!(axrom.cpu_read(0x8000), Some(5));
+        assert_eq!(axrom.cpu_read(0xFFFF), Some(5));
+        // Bits 3 and up don't pick the bank
+        axrom.cpu_write(0x8000, 0b1110_1010);
+        assert_eq!(axrom.cpu_read(0x8000), Some(2));
+    }
+    #[test]
+    fn test_axrom_single_screen() {
+        let mut axrom: Axrom = axrom(8, 0);
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+        axrom.cpu_write(0x8000, NAMETABLE_SELECT | 1);
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenUpper);
+        assert_eq!(axrom.cpu_read(0x8000), Some(1));
+        axrom.cpu_write(0x8000, 1);
+        assert_eq!(axrom.mirroring(), Mirroring::SingleScreenLower);
+    }
+    #[test]
+    fn test_axrom_bus_conflicts() {
+        // AMROM: bank 0 drives 0 on the bus, so nothing gets through
+        let mut amrom: Axrom = axrom(8, 2);
+        amrom.cpu_write(0x8000, 3);
+        assert_eq!(amrom.cpu_read(0x8000), Some(0));
+        // Bank 3 drives 3, so writing 6 gets 3 & 6 = 2
+        amrom.register = 3;
+        amrom.cpu_write(0x8000, 6);
+        assert_eq!(amrom.cpu_read(0x8000), Some(2));
+        // Without them the write goes through as is
+        let mut anrom: Axrom = axrom(8, 0);
+        anrom.cpu_write(0x8000, 3);
+        anrom.cpu_write(0x8000, 6);
+        assert_eq!(anrom.cpu_read(0x8000), Some(6));
+    }
+}
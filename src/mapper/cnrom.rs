@@ -0,0 +1,116 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_ROM;
+
+const CHR_BANK_SIZE: usize = 0x2000;
+
+// Mapper 3, CNROM (Arkanoid, Gradius, Solomon's Key, ...).
+// PRG is laid out like NROM; a latch written through $8000-$FFFF picks the 8 KiB CHR ROM bank.
+pub struct Cnrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    chr_bank: u8,
+}
+
+impl Cnrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Cnrom {
+            bus_conflicts: super::bus_conflicts(&rom, true),
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: rom.screen_mirroring,
+            chr_bank: 0,
+        }
+    }
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank: usize = self.chr_bank as usize * CHR_BANK_SIZE;
+        (bank + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+}
+
+impl Mapper for Cnrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_ROM.. if !self.prg_rom.is_empty() => {
+                Some(self.prg_rom[(addr - PRG_ROM) as usize % self.prg_rom.len()])
+            }
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if addr >= PRG_ROM {
+            self.chr_bank = super::with_bus_conflict(self.bus_conflicts, self.cpu_read(addr), data);
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = self.chr_offset(addr);
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    // 32 KiB of $FF so bus conflicts never get in the way, and four numbered CHR banks
+    fn cnrom(submapper: u8) -> Cnrom {
+        let mut rom: Rom = test_rom(vec![0xFF; 0x8000]);
+        rom.mapper = 3;
+        rom.submapper = submapper;
+        rom.chr_rom = numbered_banks(4, CHR_BANK_SIZE);
+        Cnrom::new(rom)
+    }
+
+    #[test]
+    fn test_cnrom_chr_banks() {
+        let mut cnrom: Cnrom = cnrom(0);
+        assert_eq!(cnrom.ppu_read(0x0000), 0);
+        cnrom.cpu_write(0x8000, 2);
+        assert_eq!(cnrom.ppu_read(0x0000), 2);
+        assert_eq!(cnrom.ppu_read(0x1FFF), 2);
+        cnrom.cpu_write(0xFFFF, 7);
+        assert_eq!(cnrom.ppu_read(0x0000), 3);
+        // CHR ROM can't be written
+        cnrom.ppu_write(0x0000, 0x55);
+        assert_eq!(cnrom.ppu_read(0x0000), 3);
+    }
+    #[test]
+    fn test_cnrom_prg() {
+        let mut rom: Rom = test_rom(vec![0; 0x4000]);
+        rom.prg_rom[0x0000] = 0x11;
+        let cnrom: Cnrom = Cnrom::new(rom);
+        assert_eq!(cnrom.cpu_read(0x8000), Some(0x11));
+        assert_eq!(cnrom.cpu_read(0xC000), Some(0x11));
+    }
+    #[test]
+    fn test_cnrom_bus_conflicts() {
+        // The ROM drives $01 everywhere, so only bit 0 of a write gets through
+        let mut rom: Rom = test_rom(vec![0x01; 0x4000]);
+        rom.chr_rom = numbered_banks(4, CHR_BANK_SIZE);
+        let mut cnrom: Cnrom = Cnrom::new(rom.clone());
+        cnrom.cpu_write(0x8000, 0x03);
+        assert_eq!(cnrom.ppu_read(0x0000), 1);
+        cnrom.cpu_write(0x8000, 0x02);
+        assert_eq!(cnrom.ppu_read(0x0000), 0);
+
+        // Submapper 1 boards have no conflicts
+        rom.submapper = 1;
+        let mut cnrom: Cnrom = Cnrom::new(rom);
+        cnrom.cpu_write(0x8000, 0x02);
+        assert_eq!(cnrom.ppu_read(0x0000), 2);
+    }
+}
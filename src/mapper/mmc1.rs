@@ -0,0 +1,318 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_RAM;
+use crate::mapper::PRG_RAM_END;
+use crate::mapper::PRG_ROM;
+const PRG_BANK_SIZE: usize = 0x4000;
+const PRG_RAM_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x1000;
+
+const SHIFT_RESET: u8 = 0b1000_0000;
+const CONTROL_MIRRORING: u8 = 0b0_0011;
+const CONTROL_PRG_MODE: u8 = 0b0_1100;
+const CONTROL_CHR_4K: u8 = 0b1_0000;
+const PRG_BANK: u8 = 0b0_1111;
+const PRG_RAM_DISABLE: u8 = 0b1_0000;
+// SUROM/SXROM reuse CHR bank bits that CHR RAM boards have no use for
+const CHR_PRG_OUTER_BANK: u8 = 0b1_0000; // Which 256 KiB half of a 512 KiB PRG ROM
+const CHR_PRG_RAM_BANK: u8 = 0b0_1100; // Which 8 KiB of 16/32 KiB PRG RAM
+
+// Mapper 1, Nintendo's MMC1 (SxROM boards: Zelda, Metroid, Final Fantasy, ...).
+// Registers are written one bit at a time: five writes to $8000-$FFFF shift bit 0 of the data
+// into a shift register, and the fifth write's address picks the register that gets it.
+// A write with bit 7 set clears the shift register instead and locks the last PRG bank in.
+//   $8000-$9FFF  control   ...CPPMM  C: CHR 8K/4K, PP: PRG mode, MM: mirroring
+//   $A000-$BFFF  CHR bank 0 (4 KiB at PPU $0000, or 8 KiB with bit 0 ignored)
+//   $C000-$DFFF  CHR bank 1 (4 KiB at PPU $1000, unused in 8 KiB mode)
+//   $E000-$FFFF  PRG bank  ...RPPPP  R: PRG RAM disable, PPPP: 16 KiB bank
+// PRG modes: 0/1 switch 32 KiB at $8000 (bit 0 of the bank ignored), 2 fix the first bank at
+// $8000 and switch $C000, 3 (power on) switch $8000 and fix the last bank at $C000.
+pub struct Mmc1 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+
+    shift: u8,
+    shift_count: u8,
+    control: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    // The MMC1 ignores a write on the cycle right after another one, which is what the
+    // double write of a read-modify-write instruction looks like. With the bus ticked once per
+    // instruction, that means any second write before the next tick.
+    written_since_tick: bool,
+}
+
+impl Mmc1 {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram: Vec<u8> = super::prg_ram(&rom);
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Mmc1 {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            battery: rom.battery,
+            chr,
+            chr_is_ram,
+            shift: 0,
+            shift_count: 0,
+            control: CONTROL_PRG_MODE,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            written_since_tick: false,
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match addr {
+            0x8000..=0x9FFF => self.control = value,
+            0xA000..=0xBFFF => self.chr_bank_0 = value,
+            0xC000..=0xDFFF => self.chr_bank_1 = value,
+            _ => self.prg_bank = value,
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank: u8 = self.prg_bank & PRG_BANK;
+        let upper_half: bool = addr >= 0xC000;
+        let bank: u8 = match ((self.control & CONTROL_PRG_MODE) >> 2, upper_half) {
+            (0 | 1, false) => bank & !1,
+            (0 | 1, true) => bank | 1,
+            (2, false) => 0,
+            (2, true) => bank,
+            (_, false) => bank,
+            (_, true) => PRG_BANK,
+        };
+        // SUROM: 512 KiB is two 256 KiB halves, each banked as above
+        let surom: bool = self.prg_rom.len() > 16 * PRG_BANK_SIZE;
+        let outer: usize = if surom && self.chr_bank_0 & CHR_PRG_OUTER_BANK != 0 { 16 } else { 0 };
+        let offset: usize = (outer + bank as usize) * PRG_BANK_SIZE;
+        (offset + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn prg_ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.prg_ram.is_empty() || self.prg_bank & PRG_RAM_DISABLE != 0 {
+            return None;
+        }
+        let bank: usize = ((self.chr_bank_0 & CHR_PRG_RAM_BANK) >> 2) as usize;
+        Some((bank * PRG_RAM_BANK_SIZE + (addr - PRG_RAM) as usize) % self.prg_ram.len())
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank: u8 = match (self.control & CONTROL_CHR_4K != 0, addr >= 0x1000) {
+            (true, false) => self.chr_bank_0,
+            (true, true) => self.chr_bank_1,
+            (false, false) => self.chr_bank_0 & !1,
+            (false, true) => self.chr_bank_0 | 1,
+        };
+        (bank as usize * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+}
+
+impl Mapper for Mmc1 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram_offset(addr).map(|offset| self.prg_ram[offset]),
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => {
+                if let Some(offset) = self.prg_ram_offset(addr) {
+                    self.prg_ram[offset] = data;
+                }
+            }
+            PRG_ROM.. => {
+                if std::mem::replace(&mut self.written_since_tick, true) {
+                    return;
+                }
+                if data & SHIFT_RESET != 0 {
+                    self.shift = 0;
+                    self.shift_count = 0;
+                    self.control |= CONTROL_PRG_MODE;
+                    return;
+                }
+                self.shift |= (data & 1) << self.shift_count;
+                self.shift_count += 1;
+                if self.shift_count == 5 {
+                    self.write_register(addr, self.shift);
+                    self.shift = 0;
+                    self.shift_count = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = self.chr_offset(addr);
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.control & CONTROL_MIRRORING {
+            0 => Mirroring::SingleScreenLower,
+            1 => Mirroring::SingleScreenUpper,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+    fn cpu_tick(&mut self, _cycles: u8) {
+        self.written_since_tick = false;
+    }
+    fn battery_ram(&self) -> Option<&[u8]> {
+        super::battery_ram(self.battery, &self.prg_ram)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        super::load_battery_ram(self.battery, &mut self.prg_ram, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    fn mmc1(prg_banks: usize, chr_banks: usize) -> Mmc1 {
+        let mut rom: Rom = test_rom(numbered_banks(prg_banks, PRG_BANK_SIZE));
+        rom.mapper = 1;
+        rom.chr_rom = numbered_banks(chr_banks, CHR_BANK_SIZE);
+        Mmc1::new(rom)
+    }
+    // Serial load, one instruction per write
+    fn write(mmc1: &mut Mmc1, addr: u16, value: u8) {
+        for bit in 0..5 {
+            mmc1.cpu_write(addr, (value >> bit) & 1);
+            mmc1.cpu_tick(4);
+        }
+    }
+
+    #[test]
+    fn test_power_on_fixes_last_bank() {
+        let mmc1: Mmc1 = mmc1(8, 2);
+        assert_eq!(mmc1.cpu_read(0x8000), Some(0));
+        assert_eq!(mmc1.cpu_read(0xC000), Some(7));
+    }
+    #[test]
+    fn test_prg_modes() {
+        let mut mmc1: Mmc1 = mmc1(8, 2);
+        write(&mut mmc1, 0xE000, 5);
+        assert_eq!(mmc1.cpu_read(0x8000), Some(5));
+        assert_eq!(mmc1.cpu_read(0xC000), Some(7));
+
+        write(&mut mmc1, 0x8000, 0b0_1000); // Fix first
+        assert_eq!(mmc1.cpu_read(0x8000), Some(0));
+        assert_eq!(mmc1.cpu_read(0xC000), Some(5));
+
+        write(&mut mmc1, 0x8000, 0b0_0000); // 32 KiB, low bit of the bank ignored
+        assert_eq!(mmc1.cpu_read(0x8000), Some(4));
+        assert_eq!(mmc1.cpu_read(0xC000), Some(5));
+    }
+    #[test]
+    fn test_reset_bit() {
+        let mut mmc1: Mmc1 = mmc1(8, 2);
+        write(&mut mmc1, 0x8000, 0b0_0000);
+        write(&mut mmc1, 0xE000, 2);
+        // Two bits in, then a reset: the shift register starts over and the last bank is fixed
+        mmc1.cpu_write(0xE000, 1);
+        mmc1.cpu_tick(4);
+        mmc1.cpu_write(0xE000, 1);
+        mmc1.cpu_tick(4);
+        mmc1.cpu_write(0x8000, 0x80);
+        mmc1.cpu_tick(4);
+        assert_eq!(mmc1.cpu_read(0x8000), Some(2));
+        assert_eq!(mmc1.cpu_read(0xC000), Some(7));
+        write(&mut mmc1, 0xE000, 3);
+        assert_eq!(mmc1.cpu_read(0x8000), Some(3));
+    }
+    #[test]
+    fn test_consecutive_writes_are_ignored() {
+        let mut mmc1: Mmc1 = mmc1(8, 2);
+        for bit in [1, 0, 1, 0, 0] {
+            mmc1.cpu_write(0xE000, bit);
+            mmc1.cpu_write(0xE000, bit ^ 1); // Same instruction, dropped
+            mmc1.cpu_tick(6);
+        }
+        assert_eq!(mmc1.cpu_read(0x8000), Some(5));
+    }
+    #[test]
+    fn test_chr_banking() {
+        let mut mmc1: Mmc1 = mmc1(2, 8);
+        write(&mut mmc1, 0xA000, 5);
+        // 8 KiB mode uses bank 0 with bit 0 ignored
+        assert_eq!(mmc1.ppu_read(0x0000), 4);
+        assert_eq!(mmc1.ppu_read(0x1000), 5);
+
+        write(&mut mmc1, 0x8000, CONTROL_CHR_4K | CONTROL_PRG_MODE);
+        write(&mut mmc1, 0xC000, 2);
+        assert_eq!(mmc1.ppu_read(0x0000), 5);
+        assert_eq!(mmc1.ppu_read(0x1000), 2);
+    }
+    #[test]
+    fn test_mirroring_control() {
+        let mut mmc1: Mmc1 = mmc1(2, 2);
+        for (bits, mirroring) in [
+            (0, Mirroring::SingleScreenLower),
+            (1, Mirroring::SingleScreenUpper),
+            (2, Mirroring::Vertical),
+            (3, Mirroring::Horizontal),
+        ] {
+            write(&mut mmc1, 0x8000, CONTROL_PRG_MODE | bits);
+            assert_eq!(mmc1.mirroring(), mirroring);
+        }
+    }
+    #[test]
+    fn test_prg_ram_and_battery() {
+        let mut rom: Rom = test_rom(vec![0; 2 * PRG_BANK_SIZE]);
+        rom.battery = true;
+        let mut mmc1: Mmc1 = Mmc1::new(rom);
+        mmc1.cpu_write(0x6000, 0x42);
+        assert_eq!(mmc1.cpu_read(0x6000), Some(0x42));
+        assert_eq!(mmc1.battery_ram().unwrap()[0], 0x42);
+
+        write(&mut mmc1, 0xE000, PRG_RAM_DISABLE);
+        assert_eq!(mmc1.cpu_read(0x6000), None);
+        mmc1.cpu_write(0x6000, 0x99);
+        write(&mut mmc1, 0xE000, 0);
+        assert_eq!(mmc1.cpu_read(0x6000), Some(0x42));
+
+        mmc1.load_battery_ram(&[1, 2, 3]);
+        assert_eq!(mmc1.cpu_read(0x6002), Some(3));
+        assert_eq!(mmc1.cpu_read(0x6003), Some(0));
+    }
+    #[test]
+    fn test_surom_outer_bank() {
+        let mut rom: Rom = test_rom(numbered_banks(32, PRG_BANK_SIZE));
+        rom.chr_rom = vec![];
+        let mut mmc1: Mmc1 = Mmc1::new(rom);
+        assert_eq!(mmc1.cpu_read(0xC000), Some(15));
+        write(&mut mmc1, 0xA000, CHR_PRG_OUTER_BANK);
+        write(&mut mmc1, 0xE000, 3);
+        assert_eq!(mmc1.cpu_read(0x8000), Some(19));
+        assert_eq!(mmc1.cpu_read(0xC000), Some(31));
+    }
+    #[test]
+    fn test_sxrom_prg_ram_banks() {
+        let mut rom: Rom = test_rom(vec![0; 2 * PRG_BANK_SIZE]);
+        rom.prg_ram_size = 4 * PRG_RAM_BANK_SIZE;
+        rom.chr_rom = vec![];
+        let mut mmc1: Mmc1 = Mmc1::new(rom);
+        mmc1.cpu_write(0x6000, 0x11);
+        write(&mut mmc1, 0xA000, 0b0_1000);
+        assert_eq!(mmc1.cpu_read(0x6000), Some(0x00));
+        mmc1.cpu_write(0x6000, 0x22);
+        write(&mut mmc1, 0xA000, 0b0_0000);
+        assert_eq!(mmc1.cpu_read(0x6000), Some(0x11));
+        // CHR RAM still works with the extra bits set
+        mmc1.ppu_write(0x0010, 0x33);
+        assert_eq!(mmc1.ppu_read(0x0010), 0x33);
+    }
+}
@@ -0,0 +1,388 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_RAM;
+use crate::mapper::PRG_RAM_END;
+use crate::mapper::PRG_ROM;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const BANK_SELECT_REGISTER: u8 = 0b0000_0111;
+const BANK_SELECT_PRG_MODE: u8 = 0b0100_0000;
+const BANK_SELECT_CHR_INVERSION: u8 = 0b1000_0000;
+const MIRRORING_HORIZONTAL: u8 = 0b0000_0001;
+const PRG_RAM_WRITE_PROTECT: u8 = 0b0100_0000;
+const PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+// A12 has to stay low for about three CPU cycles before a rise clocks the IRQ counter, so
+// the quick toggling between nametable and pattern fetches doesn't count
+const A12_LOW_DOTS: u64 = 10;
+
+// Mapper 4, Nintendo's MMC3 (TxROM boards: Super Mario Bros. 3, Kirby's Adventure, ...).
+// Registers come in even/odd pairs, mirrored through each 8 KiB:
+//   $8000  bank select  CP...RRR  C: CHR A12 inversion, P: PRG mode, RRR: register for $8001
+//   $8001  bank data    R0/R1: 2 KiB CHR, R2-R5: 1 KiB CHR, R6/R7: 8 KiB PRG
+//   $A000  mirroring    .......M  0: vertical, 1: horizontal (no effect on four-screen boards)
+//   $A001  PRG RAM      EW......  E: enable, W: deny writes
+//   $C000  IRQ latch    value the counter is reloaded with
+//   $C001  IRQ reload   clears the counter so the next clock reloads it
+//   $E000  IRQ disable  also acknowledges a pending IRQ
+//   $E001  IRQ enable
+// PRG mode 0 maps R6, R7, then the last two banks at $8000-$FFFF; mode 1 swaps R6 with the
+// second to last bank. CHR maps R0, R1 (2 KiB each) at $0000 and R2-R5 at $1000, or the other
+// way round with inversion set.
+//
+// The IRQ counter is clocked by rises of PPU A12, once per scanline when the background and
+// sprites use different pattern tables. A clock reloads a zero counter from the latch and
+// decrements any other. The MMC3B and later raise the IRQ whenever the counter is zero after a
+// clock; the MMC3A (NES 2.0 submapper 4) only when it got there by decrementing or by a reload
+// asked for through $C001, so a latch of 0 gives a single IRQ instead of one every scanline.
+pub struct Mmc3 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    four_screen: bool,
+    mmc3a_irq: bool,
+
+    bank_select: u8,
+    banks: [u8; 8],
+    mirroring: u8,
+    prg_ram_protect: u8,
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+    a12_low_since: Option<u64>, // PPU dot A12 last went low, None while it's high
+}
+
+impl Mmc3 {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram: Vec<u8> = super::prg_ram(&rom);
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Mmc3 {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            battery: rom.battery,
+            chr,
+            chr_is_ram,
+            four_screen: rom.screen_mirroring == Mirroring::FourScreen,
+            mmc3a_irq: rom.submapper == 4,
+            bank_select: 0,
+            banks: [0, 2, 4, 5, 6, 7, 0, 1],
+            mirroring: 0,
+            // Undefined at power on, and some games use the RAM without ever enabling it
+            prg_ram_protect: PRG_RAM_ENABLE,
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+            a12_low_since: Some(0),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        match (addr & 0xE000, addr & 1) {
+            (0x8000, 0) => self.bank_select = value,
+            (0x8000, _) => self.banks[(self.bank_select & BANK_SELECT_REGISTER) as usize] = value,
+            (0xA000, 0) => self.mirroring = value,
+            (0xA000, _) => self.prg_ram_protect = value,
+            (0xC000, 0) => self.irq_latch = value,
+            (0xC000, _) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (_, 0) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (_, _) => self.irq_enabled = true,
+        }
+    }
+
+    fn clock_irq_counter(&mut self) {
+        let reloaded_by_write: bool = self.irq_reload;
+        let was_zero: bool = self.irq_counter == 0;
+        if was_zero || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+        let fires: bool = if self.mmc3a_irq {
+            self.irq_counter == 0 && (!was_zero || reloaded_by_write)
+        } else {
+            self.irq_counter == 0
+        };
+        if fires && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let last: usize = self.prg_rom.len() / PRG_BANK_SIZE - 1;
+        let swapped: bool = self.bank_select & BANK_SELECT_PRG_MODE != 0;
+        let bank: usize = match ((addr - PRG_ROM) as usize / PRG_BANK_SIZE, swapped) {
+            (0, false) | (2, true) => (self.banks[6] & 0b0011_1111) as usize,
+            (0, true) | (2, false) => last - 1,
+            (1, _) => (self.banks[7] & 0b0011_1111) as usize,
+            (_, _) => last,
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let addr: u16 = if self.bank_select & BANK_SELECT_CHR_INVERSION != 0 {
+            addr ^ 0x1000
+        } else {
+            addr
+        };
+        let bank: usize = match addr / CHR_BANK_SIZE as u16 {
+            0 => (self.banks[0] & !1) as usize,
+            1 => (self.banks[0] | 1) as usize,
+            2 => (self.banks[1] & !1) as usize,
+            3 => (self.banks[1] | 1) as usize,
+            slot => self.banks[slot as usize - 2] as usize,
+        };
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn prg_ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.prg_ram.is_empty() || self.prg_ram_protect & PRG_RAM_ENABLE == 0 {
+            return None;
+        }
+        Some((addr - PRG_RAM) as usize % self.prg_ram.len())
+    }
+}
+
+impl Mapper for Mmc3 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram_offset(addr).map(|offset| self.prg_ram[offset]),
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => {
+                if self.prg_ram_protect & PRG_RAM_WRITE_PROTECT != 0 {
+                    return;
+                }
+                if let Some(offset) = self.prg_ram_offset(addr) {
+                    self.prg_ram[offset] = data;
+                }
+            }
+            PRG_ROM.. => self.write_register(addr, data),
+            _ => {}
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = self.chr_offset(addr);
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        if self.four_screen {
+            Mirroring::FourScreen
+        } else if self.mirroring & MIRRORING_HORIZONTAL != 0 {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        }
+    }
+    fn irq_line(&self) -> bool {
+        self.irq_pending
+    }
+    fn ppu_a12(&mut self, high: bool, dot: u64) {
+        if !high {
+            self.a12_low_since = Some(dot);
+        } else if let Some(low_since) = self.a12_low_since.take() {
+            if dot - low_since >= A12_LOW_DOTS {
+                self.clock_irq_counter();
+            }
+        }
+    }
+    fn battery_ram(&self) -> Option<&[u8]> {
+        super::battery_ram(self.battery, &self.prg_ram)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        super::load_battery_ram(self.battery, &mut self.prg_ram, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+    use crate::ppu::NesPPU;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn mmc3_rom(prg_banks: usize, chr_banks: usize) -> Rom {
+        let mut rom: Rom = test_rom(numbered_banks(prg_banks, PRG_BANK_SIZE));
+        rom.mapper = 4;
+        rom.chr_rom = numbered_banks(chr_banks, CHR_BANK_SIZE);
+        rom
+    }
+    fn mmc3(prg_banks: usize, chr_banks: usize) -> Mmc3 {
+        Mmc3::new(mmc3_rom(prg_banks, chr_banks))
+    }
+    fn clock_scanline(mmc3: &mut Mmc3, dot: &mut u64) {
+        *dot += 341;
+        mmc3.ppu_a12(true, *dot);
+        mmc3.ppu_a12(false, *dot + 8);
+    }
+
+    #[test]
+    fn test_mmc3_prg_modes() {
+        let mut mmc3: Mmc3 = mmc3(16, 8);
+        mmc3.cpu_write(0x8000, 6);
+        mmc3.cpu_write(0x8001, 3);
+        mmc3.cpu_write(0x8000, 7);
+        mmc3.cpu_write(0x8001, 9);
+        assert_eq!(mmc3.cpu_read(0x8000), Some(3));
+        assert_eq!(mmc3.cpu_read(0xA000), Some(9));
+        assert_eq!(mmc3.cpu_read(0xC000), Some(14));
+        assert_eq!(mmc3.cpu_read(0xE000), Some(15));
+
+        // Mode 1 swaps $8000 and $C000; the registers are mirrored up to $9FFF
+        mmc3.cpu_write(0x9FFE, BANK_SELECT_PRG_MODE | 6);
+        assert_eq!(mmc3.cpu_read(0x8000), Some(14));
+        assert_eq!(mmc3.cpu_read(0xA000), Some(9));
+        assert_eq!(mmc3.cpu_read(0xC000), Some(3));
+        assert_eq!(mmc3.cpu_read(0xE000), Some(15));
+    }
+    #[test]
+    fn test_mmc3_chr_banks() {
+        let mut mmc3: Mmc3 = mmc3(4, 32);
+        for (register, bank) in [(0, 9), (1, 12), (2, 20), (3, 21), (4, 22), (5, 31)] {
+            mmc3.cpu_write(0x8000, register);
+            mmc3.cpu_write(0x8001, bank);
+        }
+        // 2 KiB banks ignore bit 0
+        let banks = |mmc3: &Mmc3| -> Vec<u8> {
+            (0..8).map(|slot| mmc3.ppu_read(slot * 0x400)).collect()
+        };
+        assert_eq!(banks(&mmc3), vec![8, 9, 12, 13, 20, 21, 22, 31]);
+        mmc3.cpu_write(0x8000, BANK_SELECT_CHR_INVERSION);
+        assert_eq!(banks(&mmc3), vec![20, 21, 22, 31, 8, 9, 12, 13]);
+    }
+    #[test]
+    fn test_mmc3_mirroring_and_prg_ram() {
+        let mut mmc3: Mmc3 = mmc3(4, 8);
+        assert_eq!(mmc3.mirroring(), Mirroring::Vertical);
+        mmc3.cpu_write(0xA000, 1);
+        assert_eq!(mmc3.mirroring(), Mirroring::Horizontal);
+
+        mmc3.cpu_write(0x6000, 0x12);
+        assert_eq!(mmc3.cpu_read(0x6000), Some(0x12));
+        mmc3.cpu_write(0xA001, PRG_RAM_ENABLE | PRG_RAM_WRITE_PROTECT);
+        mmc3.cpu_write(0x6000, 0x34);
+        assert_eq!(mmc3.cpu_read(0x6000), Some(0x12));
+        mmc3.cpu_write(0xA001, 0);
+        assert_eq!(mmc3.cpu_read(0x6000), None);
+
+        let mut rom: Rom = mmc3_rom(4, 8);
+        rom.screen_mirroring = Mirroring::FourScreen;
+        let mut mmc3: Mmc3 = Mmc3::new(rom);
+        mmc3.cpu_write(0xA000, 1);
+        assert_eq!(mmc3.mirroring(), Mirroring::FourScreen);
+    }
+    #[test]
+    fn test_mmc3_irq_counter() {
+        let mut mmc3: Mmc3 = mmc3(4, 8);
+        let mut dot: u64 = 0;
+        mmc3.cpu_write(0xC000, 3);
+        mmc3.cpu_write(0xC001, 0);
+        mmc3.cpu_write(0xE001, 0);
+        clock_scanline(&mut mmc3, &mut dot); // reload to 3
+        clock_scanline(&mut mmc3, &mut dot);
+        clock_scanline(&mut mmc3, &mut dot);
+        assert!(!mmc3.irq_line());
+        clock_scanline(&mut mmc3, &mut dot);
+        assert!(mmc3.irq_line());
+        // Held until acknowledged
+        clock_scanline(&mut mmc3, &mut dot);
+        assert!(mmc3.irq_line());
+        mmc3.cpu_write(0xE000, 0);
+        assert!(!mmc3.irq_line());
+        // Disabled: the counter keeps going but nothing fires
+        for _ in 0..8 {
+            clock_scanline(&mut mmc3, &mut dot);
+        }
+        assert!(!mmc3.irq_line());
+    }
+    #[test]
+    fn test_mmc3_a12_filter() {
+        let mut mmc3: Mmc3 = mmc3(4, 8);
+        mmc3.cpu_write(0xC000, 1);
+        mmc3.cpu_write(0xE001, 0);
+        // Background fetches from $1000: A12 drops for 4 dots between tiles
+        for tile in 1..34 {
+            mmc3.ppu_a12(true, tile * 8 + 5);
+            mmc3.ppu_a12(false, tile * 8 + 9);
+        }
+        assert_eq!(mmc3.irq_counter, 1);
+        // Sprites from $0000 keep it low long enough for the next background fetch to count
+        mmc3.ppu_a12(true, 1000);
+        mmc3.ppu_a12(false, 1004);
+        mmc3.ppu_a12(true, 1008);
+        assert_eq!(mmc3.irq_counter, 0);
+        assert!(mmc3.irq_line());
+    }
+    #[test]
+    fn test_mmc3_latch_zero_revisions() {
+        // MMC3B: a latch of 0 fires on every scanline
+        let mut mmc3b: Mmc3 = mmc3(4, 8);
+        let mut dot: u64 = 0;
+        mmc3b.cpu_write(0xE001, 0);
+        clock_scanline(&mut mmc3b, &mut dot);
+        assert!(mmc3b.irq_line());
+        mmc3b.cpu_write(0xE000, 0);
+        mmc3b.cpu_write(0xE001, 0);
+        clock_scanline(&mut mmc3b, &mut dot);
+        assert!(mmc3b.irq_line());
+
+        // MMC3A: only after a $C001 reload
+        let mut rom: Rom = mmc3_rom(4, 8);
+        rom.submapper = 4;
+        let mut mmc3a: Mmc3 = Mmc3::new(rom);
+        let mut dot: u64 = 0;
+        mmc3a.cpu_write(0xE001, 0);
+        clock_scanline(&mut mmc3a, &mut dot);
+        assert!(!mmc3a.irq_line());
+        mmc3a.cpu_write(0xC001, 0);
+        clock_scanline(&mut mmc3a, &mut dot);
+        assert!(mmc3a.irq_line());
+        mmc3a.cpu_write(0xE000, 0);
+        mmc3a.cpu_write(0xE001, 0);
+        clock_scanline(&mut mmc3a, &mut dot);
+        assert!(!mmc3a.irq_line());
+    }
+    #[test]
+    fn test_mmc3_counts_rendered_scanlines() {
+        // Background at $0000, sprites at $1000: one clock per rendered line, at dot 260 or so
+        let mmc3: Rc<RefCell<Mmc3>> = Rc::new(RefCell::new(mmc3(4, 8)));
+        let mut ppu: NesPPU = NesPPU::new(mmc3.clone());
+        ppu.tick(261 * 341);
+        mmc3.borrow_mut().cpu_write(0xC000, 9);
+        mmc3.borrow_mut().cpu_write(0xC001, 0);
+        mmc3.borrow_mut().cpu_write(0xE001, 0);
+        ppu.write_to_ctrl(0b0000_1000);
+        ppu.write_to_mask(0b0001_1000);
+        // The pre-render line reloads the counter, then lines 0-8 count it down to 0
+        ppu.tick(10 * 341 - 100);
+        assert!(!mmc3.borrow().irq_line());
+        ppu.tick(200);
+        assert!(mmc3.borrow().irq_line());
+        assert_eq!(ppu.scanline, 9);
+    }
+}
@@ -0,0 +1,143 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_RAM;
+use crate::mapper::PRG_RAM_END;
+use crate::mapper::PRG_ROM;
+
+// Mapper 0: no registers, no bank switching.
+// $6000-$7FFF  PRG RAM, when the board has any (Family BASIC does, most games don't)
+// $8000-$FFFF  16 KiB of PRG ROM mirrored twice (NROM-128) or 32 KiB (NROM-256)
+// PPU $0000-$1FFF  8 KiB of CHR ROM, or CHR RAM when the file has none
+// Mirroring is soldered on the board and comes from the header.
+pub struct Nrom {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+}
+
+impl Nrom {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram: Vec<u8> = super::prg_ram(&rom);
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Nrom {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            battery: rom.battery,
+            chr,
+            chr_is_ram,
+            mirroring: rom.screen_mirroring,
+        }
+    }
+    // A board with only CHR ROM on it, for driving the PPU on its own
+    pub fn from_chr(chr: Vec<u8>, mirroring: Mirroring) -> Self {
+        Nrom {
+            prg_rom: vec![],
+            prg_ram: vec![],
+            battery: false,
+            chr,
+            chr_is_ram: false,
+            mirroring,
+        }
+    }
+}
+
+impl Mapper for Nrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END if !self.prg_ram.is_empty() => {
+                Some(self.prg_ram[(addr - PRG_RAM) as usize % self.prg_ram.len()])
+            }
+            PRG_ROM.. if !self.prg_rom.is_empty() => {
+                Some(self.prg_rom[(addr - PRG_ROM) as usize % self.prg_rom.len()])
+            }
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if (PRG_RAM..=PRG_RAM_END).contains(&addr) && !self.prg_ram.is_empty() {
+            let len: usize = self.prg_ram.len();
+            self.prg_ram[(addr - PRG_RAM) as usize % len] = data;
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = addr as usize % self.chr.len();
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+    fn battery_ram(&self) -> Option<&[u8]> {
+        super::battery_ram(self.battery, &self.prg_ram)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        super::load_battery_ram(self.battery, &mut self.prg_ram, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::test_rom;
+
+    #[test]
+    fn test_nrom_128_is_mirrored() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+        prg_rom[0x0000] = 0x11;
+        prg_rom[0x3FFF] = 0x22;
+        let nrom: Nrom = Nrom::new(test_rom(prg_rom));
+        assert_eq!(nrom.cpu_read(0x8000), Some(0x11));
+        assert_eq!(nrom.cpu_read(0xC000), Some(0x11));
+        assert_eq!(nrom.cpu_read(0xBFFF), Some(0x22));
+        assert_eq!(nrom.cpu_read(0xFFFF), Some(0x22));
+    }
+    #[test]
+    fn test_nrom_256() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x8000];
+        prg_rom[0x0000] = 0x11;
+        prg_rom[0x4000] = 0x22;
+        let mut nrom: Nrom = Nrom::new(test_rom(prg_rom));
+        assert_eq!(nrom.cpu_read(0x8000), Some(0x11));
+        assert_eq!(nrom.cpu_read(0xC000), Some(0x22));
+        // ROM ignores writes
+        nrom.cpu_write(0x8000, 0x33);
+        assert_eq!(nrom.cpu_read(0x8000), Some(0x11));
+    }
+    #[test]
+    fn test_nrom_prg_ram() {
+        let mut nrom: Nrom = Nrom::new(test_rom(vec![0; 0x4000]));
+        nrom.cpu_write(0x6000, 0x12);
+        nrom.cpu_write(0x7FFF, 0x34);
+        assert_eq!(nrom.cpu_read(0x6000), Some(0x12));
+        assert_eq!(nrom.cpu_read(0x7FFF), Some(0x34));
+        // Nothing below $6000
+        assert_eq!(nrom.cpu_read(0x5000), None);
+
+        let mut rom: Rom = test_rom(vec![0; 0x4000]);
+        rom.prg_ram_size = 0;
+        let mut nrom: Nrom = Nrom::new(rom);
+        nrom.cpu_write(0x6000, 0x12);
+        assert_eq!(nrom.cpu_read(0x6000), None);
+    }
+    #[test]
+    fn test_nrom_chr_rom_and_ram() {
+        let mut rom: Rom = test_rom(vec![0; 0x4000]);
+        rom.chr_rom[0x1234] = 0x56;
+        let mut nrom: Nrom = Nrom::new(rom);
+        assert_eq!(nrom.ppu_read(0x1234), 0x56);
+        nrom.ppu_write(0x1234, 0x78);
+        assert_eq!(nrom.ppu_read(0x1234), 0x56);
+
+        let mut rom: Rom = test_rom(vec![0; 0x4000]);
+        rom.chr_rom = vec![];
+        let mut nrom: Nrom = Nrom::new(rom);
+        nrom.ppu_write(0x1FFF, 0x78);
+        assert_eq!(nrom.ppu_read(0x1FFF), 0x78);
+    }
+}
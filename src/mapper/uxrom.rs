@@ -0,0 +1,109 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_ROM;
+
+const PRG_BANK_SIZE: usize = 0x4000;
+
+// Mapper 2, UxROM (UNROM, UOROM: Mega Man, Castlevania, Contra, ...).
+// A 74HC161 latch on the data bus picks the 16 KiB bank at $8000-$BFFF; $C000-$FFFF always
+// holds the last bank. CHR is 8 KiB of unbanked RAM, and mirroring is fixed by the board.
+pub struct Uxrom {
+    prg_rom: Vec<u8>,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    mirroring: Mirroring,
+    bus_conflicts: bool,
+    prg_bank: u8,
+}
+
+impl Uxrom {
+    pub fn new(rom: Rom) -> Self {
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Uxrom {
+            bus_conflicts: super::bus_conflicts(&rom, true),
+            prg_rom: rom.prg_rom,
+            chr,
+            chr_is_ram,
+            mirroring: rom.screen_mirroring,
+            prg_bank: 0,
+        }
+    }
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let banks: usize = self.prg_rom.len() / PRG_BANK_SIZE;
+        let bank: usize = if addr < 0xC000 {
+            self.prg_bank as usize % banks
+        } else {
+            banks - 1
+        };
+        bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))
+    }
+}
+
+impl Mapper for Uxrom {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        if addr >= PRG_ROM && !self.prg_rom.is_empty() {
+            self.prg_bank = super::with_bus_conflict(self.bus_conflicts, self.cpu_read(addr), data);
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[addr as usize % self.chr.len()]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = addr as usize % self.chr.len();
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    fn uxrom(banks: usize, submapper: u8) -> Uxrom {
+        let mut rom: Rom = test_rom(numbered_banks(banks, PRG_BANK_SIZE));
+        rom.mapper = 2;
+        rom.submapper = submapper;
+        rom.chr_rom = vec![];
+        Uxrom::new(rom)
+    }
+
+    #[test]
+    fn test_uxrom_banks() {
+        let mut uxrom: Uxrom = uxrom(8, 1);
+        assert_eq!(uxrom.cpu_read(0x8000), Some(0));
+        assert_eq!(uxrom.cpu_read(0xC000), Some(7));
+        uxrom.cpu_write(0x8000, 3);
+        assert_eq!(uxrom.cpu_read(0xBFFF), Some(3));
+        assert_eq!(uxrom.cpu_read(0xFFFF), Some(7));
+        // Banks past the end of the ROM wrap around
+        uxrom.cpu_write(0xFFFF, 13);
+        assert_eq!(uxrom.cpu_read(0x8000), Some(5));
+    }
+    #[test]
+    fn test_uxrom_bus_conflicts() {
+        // The ROM drives 7 at $C000 while the CPU writes 6, and 7 & 6 = 6
+        let mut uxrom: Uxrom = uxrom(8, 0);
+        uxrom.cpu_write(0xC000, 0b0110);
+        assert_eq!(uxrom.cpu_read(0x8000), Some(0b0110));
+        // With bank 6 at $8000, writing 5 there gets 6 & 5 = 4
+        uxrom.cpu_write(0x8000, 0b0101);
+        assert_eq!(uxrom.cpu_read(0x8000), Some(0b0100));
+    }
+    #[test]
+    fn test_uxrom_chr_ram() {
+        let mut uxrom: Uxrom = uxrom(2, 0);
+        uxrom.ppu_write(0x1234, 0x56);
+        assert_eq!(uxrom.ppu_read(0x1234), 0x56);
+    }
+}
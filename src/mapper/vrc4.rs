@@ -0,0 +1,264 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::vrc_irq::VrcIrq;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_RAM;
+use crate::mapper::PRG_RAM_END;
+use crate::mapper::PRG_ROM;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const PRG_SWAP_MODE: u8 = 0b0000_0010;
+
+// Mappers 21, 22, 23 and 25: Konami's VRC2 and VRC4 (Gradius II, Ganbare Goemon 2, Contra J).
+// The chip decodes $8000-$FFFF in 4 KiB blocks, each with four registers picked by its A0 and
+// A1 pins, and every board wires those to different CPU address lines (the NES 2.0 submapper
+// says which; plain iNES files get both candidate lines ORed together):
+//   $8000-$8003  PRG bank at $8000 (or $C000 in swap mode), 8 KiB
+//   $9000-$9001  mirroring  ......MM  vertical, horizontal, one-screen lower, upper
+//   $9002-$9003  VRC4 only: ......S.  S: PRG swap mode
+//   $A000-$A003  PRG bank at $A000
+//   $B000-$E003  CHR banks, two per block, each written as a low then a high nibble
+//   $F000-$F003  VRC4 only: IRQ latch low, latch high, control, acknowledge
+// The second to last PRG bank sits at $C000 (or $8000 in swap mode), the last at $E000.
+// The VRC2 is the same chip without the IRQ, the swap mode or one-screen mirroring; on the
+// VRC2a (mapper 22) the board drops CHR A10, so bank numbers go in one bit too high.
+pub struct Vrc4 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    vrc2: bool,
+    chr_shift: u8,
+    // The CPU address lines wired to the chip's A0 and A1
+    a0: u16,
+    a1: u16,
+
+    prg_banks: [u8; 2],
+    prg_swap: bool,
+    mirroring: u8,
+    chr_banks: [u16; 8],
+    irq: VrcIrq,
+}
+
+impl Vrc4 {
+    pub fn new(rom: Rom) -> Self {
+        let (vrc2, a0, a1): (bool, u16, u16) = match (rom.mapper, rom.submapper) {
+            (21, 1) => (false, 0x02, 0x04), // VRC4a
+            (21, 2) => (false, 0x40, 0x80), // VRC4c
+            (21, _) => (false, 0x42, 0x84),
+            (22, _) => (true, 0x02, 0x01), // VRC2a
+            (23, 1) => (false, 0x01, 0x02), // VRC4f
+            (23, 2) => (false, 0x04, 0x08), // VRC4e
+            (23, 3) => (true, 0x01, 0x02),  // VRC2b
+            (23, _) => (false, 0x05, 0x0A),
+            (25, 1) => (false, 0x02, 0x01), // VRC4b
+            (25, 2) => (false, 0x08, 0x04), // VRC4d
+            (25, 3) => (true, 0x02, 0x01),  // VRC2c
+            (_, _) => (false, 0x0A, 0x05),
+        };
+        let prg_ram: Vec<u8> = super::prg_ram(&rom);
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Vrc4 {
+            chr_shift: if rom.mapper == 22 { 1 } else { 0 },
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            battery: rom.battery,
+            chr,
+            chr_is_ram,
+            vrc2,
+            a0,
+            a1,
+            prg_banks: [0, 0],
+            prg_swap: false,
+            mirroring: 0,
+            chr_banks: [0; 8],
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        let register: u8 = (addr & self.a0 != 0) as u8 | ((addr & self.a1 != 0) as u8) << 1;
+        match (addr & 0xF000, register) {
+            (0x8000, _) => self.prg_banks[0] = value & 0b0001_1111,
+            (0x9000, _) if self.vrc2 => self.mirroring = value & 0b0000_0001,
+            (0x9000, 0 | 1) => self.mirroring = value & 0b0000_0011,
+            (0x9000, _) => self.prg_swap = value & PRG_SWAP_MODE != 0,
+            (0xA000, _) => self.prg_banks[1] = value & 0b0001_1111,
+            (0xB000..=0xE000, _) => {
+                let block: usize = ((addr & 0xF000) - 0xB000) as usize / 0x1000;
+                let index: usize = block * 2 + (register >> 1) as usize;
+                let bank: u16 = self.chr_banks[index];
+                self.chr_banks[index] = if register & 1 == 0 {
+                    (bank & !0x0F) | (value & 0x0F) as u16
+                } else {
+                    (bank & 0x0F) | ((value & 0x1F) as u16) << 4
+                };
+            }
+            (_, _) if self.vrc2 => {}
+            (_, 0) => self.irq.write_latch_low(value),
+            (_, 1) => self.irq.write_latch_high(value),
+            (_, 2) => self.irq.write_control(value),
+            (_, _) => self.irq.acknowledge(),
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let last: usize = self.prg_rom.len() / PRG_BANK_SIZE - 1;
+        let bank: usize = match ((addr - PRG_ROM) as usize / PRG_BANK_SIZE, self.prg_swap) {
+            (0, false) | (2, true) => self.prg_banks[0] as usize,
+            (0, true) | (2, false) => last - 1,
+            (1, _) => self.prg_banks[1] as usize,
+            (_, _) => last,
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank: u16 = self.chr_banks[addr as usize / CHR_BANK_SIZE] >> self.chr_shift;
+        let bank: usize = bank as usize;
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+}
+
+impl Mapper for Vrc4 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END if !self.prg_ram.is_empty() => {
+                Some(self.prg_ram[(addr - PRG_RAM) as usize % self.prg_ram.len()])
+            }
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END if !self.prg_ram.is_empty() => {
+                let len: usize = self.prg_ram.len();
+                self.prg_ram[(addr - PRG_RAM) as usize % len] = data;
+            }
+            PRG_ROM.. => self.write_register(addr, data),
+            _ => {}
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = self.chr_offset(addr);
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_line(&self) -> bool {
+        self.irq.line()
+    }
+    fn cpu_tick(&mut self, cycles: u8) {
+        self.irq.tick(cycles);
+    }
+    fn battery_ram(&self) -> Option<&[u8]> {
+        super::battery_ram(self.battery, &self.prg_ram)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        super::load_battery_ram(self.battery, &mut self.prg_ram, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    fn vrc4(mapper: u16, submapper: u8) -> Vrc4 {
+        let mut rom: Rom = test_rom(numbered_banks(16, PRG_BANK_SIZE));
+        rom.mapper = mapper;
+        rom.submapper = submapper;
+        rom.chr_rom = numbered_banks(256, CHR_BANK_SIZE);
+        Vrc4::new(rom)
+    }
+
+    #[test]
+    fn test_vrc4_prg_banks_and_swap_mode() {
+        let mut vrc4: Vrc4 = vrc4(21, 1);
+        vrc4.cpu_write(0x8000, 3);
+        vrc4.cpu_write(0xA000, 5);
+        assert_eq!(vrc4.cpu_read(0x8000), Some(3));
+        assert_eq!(vrc4.cpu_read(0xA000), Some(5));
+        assert_eq!(vrc4.cpu_read(0xC000), Some(14));
+        assert_eq!(vrc4.cpu_read(0xE000), Some(15));
+        // VRC4a has A1 on CPU A2, so $9004 is the swap mode register
+        vrc4.cpu_write(0x9004, PRG_SWAP_MODE);
+        assert_eq!(vrc4.cpu_read(0x8000), Some(14));
+        assert_eq!(vrc4.cpu_read(0xC000), Some(3));
+        vrc4.cpu_write(0x9000, 3);
+        assert_eq!(vrc4.mirroring(), Mirroring::SingleScreenUpper);
+    }
+    #[test]
+    fn test_vrc4_chr_nibbles_on_each_wiring() {
+        // Mapper, submapper, then where the high nibble of CHR bank 0 and the low nibble of
+        // bank 1 go
+        for (mapper, submapper, high, next) in [
+            (21, 1, 0xB002, 0xB004),
+            (21, 2, 0xB040, 0xB080),
+            (21, 0, 0xB040, 0xB004),
+            (23, 1, 0xB001, 0xB002),
+            (23, 2, 0xB004, 0xB008),
+            (23, 0, 0xB004, 0xB002),
+            (25, 1, 0xB002, 0xB001),
+            (25, 2, 0xB008, 0xB004),
+            (25, 0, 0xB002, 0xB004),
+        ] {
+            let mut vrc4: Vrc4 = vrc4(mapper, submapper);
+            vrc4.cpu_write(0xB000, 0x05);
+            vrc4.cpu_write(high, 0x03);
+            vrc4.cpu_write(next, 0x07);
+            assert_eq!(vrc4.ppu_read(0x0000), 0x35, "mapper {}.{}", mapper, submapper);
+            assert_eq!(vrc4.ppu_read(0x0400), 0x07, "mapper {}.{}", mapper, submapper);
+        }
+        // The last block: banks 6 and 7 at $1800 and $1C00
+        let mut vrc4: Vrc4 = vrc4(23, 1);
+        vrc4.cpu_write(0xE002, 0x09);
+        assert_eq!(vrc4.ppu_read(0x1C00), 0x09);
+    }
+    #[test]
+    fn test_vrc2() {
+        // VRC2a: CHR bank numbers lose their low bit
+        let mut vrc2: Vrc4 = vrc4(22, 0);
+        vrc2.cpu_write(0xB000, 0x07);
+        assert_eq!(vrc2.ppu_read(0x0000), 0x03);
+        // Only one mirroring bit, and no swap mode or IRQ
+        let mut vrc2: Vrc4 = vrc4(23, 3);
+        vrc2.cpu_write(0x9000, 3);
+        assert_eq!(vrc2.mirroring(), Mirroring::Horizontal);
+        vrc2.cpu_write(0x9002, PRG_SWAP_MODE);
+        assert_eq!(vrc2.cpu_read(0xC000), Some(14));
+        vrc2.cpu_write(0xF000, 0x0F);
+        vrc2.cpu_write(0xF001, 0x0F);
+        vrc2.cpu_write(0xF002, 0b0000_0110);
+        vrc2.cpu_tick(1);
+        assert!(!vrc2.irq_line());
+    }
+    #[test]
+    fn test_vrc4_irq() {
+        let mut vrc4: Vrc4 = vrc4(25, 1);
+        // VRC4b swaps the lines: $F002 is latch high, $F001 control
+        vrc4.cpu_write(0xF000, 0x0E);
+        vrc4.cpu_write(0xF002, 0x0F);
+        vrc4.cpu_write(0xF001, 0b0000_0110);
+        vrc4.cpu_tick(1);
+        assert!(!vrc4.irq_line());
+        vrc4.cpu_tick(1);
+        assert!(vrc4.irq_line());
+        vrc4.cpu_write(0xF003, 0);
+        assert!(!vrc4.irq_line());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::vrc_irq::VrcIrq;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_RAM;
+use crate::mapper::PRG_RAM_END;
+use crate::mapper::PRG_ROM;
+
+const PRG_BANK_16K: usize = 0x4000;
+const PRG_BANK_8K: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const BANKING_CHR_MODE: u8 = 0b0000_0011;
+const BANKING_MIRRORING: u8 = 0b0000_1100;
+const BANKING_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+// Mappers 24 and 26, Konami's VRC6 (Akumajou Densetsu, Madara, Esper Dream 2). Mapper 26
+// boards swap the A0 and A1 lines, so $x001 and $x002 trade places.
+//   $8000-$8003  16 KiB PRG bank at $8000
+//   $9000-$B002  expansion sound, not emulated
+//   $B003        banking  R...MMCC  R: PRG RAM enable, MM: mirroring, CC: CHR mode
+//   $C000-$C003  8 KiB PRG bank at $C000; the last 8 KiB is fixed at $E000
+//   $D000-$D003  CHR registers R0-R3
+//   $E000-$E003  CHR registers R4-R7
+//   $F000-$F002  IRQ latch, control, acknowledge
+// CHR mode 0 maps R0-R7 as 1 KiB banks; mode 1 uses R0-R3 as 2 KiB banks; modes 2 and 3 put
+// R0-R3 at $0000 in 1 KiB and R4, R5 at $1000 in 2 KiB. A 2 KiB bank takes its low bit from
+// PPU A10. Mirroring is vertical, horizontal, then one-screen lower and upper; the chip can
+// also map nametables to CHR ROM, which no game relies on and isn't emulated.
+pub struct Vrc6 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    swapped_lines: bool,
+
+    prg_bank_16k: u8,
+    prg_bank_8k: u8,
+    banking: u8,
+    chr_banks: [u8; 8],
+    irq: VrcIrq,
+}
+
+impl Vrc6 {
+    pub fn new(rom: Rom) -> Self {
+        let prg_ram: Vec<u8> = super::prg_ram(&rom);
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Vrc6 {
+            swapped_lines: rom.mapper == 26,
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            battery: rom.battery,
+            chr,
+            chr_is_ram,
+            prg_bank_16k: 0,
+            prg_bank_8k: 0,
+            banking: 0,
+            chr_banks: [0; 8],
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        let register: usize = if self.swapped_lines {
+            ((addr & 1) << 1 | (addr & 2) >> 1) as usize
+        } else {
+            (addr & 3) as usize
+        };
+        match (addr & 0xF000, register) {
+            (0x8000, _) => self.prg_bank_16k = value & 0b0000_1111,
+            (0xB000, 3) => self.banking = value,
+            (0xC000, _) => self.prg_bank_8k = value & 0b0001_1111,
+            (0xD000, _) => self.chr_banks[register] = value,
+            (0xE000, _) => self.chr_banks[4 + register] = value,
+            (0xF000, 0) => self.irq.write_latch(value),
+            (0xF000, 1) => self.irq.write_control(value),
+            (0xF000, 2) => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let (bank, size): (usize, usize) = match addr {
+            0x8000..=0xBFFF => (self.prg_bank_16k as usize, PRG_BANK_16K),
+            0xC000..=0xDFFF => (self.prg_bank_8k as usize, PRG_BANK_8K),
+            _ => (self.prg_rom.len() / PRG_BANK_8K - 1, PRG_BANK_8K),
+        };
+        (bank * size + (addr as usize & (size - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let slot: usize = addr as usize / CHR_BANK_SIZE;
+        let a10: u8 = (slot & 1) as u8;
+        let bank: u8 = match (self.banking & BANKING_CHR_MODE, slot) {
+            (0, _) => self.chr_banks[slot],
+            (1, _) => (self.chr_banks[slot / 2] & !1) | a10,
+            (_, 0..=3) => self.chr_banks[slot],
+            (_, _) => (self.chr_banks[2 + slot / 2] & !1) | a10,
+        };
+        (bank as usize * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn prg_ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.prg_ram.is_empty() || self.banking & BANKING_PRG_RAM_ENABLE == 0 {
+            return None;
+        }
+        Some((addr - PRG_RAM) as usize % self.prg_ram.len())
+    }
+}
+
+impl Mapper for Vrc6 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram_offset(addr).map(|offset| self.prg_ram[offset]),
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => {
+                if let Some(offset) = self.prg_ram_offset(addr) {
+                    self.prg_ram[offset] = data;
+                }
+            }
+            PRG_ROM.. => self.write_register(addr, data),
+            _ => {}
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = self.chr_offset(addr);
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking & BANKING_MIRRORING) >> 2 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_line(&self) -> bool {
+        self.irq.line()
+    }
+    fn cpu_tick(&mut self, cycles: u8) {
+        self.irq.tick(cycles);
+    }
+    fn battery_ram(&self) -> Option<&[u8]> {
+        super::battery_ram(self.battery, &self.prg_ram)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        super::load_battery_ram(self.battery, &mut self.prg_ram, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    fn vrc6(mapper: u16) -> Vrc6 {
+        let mut rom: Rom = test_rom(numbered_banks(16, PRG_BANK_8K));
+        rom.mapper = mapper;
+        rom.chr_rom = numbered_banks(32, CHR_BANK_SIZE);
+        Vrc6::new(rom)
+    }
+    fn chr_banks(vrc6: &Vrc6) -> Vec<u8> {
+        (0..8).map(|slot| vrc6.ppu_read(slot * 0x400)).collect()
+    }
+
+    #[test]
+    fn test_vrc6_prg_banks() {
+        let mut vrc6: Vrc6 = vrc6(24);
+        vrc6.cpu_write(0x8000, 2);
+        vrc6.cpu_write(0xC000, 9);
+        assert_eq!(vrc6.cpu_read(0x8000), Some(4));
+        assert_eq!(vrc6.cpu_read(0xA000), Some(5));
+        assert_eq!(vrc6.cpu_read(0xC000), Some(9));
+        assert_eq!(vrc6.cpu_read(0xE000), Some(15));
+    }
+    #[test]
+    fn test_vrc6_chr_modes() {
+        let mut vrc6: Vrc6 = vrc6(24);
+        for (i, bank) in [10, 11, 12, 13, 14, 15, 16, 17].iter().enumerate() {
+            vrc6.cpu_write(0xD000 + (i as u16 / 4) * 0x1000 + i as u16 % 4, *bank);
+        }
+        assert_eq!(chr_banks(&vrc6), vec![10, 11, 12, 13, 14, 15, 16, 17]);
+        vrc6.cpu_write(0xB003, 1);
+        assert_eq!(chr_banks(&vrc6), vec![10, 11, 10, 11, 12, 13, 12, 13]);
+        vrc6.cpu_write(0xB003, 2);
+        assert_eq!(chr_banks(&vrc6), vec![10, 11, 12, 13, 14, 15, 14, 15]);
+    }
+    #[test]
+    fn test_vrc6_banking_register() {
+        let mut vrc6: Vrc6 = vrc6(24);
+        assert_eq!(vrc6.mirroring(), Mirroring::Vertical);
+        vrc6.cpu_write(0xB003, 0b0000_0100);
+        assert_eq!(vrc6.mirroring(), Mirroring::Horizontal);
+        vrc6.cpu_write(0xB003, 0b0000_1100);
+        assert_eq!(vrc6.mirroring(), Mirroring::SingleScreenUpper);
+        // PRG RAM is off until bit 7 turns it on
+        vrc6.cpu_write(0x6000, 0x12);
+        assert_eq!(vrc6.cpu_read(0x6000), None);
+        vrc6.cpu_write(0xB003, BANKING_PRG_RAM_ENABLE);
+        vrc6.cpu_write(0x6000, 0x12);
+        assert_eq!(vrc6.cpu_read(0x6000), Some(0x12));
+    }
+    #[test]
+    fn test_vrc6b_swaps_a0_and_a1() {
+        let mut vrc6b: Vrc6 = vrc6(26);
+        vrc6b.cpu_write(0xD001, 20); // R2
+        vrc6b.cpu_write(0xD002, 21); // R1
+        assert_eq!(chr_banks(&vrc6b)[1..3], [21, 20]);
+        vrc6b.cpu_write(0xF000, 0xFF);
+        vrc6b.cpu_write(0xF002, 0b0000_0110); // control
+        vrc6b.cpu_tick(1);
+        assert!(vrc6b.irq_line());
+        vrc6b.cpu_write(0xF001, 0); // acknowledge
+        assert!(!vrc6b.irq_line());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::cartridge::Mirroring;
+use crate::cartridge::Rom;
+use crate::mapper::vrc_irq::VrcIrq;
+use crate::mapper::Mapper;
+use crate::mapper::PRG_RAM;
+use crate::mapper::PRG_RAM_END;
+use crate::mapper::PRG_ROM;
+
+const PRG_BANK_SIZE: usize = 0x2000;
+const CHR_BANK_SIZE: usize = 0x0400;
+
+const CONTROL_MIRRORING: u8 = 0b0000_0011;
+const CONTROL_PRG_RAM_ENABLE: u8 = 0b1000_0000;
+
+// Mapper 85, Konami's VRC7 (Lagrange Point, Tiny Toon Adventures 2). Each 4 KiB block has two
+// registers, the second one at $x010 on the VRC7a (submapper 2) and at $x008 on the VRC7b
+// (submapper 1):
+//   $8000 / $8010  8 KiB PRG banks at $8000 and $A000
+//   $9000          8 KiB PRG bank at $C000; the last bank is fixed at $E000
+//   $9010, $9030   FM sound, not emulated
+//   $A000-$D010    CHR banks 0-7, 1 KiB each
+//   $E000          control  RS....MM  R: PRG RAM enable, S: sound reset, MM: mirroring
+//   $E010          IRQ latch
+//   $F000 / $F010  IRQ control, acknowledge
+pub struct Vrc7 {
+    prg_rom: Vec<u8>,
+    prg_ram: Vec<u8>,
+    battery: bool,
+    chr: Vec<u8>,
+    chr_is_ram: bool,
+    // The CPU address line(s) wired to the chip's A0
+    a0: u16,
+
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+}
+
+impl Vrc7 {
+    pub fn new(rom: Rom) -> Self {
+        let a0: u16 = match rom.submapper {
+            1 => 0x08,
+            2 => 0x10,
+            _ => 0x18,
+        };
+        let prg_ram: Vec<u8> = super::prg_ram(&rom);
+        let (chr, chr_is_ram) = super::chr_memory(&rom);
+        Vrc7 {
+            prg_rom: rom.prg_rom,
+            prg_ram,
+            battery: rom.battery,
+            chr,
+            chr_is_ram,
+            a0,
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn write_register(&mut self, addr: u16, value: u8) {
+        // The sound ports sit at the same addresses on both revisions, so they're decoded before
+        // A0; on the VRC7b $9010 would otherwise look like the $C000 bank register
+        if let 0x9010 | 0x9030 = addr & 0xF030 {
+            return;
+        }
+        let second: bool = addr & self.a0 != 0;
+        match (addr & 0xF000, second) {
+            (0x8000, false) => self.prg_banks[0] = value & 0b0011_1111,
+            (0x8000, true) => self.prg_banks[1] = value & 0b0011_1111,
+            (0x9000, false) => self.prg_banks[2] = value & 0b0011_1111,
+            (0x9000, true) => {}
+            (0xA000..=0xD000, _) => {
+                let block: usize = ((addr & 0xF000) - 0xA000) as usize / 0x1000;
+                self.chr_banks[block * 2 + second as usize] = value;
+            }
+            (0xE000, false) => self.control = value,
+            (0xE000, true) => self.irq.write_latch(value),
+            (_, false) => self.irq.write_control(value),
+            (_, true) => self.irq.acknowledge(),
+        }
+    }
+
+    fn prg_rom_offset(&self, addr: u16) -> usize {
+        let bank: usize = match (addr - PRG_ROM) as usize / PRG_BANK_SIZE {
+            slot @ 0..=2 => self.prg_banks[slot] as usize,
+            _ => self.prg_rom.len() / PRG_BANK_SIZE - 1,
+        };
+        (bank * PRG_BANK_SIZE + (addr as usize & (PRG_BANK_SIZE - 1))) % self.prg_rom.len()
+    }
+
+    fn chr_offset(&self, addr: u16) -> usize {
+        let bank: usize = self.chr_banks[addr as usize / CHR_BANK_SIZE] as usize;
+        (bank * CHR_BANK_SIZE + (addr as usize & (CHR_BANK_SIZE - 1))) % self.chr.len()
+    }
+
+    fn prg_ram_offset(&self, addr: u16) -> Option<usize> {
+        if self.prg_ram.is_empty() || self.control & CONTROL_PRG_RAM_ENABLE == 0 {
+            return None;
+        }
+        Some((addr - PRG_RAM) as usize % self.prg_ram.len())
+    }
+}
+
+impl Mapper for Vrc7 {
+    fn cpu_read(&self, addr: u16) -> Option<u8> {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => self.prg_ram_offset(addr).map(|offset| self.prg_ram[offset]),
+            PRG_ROM.. if !self.prg_rom.is_empty() => Some(self.prg_rom[self.prg_rom_offset(addr)]),
+            _ => None,
+        }
+    }
+    fn cpu_write(&mut self, addr: u16, data: u8) {
+        match addr {
+            PRG_RAM..=PRG_RAM_END => {
+                if let Some(offset) = self.prg_ram_offset(addr) {
+                    self.prg_ram[offset] = data;
+                }
+            }
+            PRG_ROM.. => self.write_register(addr, data),
+            _ => {}
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        self.chr[self.chr_offset(addr)]
+    }
+    fn ppu_write(&mut self, addr: u16, data: u8) {
+        let offset: usize = self.chr_offset(addr);
+        super::write_chr(&mut self.chr, self.chr_is_ram, offset, data);
+    }
+    fn mirroring(&self) -> Mirroring {
+        match self.control & CONTROL_MIRRORING {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenLower,
+            _ => Mirroring::SingleScreenUpper,
+        }
+    }
+    fn irq_line(&self) -> bool {
+        self.irq.line()
+    }
+    fn cpu_tick(&mut self, cycles: u8) {
+        self.irq.tick(cycles);
+    }
+    fn battery_ram(&self) -> Option<&[u8]> {
+        super::battery_ram(self.battery, &self.prg_ram)
+    }
+    fn load_battery_ram(&mut self, data: &[u8]) {
+        super::load_battery_ram(self.battery, &mut self.prg_ram, data);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::numbered_banks;
+    use crate::cartridge::test::test_rom;
+
+    fn vrc7(submapper: u8) -> Vrc7 {
+        let mut rom: Rom = test_rom(numbered_banks(16, PRG_BANK_SIZE));
+        rom.mapper = 85;
+        rom.submapper = submapper;
+        rom.chr_rom = numbered_banks(32, CHR_BANK_SIZE);
+        Vrc7::new(rom)
+    }
+
+    #[test]
+    fn test_vrc7_banks() {
+        // VRC7a (Lagrange Point) has the second registers at $x010
+        let mut vrc7a: Vrc7 = vrc7(2);
+        vrc7a.cpu_write(0x8000, 4);
+        vrc7a.cpu_write(0x8010, 5);
+        vrc7a.cpu_write(0x9000, 6);
+        vrc7a.cpu_write(0xA000, 20);
+        vrc7a.cpu_write(0xA010, 21);
+        vrc7a.cpu_write(0xD010, 27);
+        assert_eq!(vrc7a.cpu_read(0x8000), Some(4));
+        assert_eq!(vrc7a.cpu_read(0xA000), Some(5));
+        assert_eq!(vrc7a.cpu_read(0xC000), Some(6));
+        assert_eq!(vrc7a.cpu_read(0xE000), Some(15));
+        assert_eq!(vrc7a.ppu_read(0x0000), 20);
+        assert_eq!(vrc7a.ppu_read(0x0400), 21);
+        assert_eq!(vrc7a.ppu_read(0x1C00), 27);
+        // On the VRC7b it's $x008
+        let mut vrc7b: Vrc7 = vrc7(1);
+        vrc7b.cpu_write(0x8008, 5);
+        vrc7b.cpu_write(0x8010, 7);
+        assert_eq!(vrc7b.cpu_read(0xA000), Some(5));
+        assert_eq!(vrc7b.cpu_read(0x8000), Some(7));
+    }
+    #[test]
+    fn test_vrc7_sound_ports_leave_the_banks_alone() {
+        let mut vrc7b: Vrc7 = vrc7(1);
+        vrc7b.cpu_write(0x9000, 6);
+        vrc7b.cpu_write(0x9010, 0x20); // FM register select
+        vrc7b.cpu_write(0x9030, 0x11); // FM register data
+        assert_eq!(vrc7b.cpu_read(0xC000), Some(6));
+    }
+    #[test]
+    fn test_vrc7_control_and_irq() {
+        let mut vrc7: Vrc7 = vrc7(0);
+        vrc7.cpu_write(0xE000, 1);
+        assert_eq!(vrc7.mirroring(), Mirroring::Horizontal);
+        assert_eq!(vrc7.cpu_read(0x6000), None);
+        vrc7.cpu_write(0xE000, CONTROL_PRG_RAM_ENABLE);
+        vrc7.cpu_write(0x6000, 0x12);
+        assert_eq!(vrc7.cpu_read(0x6000), Some(0x12));
+
+        vrc7.cpu_write(0xE010, 0xFE);
+        vrc7.cpu_write(0xF000, 0b0000_0110);
+        vrc7.cpu_tick(2);
+        assert!(vrc7.irq_line());
+        vrc7.cpu_write(0xF008, 0);
+        assert!(!vrc7.irq_line());
+    }
+}
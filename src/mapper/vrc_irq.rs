@@ -0,0 +1,142 @@
+const CONTROL_ENABLE_AFTER_ACK: u8 = 0b0000_0001;
+const CONTROL_ENABLE: u8 = 0b0000_0010;
+const CONTROL_CYCLE_MODE: u8 = 0b0000_0100;
+
+// The prescaler counts down 3 per CPU cycle from 341, so scanline mode clocks the counter
+// every 113 2/3 CPU cycles, a scanline's worth, without looking at the PPU at all
+const PRESCALER_RELOAD: i16 = 341;
+
+// The IRQ counter Konami put in the VRC4, VRC6 and VRC7. An 8-bit counter counts up from the
+// latch and raises the IRQ when it overflows, reloading itself. In cycle mode it counts CPU
+// cycles, in scanline mode it counts through the prescaler.
+//   latch      value the counter starts from after an overflow or enabling
+//   control    .....MEA  M: cycle mode, E: enable, A: E to use after an acknowledge.
+//              Writing it clears a pending IRQ and, with E set, reloads the counter.
+//   acknowledge  clears a pending IRQ and copies A into E
+pub struct VrcIrq {
+    latch: u8,
+    counter: u8,
+    control: u8,
+    prescaler: i16,
+    pending: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> Self {
+        VrcIrq {
+            latch: 0,
+            counter: 0,
+            control: 0,
+            prescaler: PRESCALER_RELOAD,
+            pending: false,
+        }
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        self.latch = value;
+    }
+    // VRC4 takes the latch 4 bits at a time
+    pub fn write_latch_low(&mut self, value: u8) {
+        self.latch = (self.latch & 0xF0) | (value & 0x0F);
+    }
+    pub fn write_latch_high(&mut self, value: u8) {
+        self.latch = (self.latch & 0x0F) | (value << 4);
+    }
+    pub fn write_control(&mut self, value: u8) {
+        self.control = value & 0b0000_0111;
+        self.pending = false;
+        if self.control & CONTROL_ENABLE != 0 {
+            self.counter = self.latch;
+            self.prescaler = PRESCALER_RELOAD;
+        }
+    }
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+        if self.control & CONTROL_ENABLE_AFTER_ACK != 0 {
+            self.control |= CONTROL_ENABLE;
+        } else {
+            self.control &= !CONTROL_ENABLE;
+        }
+    }
+    pub fn line(&self) -> bool {
+        self.pending
+    }
+
+    pub fn tick(&mut self, cycles: u8) {
+        if self.control & CONTROL_ENABLE == 0 {
+            return;
+        }
+        for _ in 0..cycles {
+            if self.control & CONTROL_CYCLE_MODE != 0 {
+                self.clock();
+            } else {
+                self.prescaler -= 3;
+                if self.prescaler <= 0 {
+                    self.prescaler += PRESCALER_RELOAD;
+                    self.clock();
+                }
+            }
+        }
+    }
+    fn clock(&mut self) {
+        if self.counter == 0xFF {
+            self.counter = self.latch;
+            self.pending = true;
+        } else {
+            self.counter += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vrc_irq_cycle_mode() {
+        let mut irq: VrcIrq = VrcIrq::new();
+        irq.write_latch(0xFB);
+        irq.write_control(CONTROL_CYCLE_MODE | CONTROL_ENABLE | CONTROL_ENABLE_AFTER_ACK);
+        irq.tick(4);
+        assert!(!irq.line());
+        irq.tick(1);
+        assert!(irq.line());
+        irq.acknowledge();
+        assert!(!irq.line());
+        // Reloaded from the latch, so it fires again 5 cycles later
+        irq.tick(4);
+        assert!(!irq.line());
+        irq.tick(1);
+        assert!(irq.line());
+    }
+    #[test]
+    fn test_vrc_irq_scanline_mode() {
+        let mut irq: VrcIrq = VrcIrq::new();
+        irq.write_latch_low(0x0E);
+        irq.write_latch_high(0x0F);
+        irq.write_control(CONTROL_ENABLE);
+        // Two scanlines are 227 1/3 CPU cycles
+        irq.tick(200);
+        irq.tick(27);
+        assert!(!irq.line());
+        irq.tick(1);
+        assert!(irq.line());
+    }
+    #[test]
+    fn test_vrc_irq_acknowledge_disables() {
+        let mut irq: VrcIrq = VrcIrq::new();
+        irq.write_latch(0xFF);
+        irq.write_control(CONTROL_CYCLE_MODE | CONTROL_ENABLE);
+        irq.tick(1);
+        assert!(irq.line());
+        // Without A set the acknowledge turns the counter off
+        irq.acknowledge();
+        irq.tick(255);
+        assert!(!irq.line());
+        // And a control write drops a pending IRQ
+        irq.write_control(CONTROL_CYCLE_MODE | CONTROL_ENABLE);
+        irq.tick(1);
+        irq.write_control(CONTROL_CYCLE_MODE);
+        assert!(!irq.line());
+    }
+}
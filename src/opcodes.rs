@@ -1,1090 +1,34 @@
-use std::collections::HashMap;
-
 use crate::cpu::AddressingMode;
+
 pub struct OpCode {
     pub instruction: u8,
-    pub name: String,
+    pub name: &'static str,
     pub num_bytes: u8,
     pub num_cycles: u8,
     pub mode: AddressingMode,
+    pub unofficial: bool, // Undocumented opcode (LAX, DCP, multi-byte NOPs, ...)
 }
 
-pub fn make_opcode_hashtable() -> HashMap<u8, OpCode> {
-    let mut retval: HashMap<u8, OpCode> = HashMap::new();
-
-    retval.insert(
-        0x00,
-        OpCode::new(
-            0x00,
-            "BRK".to_string(),
-            1,
-            7,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xea,
-        OpCode::new(
-            0xea,
-            "NOP".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    /*Arithmetic*/
-    retval.insert(
-        0x69,
-        OpCode::new(0x69, "ADC".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0x65,
-        OpCode::new(0x65, "ADC".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x75,
-        OpCode::new(0x75, "ADC".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0x6d,
-        OpCode::new(0x6d, "ADC".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x7d,
-        OpCode::new(
-            0x7d,
-            "ADC".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0x79,
-        OpCode::new(
-            0x79,
-            "ADC".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0x61,
-        OpCode::new(
-            0x61,
-            "ADC".to_string(),
-            2,
-            6, /*+1 if page crossed*/
-            AddressingMode::Indirect_X,
-        ),
-    );
-    retval.insert(
-        0x71,
-        OpCode::new(
-            0x71,
-            "ADC".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    retval.insert(
-        0xe9,
-        OpCode::new(0xe9, "SBC".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xe5,
-        OpCode::new(0xe5, "SBC".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xf5,
-        OpCode::new(0xf5, "SBC".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0xed,
-        OpCode::new(0xed, "SBC".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0xfd,
-        OpCode::new(
-            0xfd,
-            "SBC".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0xf9,
-        OpCode::new(
-            0xf9,
-            "SBC".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0xe1,
-        OpCode::new(
-            0xe1,
-            "SBC".to_string(),
-            2,
-            6, /*+1 if page crossed*/
-            AddressingMode::Indirect_X,
-        ),
-    );
-    retval.insert(
-        0xf1,
-        OpCode::new(
-            0xf1,
-            "SBC".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    retval.insert(
-        0x29,
-        OpCode::new(0x29, "AND".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0x25,
-        OpCode::new(0x25, "AND".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x35,
-        OpCode::new(0x35, "AND".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0x2d,
-        OpCode::new(0x2d, "AND".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x3d,
-        OpCode::new(
-            0x3d,
-            "AND".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0x39,
-        OpCode::new(
-            0x39,
-            "AND".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0x21,
-        OpCode::new(
-            0x21,
-            "AND".to_string(),
-            2,
-            6, /*+1 if page crossed*/
-            AddressingMode::Indirect_X,
-        ),
-    );
-    retval.insert(
-        0x31,
-        OpCode::new(
-            0x31,
-            "AND".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    retval.insert(
-        0x49,
-        OpCode::new(0x49, "EOR".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0x45,
-        OpCode::new(0x45, "EOR".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x55,
-        OpCode::new(0x55, "EOR".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0x4d,
-        OpCode::new(0x4d, "EOR".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x5d,
-        OpCode::new(
-            0x5d,
-            "EOR".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0x59,
-        OpCode::new(
-            0x59,
-            "EOR".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0x41,
-        OpCode::new(
-            0x41,
-            "EOR".to_string(),
-            2,
-            6, /*+1 if page crossed*/
-            AddressingMode::Indirect_X,
-        ),
-    );
-    retval.insert(
-        0x51,
-        OpCode::new(
-            0x51,
-            "EOR".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    retval.insert(
-        0x09,
-        OpCode::new(0x09, "ORA".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0x05,
-        OpCode::new(0x05, "ORA".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x15,
-        OpCode::new(0x15, "ORA".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0x0d,
-        OpCode::new(0x0d, "ORA".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x1d,
-        OpCode::new(
-            0x1d,
-            "ORA".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0x19,
-        OpCode::new(
-            0x19,
-            "ORA".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0x01,
-        OpCode::new(
-            0x01,
-            "ORA".to_string(),
-            2,
-            6, /*+1 if page crossed*/
-            AddressingMode::Indirect_X,
-        ),
-    );
-    retval.insert(
-        0x11,
-        OpCode::new(
-            0x11,
-            "ORA".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    /*Shifts*/
-    retval.insert(
-        0x0a,
-        OpCode::new(
-            0x0a,
-            "ASL".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x06,
-        OpCode::new(0x06, "ASL".to_string(), 2, 5, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x16,
-        OpCode::new(0x16, "ASL".to_string(), 2, 6, AddressingMode::ZeroPage_Y),
-    );
-    retval.insert(
-        0x0e,
-        OpCode::new(0x0e, "ASL".to_string(), 3, 6, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x1e,
-        OpCode::new(0x1e, "ASL".to_string(), 3, 7, AddressingMode::Absolute_X),
-    );
-
-    retval.insert(
-        0x4a,
-        OpCode::new(
-            0x4a,
-            "LSR".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x46,
-        OpCode::new(0x46, "LSR".to_string(), 2, 5, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x56,
-        OpCode::new(0x56, "LSR".to_string(), 2, 6, AddressingMode::ZeroPage_Y),
-    );
-    retval.insert(
-        0x4e,
-        OpCode::new(0x4e, "LSR".to_string(), 3, 6, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x5e,
-        OpCode::new(0x5e, "LSR".to_string(), 3, 7, AddressingMode::Absolute_X),
-    );
-
-    retval.insert(
-        0x2a,
-        OpCode::new(
-            0x2a,
-            "ROL".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x26,
-        OpCode::new(0x26, "ROL".to_string(), 2, 5, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x36,
-        OpCode::new(0x36, "ROL".to_string(), 2, 6, AddressingMode::ZeroPage_Y),
-    );
-    retval.insert(
-        0x2e,
-        OpCode::new(0x2e, "ROL".to_string(), 3, 6, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x3e,
-        OpCode::new(0x3e, "ROL".to_string(), 3, 7, AddressingMode::Absolute_X),
-    );
-
-    retval.insert(
-        0x6a,
-        OpCode::new(
-            0x6a,
-            "ROR".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x66,
-        OpCode::new(0x66, "ROR".to_string(), 2, 5, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x76,
-        OpCode::new(0x76, "ROR".to_string(), 2, 6, AddressingMode::ZeroPage_Y),
-    );
-    retval.insert(
-        0x6e,
-        OpCode::new(0x6e, "ROR".to_string(), 3, 6, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x7e,
-        OpCode::new(0x7e, "ROR".to_string(), 3, 7, AddressingMode::Absolute_X),
-    );
-
-    retval.insert(
-        0xe6,
-        OpCode::new(0xe6, "INC".to_string(), 2, 5, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xf6,
-        OpCode::new(0xf6, "INC".to_string(), 2, 6, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0xee,
-        OpCode::new(0xee, "INC".to_string(), 3, 6, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0xfe,
-        OpCode::new(0xfe, "INC".to_string(), 3, 7, AddressingMode::Absolute_X),
-    );
-
-    retval.insert(
-        0xe8,
-        OpCode::new(
-            0xe8,
-            "INX".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xc8,
-        OpCode::new(
-            0xc8,
-            "INY".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0xc6,
-        OpCode::new(0xc6, "DEC".to_string(), 2, 5, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xd6,
-        OpCode::new(0xd6, "DEC".to_string(), 2, 6, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0xce,
-        OpCode::new(0xce, "DEC".to_string(), 3, 6, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0xde,
-        OpCode::new(0xde, "DEC".to_string(), 3, 7, AddressingMode::Absolute_X),
-    );
-
-    retval.insert(
-        0xca,
-        OpCode::new(
-            0xca,
-            "DEX".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x88,
-        OpCode::new(
-            0x88,
-            "DEY".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0xc9,
-        OpCode::new(0xc9, "CMP".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xc5,
-        OpCode::new(0xc5, "CMP".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xd5,
-        OpCode::new(0xd5, "CMP".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0xcd,
-        OpCode::new(0xcd, "CMP".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0xdd,
-        OpCode::new(
-            0xdd,
-            "CMP".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0xd9,
-        OpCode::new(
-            0xd9,
-            "CMP".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0xc1,
-        OpCode::new(0xc1, "CMP".to_string(), 2, 6, AddressingMode::Indirect_X),
-    );
-    retval.insert(
-        0xd1,
-        OpCode::new(
-            0xd1,
-            "CMP".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    retval.insert(
-        0xc0,
-        OpCode::new(0xc0, "CPY".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xc4,
-        OpCode::new(0xc4, "CPY".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xcc,
-        OpCode::new(0xcc, "CPY".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-
-    retval.insert(
-        0xe0,
-        OpCode::new(0xe0, "CPX".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xe4,
-        OpCode::new(0xe4, "CPX".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xec,
-        OpCode::new(0xec, "CPX".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-
-    /* Branching */
-    retval.insert(
-        0x4c,
-        OpCode::new(
-            0x4c,
-            "JMP".to_string(),
-            3,
-            3,
-            AddressingMode::Absolute,
-        ),
-    );
-    retval.insert(
-        0x6c,
-        OpCode::new(
-            0x6c,
-            "JMP".to_string(),
-            3,
-            5,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0x20,
-        OpCode::new(
-            0x20,
-            "JSR".to_string(),
-            3,
-            6,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x60,
-        OpCode::new(
-            0x60,
-            "RTS".to_string(),
-            1,
-            6,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0x40,
-        OpCode::new(
-            0x40,
-            "RTI".to_string(),
-            1,
-            6,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0xd0,
-        OpCode::new(
-            0xd0,
-            "BNE".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x70,
-        OpCode::new(
-            0x70,
-            "BVS".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x50,
-        OpCode::new(
-            0x50,
-            "BVC".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x30,
-        OpCode::new(
-            0x30,
-            "BMI".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xf0,
-        OpCode::new(
-            0xf0,
-            "BEQ".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xb0,
-        OpCode::new(
-            0xb0,
-            "BCS".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x90,
-        OpCode::new(
-            0x90,
-            "BCC".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x10,
-        OpCode::new(
-            0x10,
-            "BPL".to_string(),
-            2,
-            2, /*+1 if branch succeeds +2 if to a new page*/
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0x24,
-        OpCode::new(0x24, "BIT".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x2c,
-        OpCode::new(0x2c, "BIT".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-
-    /*Stores, Loads */
-    retval.insert(
-        0xa9,
-        OpCode::new(0xa9, "LDA".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xa5,
-        OpCode::new(0xa5, "LDA".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xb5,
-        OpCode::new(0xb5, "LDA".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0xad,
-        OpCode::new(0xad, "LDA".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0xbd,
-        OpCode::new(
-            0xbd,
-            "LDA".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-    retval.insert(
-        0xb9,
-        OpCode::new(
-            0xb9,
-            "LDA".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_Y,
-        ),
-    );
-    retval.insert(
-        0xa1,
-        OpCode::new(0xa1, "LDA".to_string(), 2, 6, AddressingMode::Indirect_X),
-    );
-    retval.insert(
-        0xb1,
-        OpCode::new(
-            0xb1,
-            "LDA".to_string(),
-            2,
-            5, /*+1 if page crossed*/
-            AddressingMode::Indirect_Y,
-        ),
-    );
-
-    retval.insert(
-        0xa2,
-        OpCode::new(0xa2, "LDX".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xa6,
-        OpCode::new(0xa6, "LDX".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xb6,
-        OpCode::new(
-            0xb6,
-            "LDX".to_string(),
-            2,
-            4, /*+1 if page crossed*/
-            AddressingMode::ZeroPage_Y,
-        ),
-    );
-    retval.insert(
-        0xae,
-        OpCode::new(
-            0xae,
-            "LDX".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute,
-        ),
-    );
-    retval.insert(
-        0xbe,
-        OpCode::new(0xbe, "LDX".to_string(), 3, 4, AddressingMode::Absolute_Y),
-    );
-
-    retval.insert(
-        0xa0,
-        OpCode::new(0xa0, "LDY".to_string(), 2, 2, AddressingMode::Immediate),
-    );
-    retval.insert(
-        0xa4,
-        OpCode::new(0xa4, "LDY".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0xb4,
-        OpCode::new(0xb4, "LDY".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0xac,
-        OpCode::new(0xac, "LDY".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0xbc,
-        OpCode::new(
-            0xbc,
-            "LDY".to_string(),
-            3,
-            4, /*+1 if page crossed*/
-            AddressingMode::Absolute_X,
-        ),
-    );
-
-    retval.insert(
-        0x85,
-        OpCode::new(0x85, "STA".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x95,
-        OpCode::new(0x95, "STA".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0x8d,
-        OpCode::new(0x8d, "STA".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-    retval.insert(
-        0x9d,
-        OpCode::new(0x9d, "STA".to_string(), 3, 5, AddressingMode::Absolute_X),
-    );
-    retval.insert(
-        0x99,
-        OpCode::new(0x99, "STA".to_string(), 3, 5, AddressingMode::Absolute_Y),
-    );
-    retval.insert(
-        0x81,
-        OpCode::new(0x81, "STA".to_string(), 2, 6, AddressingMode::Indirect_X),
-    );
-    retval.insert(
-        0x91,
-        OpCode::new(0x91, "STA".to_string(), 2, 6, AddressingMode::Indirect_Y),
-    );
-
-    retval.insert(
-        0x86,
-        OpCode::new(0x86, "STX".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x96,
-        OpCode::new(0x96, "STX".to_string(), 2, 4, AddressingMode::ZeroPage_Y),
-    );
-    retval.insert(
-        0x8e,
-        OpCode::new(0x8e, "STX".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-
-    retval.insert(
-        0x84,
-        OpCode::new(0x84, "STY".to_string(), 2, 3, AddressingMode::ZeroPage),
-    );
-    retval.insert(
-        0x94,
-        OpCode::new(0x94, "STY".to_string(), 2, 4, AddressingMode::ZeroPage_X),
-    );
-    retval.insert(
-        0x8c,
-        OpCode::new(0x8c, "STY".to_string(), 3, 4, AddressingMode::Absolute),
-    );
-
-    /* Flags Clear */
-    retval.insert(
-        0xd8,
-        OpCode::new(
-            0xd8,
-            "CLD".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x58,
-        OpCode::new(
-            0x58,
-            "CLI".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xb8,
-        OpCode::new(
-            0xb8,
-            "CLV".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x18,
-        OpCode::new(
-            0x18,
-            "CLC".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x38,
-        OpCode::new(
-            0x38,
-            "SEC".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x78,
-        OpCode::new(
-            0x78,
-            "SEI".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xf8,
-        OpCode::new(
-            0xf8,
-            "SED".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval.insert(
-        0xaa,
-        OpCode::new(
-            0xaa,
-            "TAX".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xa8,
-        OpCode::new(
-            0xa8,
-            "TAY".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0xba,
-        OpCode::new(
-            0xba,
-            "TSX".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x8a,
-        OpCode::new(
-            0x8a,
-            "TXA".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x9a,
-        OpCode::new(
-            0x9a,
-            "TXS".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x98,
-        OpCode::new(
-            0x98,
-            "TYA".to_string(),
-            1,
-            2,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    /* Stack */
-    retval.insert(
-        0x48,
-        OpCode::new(
-            0x48,
-            "PHA".to_string(),
-            1,
-            3,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x68,
-        OpCode::new(
-            0x68,
-            "PLA".to_string(),
-            1,
-            4,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x08,
-        OpCode::new(
-            0x08,
-            "PHP".to_string(),
-            1,
-            3,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-    retval.insert(
-        0x28,
-        OpCode::new(
-            0x28,
-            "PLP".to_string(),
-            1,
-            4,
-            AddressingMode::NoneAddressing,
-        ),
-    );
-
-    retval
-}
-
-pub struct Instructions {
-    pub map: HashMap<u8, OpCode>,
-}
-
-impl Instructions {
-    pub fn new() -> Self {
-        Instructions {
-            map: make_opcode_hashtable(),
+impl OpCode {
+    pub const fn new(
+        instruction: u8,
+        name: &'static str,
+        num_bytes: u8,
+        num_cycles: u8,
+        mode: AddressingMode,
+    ) -> Self {
+        OpCode {
+            instruction,
+            name,
+            num_bytes,
+            num_cycles,
+            mode,
+            unofficial: false,
         }
     }
-}
-impl OpCode {
-    pub fn new(
+    pub const fn unofficial(
         instruction: u8,
-        name: String,
+        name: &'static str,
         num_bytes: u8,
         num_cycles: u8,
         mode: AddressingMode,
@@ -1095,6 +39,530 @@ impl OpCode {
             num_bytes,
             num_cycles,
             mode,
+            unofficial: true,
         }
     }
 }
+
+// Every byte value decodes to something on the NMOS 6502, so the table is indexed directly by
+// opcode. Shared by the interpreter and the tracer; CPU::opcodes picks the table for the variant.
+// Accumulator, implied, relative and JMP/JSR operands use NoneAddressing.
+pub static OPCODES: [OpCode; 256] = [
+    OpCode::new(0x00, "BRK", 1, 7, AddressingMode::NoneAddressing),
+    OpCode::new(0x01, "ORA", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x02, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x03, "SLO", 2, 8, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x04, "NOP", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
+    OpCode::unofficial(0x07, "SLO", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x08, "PHP", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x09, "ORA", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x0a, "ASL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x0b, "ANC", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x0c, "NOP", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x0d, "ORA", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x0e, "ASL", 3, 6, AddressingMode::Absolute),
+    OpCode::unofficial(0x0f, "SLO", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x10, "BPL", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x11, "ORA", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0x12, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x13, "SLO", 2, 8, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0x14, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::unofficial(0x17, "SLO", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x18, "CLC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x19, "ORA", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0x1a, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x1b, "SLO", 3, 7, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0x1c, "NOP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x1d, "ORA", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x1e, "ASL", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0x1f, "SLO", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x20, "JSR", 3, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x21, "AND", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x22, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x23, "RLA", 2, 8, AddressingMode::Indirect_X),
+    OpCode::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage),
+    OpCode::unofficial(0x27, "RLA", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x28, "PLP", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x2a, "ROL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x2b, "ANC", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x2c, "BIT", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x2d, "AND", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x2e, "ROL", 3, 6, AddressingMode::Absolute),
+    OpCode::unofficial(0x2f, "RLA", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x30, "BMI", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x31, "AND", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0x32, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x33, "RLA", 2, 8, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0x34, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::unofficial(0x37, "RLA", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x38, "SEC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x39, "AND", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0x3a, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x3b, "RLA", 3, 7, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0x3c, "NOP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x3d, "AND", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x3e, "ROL", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0x3f, "RLA", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x40, "RTI", 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x41, "EOR", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x42, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x43, "SRE", 2, 8, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x44, "NOP", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage),
+    OpCode::unofficial(0x47, "SRE", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x48, "PHA", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x49, "EOR", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x4a, "LSR", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x4b, "ALR", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x4c, "JMP", 3, 3, AddressingMode::Absolute),
+    OpCode::new(0x4d, "EOR", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x4e, "LSR", 3, 6, AddressingMode::Absolute),
+    OpCode::unofficial(0x4f, "SRE", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x50, "BVC", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x51, "EOR", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0x52, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x53, "SRE", 2, 8, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0x54, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::unofficial(0x57, "SRE", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x58, "CLI", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x59, "EOR", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0x5a, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x5b, "SRE", 3, 7, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0x5c, "NOP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x5d, "EOR", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x5e, "LSR", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0x5f, "SRE", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0x60, "RTS", 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x61, "ADC", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x62, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x63, "RRA", 2, 8, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x64, "NOP", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage),
+    OpCode::unofficial(0x67, "RRA", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x68, "PLA", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x6a, "ROR", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x6b, "ARR", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x6c, "JMP", 3, 5, AddressingMode::NoneAddressing),
+    OpCode::new(0x6d, "ADC", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x6e, "ROR", 3, 6, AddressingMode::Absolute),
+    OpCode::unofficial(0x6f, "RRA", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x70, "BVS", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x71, "ADC", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0x72, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x73, "RRA", 2, 8, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0x74, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::unofficial(0x77, "RRA", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x78, "SEI", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x79, "ADC", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0x7a, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x7b, "RRA", 3, 7, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0x7c, "NOP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x7d, "ADC", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x7e, "ROR", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0x7f, "RRA", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0x80, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x81, "STA", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x82, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x83, "SAX", 2, 6, AddressingMode::Indirect_X),
+    OpCode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::unofficial(0x87, "SAX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x88, "DEY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x89, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x8a, "TXA", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x8b, "XAA", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x8c, "STY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x8d, "STA", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x8e, "STX", 3, 4, AddressingMode::Absolute),
+    OpCode::unofficial(0x8f, "SAX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x90, "BCC", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x91, "STA", 2, 6, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0x92, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x93, "AHX", 2, 6, AddressingMode::Indirect_Y),
+    OpCode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPage_Y),
+    OpCode::unofficial(0x97, "SAX", 2, 4, AddressingMode::ZeroPage_Y),
+    OpCode::new(0x98, "TYA", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x99, "STA", 3, 5, AddressingMode::Absolute_Y),
+    OpCode::new(0x9a, "TXS", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x9b, "TAS", 3, 5, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0x9c, "SHY", 3, 5, AddressingMode::Absolute_X),
+    OpCode::new(0x9d, "STA", 3, 5, AddressingMode::Absolute_X),
+    OpCode::unofficial(0x9e, "SHX", 3, 5, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0x9f, "AHX", 3, 5, AddressingMode::Absolute_Y),
+    OpCode::new(0xa0, "LDY", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xa1, "LDA", 2, 6, AddressingMode::Indirect_X),
+    OpCode::new(0xa2, "LDX", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0xa3, "LAX", 2, 6, AddressingMode::Indirect_X),
+    OpCode::new(0xa4, "LDY", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xa5, "LDA", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xa6, "LDX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::unofficial(0xa7, "LAX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xa8, "TAY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xa9, "LDA", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xaa, "TAX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xab, "LAX", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xac, "LDY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xad, "LDA", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xae, "LDX", 3, 4, AddressingMode::Absolute),
+    OpCode::unofficial(0xaf, "LAX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xb0, "BCS", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xb1, "LDA", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0xb2, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xb3, "LAX", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0xb4, "LDY", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xb5, "LDA", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xb6, "LDX", 2, 4, AddressingMode::ZeroPage_Y),
+    OpCode::unofficial(0xb7, "LAX", 2, 4, AddressingMode::ZeroPage_Y),
+    OpCode::new(0xb8, "CLV", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xb9, "LDA", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xba, "TSX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xbb, "LAS", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xbc, "LDY", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xbd, "LDA", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xbe, "LDX", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0xbf, "LAX", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xc0, "CPY", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xc1, "CMP", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0xc2, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0xc3, "DCP", 2, 8, AddressingMode::Indirect_X),
+    OpCode::new(0xc4, "CPY", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xc5, "CMP", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xc6, "DEC", 2, 5, AddressingMode::ZeroPage),
+    OpCode::unofficial(0xc7, "DCP", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xc8, "INY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xc9, "CMP", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xca, "DEX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xcb, "AXS", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xcc, "CPY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xcd, "CMP", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xce, "DEC", 3, 6, AddressingMode::Absolute),
+    OpCode::unofficial(0xcf, "DCP", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xd0, "BNE", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xd1, "CMP", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0xd2, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xd3, "DCP", 2, 8, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0xd4, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd5, "CMP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd6, "DEC", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::unofficial(0xd7, "DCP", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd8, "CLD", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xd9, "CMP", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0xda, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xdb, "DCP", 3, 7, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0xdc, "NOP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xdd, "CMP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xde, "DEC", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0xdf, "DCP", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0xe0, "CPX", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xe1, "SBC", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0xe2, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0xe3, "ISB", 2, 8, AddressingMode::Indirect_X),
+    OpCode::new(0xe4, "CPX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xe5, "SBC", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xe6, "INC", 2, 5, AddressingMode::ZeroPage),
+    OpCode::unofficial(0xe7, "ISB", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xe8, "INX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xe9, "SBC", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xea, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xeb, "SBC", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xec, "CPX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xed, "SBC", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xee, "INC", 3, 6, AddressingMode::Absolute),
+    OpCode::unofficial(0xef, "ISB", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xf0, "BEQ", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xf1, "SBC", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::unofficial(0xf2, "KIL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xf3, "ISB", 2, 8, AddressingMode::Indirect_Y),
+    OpCode::unofficial(0xf4, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf5, "SBC", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf6, "INC", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::unofficial(0xf7, "ISB", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf8, "SED", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xf9, "SBC", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::unofficial(0xfa, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xfb, "ISB", 3, 7, AddressingMode::Absolute_Y),
+    OpCode::unofficial(0xfc, "NOP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xfd, "SBC", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xfe, "INC", 3, 7, AddressingMode::Absolute_X),
+    OpCode::unofficial(0xff, "ISB", 3, 7, AddressingMode::Absolute_X),
+];
+
+// The WDC 65C02: the official NMOS instructions plus the CMOS additions. Opcodes that were
+// undocumented on the NMOS chip are reserved NOPs here, marked unofficial.
+pub static OPCODES_65C02: [OpCode; 256] = [
+    OpCode::new(0x00, "BRK", 1, 7, AddressingMode::NoneAddressing),
+    OpCode::new(0x01, "ORA", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x02, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x03, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x04, "TSB", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x05, "ORA", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x06, "ASL", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x07, "RMB0", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x08, "PHP", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x09, "ORA", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x0a, "ASL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x0b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x0c, "TSB", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x0d, "ORA", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x0e, "ASL", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x0f, "BBR0", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x10, "BPL", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x11, "ORA", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0x12, "ORA", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0x13, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x14, "TRB", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x15, "ORA", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x16, "ASL", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x17, "RMB1", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x18, "CLC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x19, "ORA", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0x1a, "INC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x1b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x1c, "TRB", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x1d, "ORA", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x1e, "ASL", 3, 6, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x1f, "BBR1", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x20, "JSR", 3, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x21, "AND", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x22, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x23, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x24, "BIT", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x25, "AND", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x26, "ROL", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x27, "RMB2", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x28, "PLP", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x29, "AND", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x2a, "ROL", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x2b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x2c, "BIT", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x2d, "AND", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x2e, "ROL", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x2f, "BBR2", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x30, "BMI", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x31, "AND", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0x32, "AND", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0x33, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x34, "BIT", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x35, "AND", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x36, "ROL", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x37, "RMB3", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x38, "SEC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x39, "AND", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0x3a, "DEC", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x3b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x3c, "BIT", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x3d, "AND", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x3e, "ROL", 3, 6, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x3f, "BBR3", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x40, "RTI", 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x41, "EOR", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x42, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x43, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x44, "NOP", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x45, "EOR", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x46, "LSR", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x47, "RMB4", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x48, "PHA", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0x49, "EOR", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x4a, "LSR", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x4b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x4c, "JMP", 3, 3, AddressingMode::Absolute),
+    OpCode::new(0x4d, "EOR", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x4e, "LSR", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x4f, "BBR4", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x50, "BVC", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x51, "EOR", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0x52, "EOR", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0x53, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x54, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x55, "EOR", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x56, "LSR", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x57, "RMB5", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x58, "CLI", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x59, "EOR", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0x5a, "PHY", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x5b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x5c, "NOP", 3, 8, AddressingMode::NoneAddressing),
+    OpCode::new(0x5d, "EOR", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x5e, "LSR", 3, 6, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x5f, "BBR5", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x60, "RTS", 1, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x61, "ADC", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x62, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x63, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x64, "STZ", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x65, "ADC", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x66, "ROR", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x67, "RMB6", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x68, "PLA", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::new(0x69, "ADC", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x6a, "ROR", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x6b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x6c, "JMP", 3, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x6d, "ADC", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x6e, "ROR", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0x6f, "BBR6", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x70, "BVS", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x71, "ADC", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0x72, "ADC", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0x73, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x74, "STZ", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x75, "ADC", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x76, "ROR", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0x77, "RMB7", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x78, "SEI", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x79, "ADC", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0x7a, "PLY", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x7b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x7c, "JMP", 3, 6, AddressingMode::NoneAddressing),
+    OpCode::new(0x7d, "ADC", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x7e, "ROR", 3, 6, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0x7f, "BBR7", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x80, "BRA", 2, 2, AddressingMode::NoneAddressing), // Always taken: +1, +1 more if to a new page
+    OpCode::new(0x81, "STA", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0x82, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0x83, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x84, "STY", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x85, "STA", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x86, "STX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0x87, "SMB0", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x88, "DEY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x89, "BIT", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0x8a, "TXA", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x8b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x8c, "STY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x8d, "STA", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x8e, "STX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x8f, "BBS0", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x90, "BCC", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0x91, "STA", 2, 6, AddressingMode::Indirect_Y),
+    OpCode::new(0x92, "STA", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0x93, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x94, "STY", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x95, "STA", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0x96, "STX", 2, 4, AddressingMode::ZeroPage_Y),
+    OpCode::new(0x97, "SMB1", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0x98, "TYA", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0x99, "STA", 3, 5, AddressingMode::Absolute_Y),
+    OpCode::new(0x9a, "TXS", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0x9b, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0x9c, "STZ", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0x9d, "STA", 3, 5, AddressingMode::Absolute_X),
+    OpCode::new(0x9e, "STZ", 3, 5, AddressingMode::Absolute_X),
+    OpCode::new(0x9f, "BBS1", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xa0, "LDY", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xa1, "LDA", 2, 6, AddressingMode::Indirect_X),
+    OpCode::new(0xa2, "LDX", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0xa3, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xa4, "LDY", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xa5, "LDA", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xa6, "LDX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xa7, "SMB2", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xa8, "TAY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xa9, "LDA", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xaa, "TAX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xab, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xac, "LDY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xad, "LDA", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xae, "LDX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xaf, "BBS2", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xb0, "BCS", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xb1, "LDA", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0xb2, "LDA", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0xb3, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xb4, "LDY", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xb5, "LDA", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xb6, "LDX", 2, 4, AddressingMode::ZeroPage_Y),
+    OpCode::new(0xb7, "SMB3", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xb8, "CLV", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xb9, "LDA", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xba, "TSX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xbb, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xbc, "LDY", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xbd, "LDA", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xbe, "LDX", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xbf, "BBS3", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xc0, "CPY", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xc1, "CMP", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0xc2, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0xc3, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xc4, "CPY", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xc5, "CMP", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xc6, "DEC", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xc7, "SMB4", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xc8, "INY", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xc9, "CMP", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xca, "DEX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xcb, "WAI", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0xcc, "CPY", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xcd, "CMP", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xce, "DEC", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xcf, "BBS4", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xd0, "BNE", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xd1, "CMP", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0xd2, "CMP", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0xd3, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xd4, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd5, "CMP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd6, "DEC", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0xd7, "SMB5", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xd8, "CLD", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xd9, "CMP", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xda, "PHX", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::new(0xdb, "STP", 1, 3, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xdc, "NOP", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xdd, "CMP", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xde, "DEC", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0xdf, "BBS5", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xe0, "CPX", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xe1, "SBC", 2, 6, AddressingMode::Indirect_X),
+    OpCode::unofficial(0xe2, "NOP", 2, 2, AddressingMode::Immediate),
+    OpCode::unofficial(0xe3, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xe4, "CPX", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xe5, "SBC", 2, 3, AddressingMode::ZeroPage),
+    OpCode::new(0xe6, "INC", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xe7, "SMB6", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xe8, "INX", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xe9, "SBC", 2, 2, AddressingMode::Immediate),
+    OpCode::new(0xea, "NOP", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xeb, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::new(0xec, "CPX", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xed, "SBC", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xee, "INC", 3, 6, AddressingMode::Absolute),
+    OpCode::new(0xef, "BBS6", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xf0, "BEQ", 2, 2, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+    OpCode::new(0xf1, "SBC", 2, 5, AddressingMode::Indirect_Y), // +1 if page crossed
+    OpCode::new(0xf2, "SBC", 2, 5, AddressingMode::ZeroPage_Indirect),
+    OpCode::unofficial(0xf3, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xf4, "NOP", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf5, "SBC", 2, 4, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf6, "INC", 2, 6, AddressingMode::ZeroPage_X),
+    OpCode::new(0xf7, "SMB7", 2, 5, AddressingMode::ZeroPage),
+    OpCode::new(0xf8, "SED", 1, 2, AddressingMode::NoneAddressing),
+    OpCode::new(0xf9, "SBC", 3, 4, AddressingMode::Absolute_Y), // +1 if page crossed
+    OpCode::new(0xfa, "PLX", 1, 4, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xfb, "NOP", 1, 1, AddressingMode::NoneAddressing),
+    OpCode::unofficial(0xfc, "NOP", 3, 4, AddressingMode::Absolute),
+    OpCode::new(0xfd, "SBC", 3, 4, AddressingMode::Absolute_X), // +1 if page crossed
+    OpCode::new(0xfe, "INC", 3, 7, AddressingMode::Absolute_X),
+    OpCode::new(0xff, "BBS7", 3, 5, AddressingMode::NoneAddressing), // +1 if branch succeeds, +1 more if to a new page
+];
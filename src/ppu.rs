@@ -0,0 +1,1211 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::cartridge::Mirroring;
+use crate::frame::Frame;
+use crate::mapper::Nrom;
+use crate::mapper::SharedMapper;
+use crate::palette::SYSTEM_PALETTE;
+
+// PPUCTRL ($2000), write only:
+// V P H B S I N N
+// NMI on vblank, PPU master/slave, sprite height, background pattern table,
+// sprite pattern table, VRAM address increment, base nametable (2 bits)
+const CTRL_NAMETABLE: u8 = 0b0000_0011;
+const CTRL_VRAM_ADD_INCREMENT: u8 = 0b0000_0100;
+const CTRL_SPRITE_PATTERN_ADDR: u8 = 0b0000_1000;
+const CTRL_BACKGROUND_PATTERN_ADDR: u8 = 0b0001_0000;
+const CTRL_SPRITE_SIZE: u8 = 0b0010_0000;
+const CTRL_GENERATE_NMI: u8 = 0b1000_0000;
+
+// PPUMASK ($2001), write only:
+// B G R s b M m G
+// Emphasize blue, green, red, show sprites, show background,
+// show sprites in leftmost 8 pixels, show background in leftmost 8 pixels, greyscale
+const MASK_GREYSCALE: u8 = 0b0000_0001;
+const MASK_SHOW_BACKGROUND_LEFT: u8 = 0b0000_0010;
+const MASK_SHOW_SPRITES_LEFT: u8 = 0b0000_0100;
+const MASK_SHOW_BACKGROUND: u8 = 0b0000_1000;
+const MASK_SHOW_SPRITES: u8 = 0b0001_0000;
+
+// PPUSTATUS ($2002), read only:
+// V S O . . . . .
+// In vblank, sprite 0 hit, sprite overflow, the rest is whatever was last on the PPU bus
+const STATUS_SPRITE_OVERFLOW: u8 = 0b0010_0000;
+const STATUS_SPRITE_ZERO_HIT: u8 = 0b0100_0000;
+const STATUS_VBLANK: u8 = 0b1000_0000;
+
+// OAM holds 64 sprites of 4 bytes: Y, tile, attributes, X. Attributes:
+// V H P . . . C C
+// Flip vertically, flip horizontally, priority (behind background), palette (2 bits)
+const SPRITE_BEHIND_BACKGROUND: u8 = 0b0010_0000;
+const SPRITE_FLIP_HORIZONTAL: u8 = 0b0100_0000;
+const SPRITE_FLIP_VERTICAL: u8 = 0b1000_0000;
+
+// 341 PPU dots per scanline, 262 scanlines per frame:
+// 0-239 visible, 240 post-render, 241-260 vblank, 261 pre-render
+const DOTS_PER_SCANLINE: u16 = 341;
+const VBLANK_SCANLINE: u16 = 241;
+const PRE_RENDER_SCANLINE: u16 = 261;
+
+// PPU address space:
+// $0000-$1FFF pattern tables (CHR), $2000-$2FFF nametables (mirrored up to $3EFF),
+// $3F00-$3F1F palette RAM (mirrored up to $3FFF)
+const NAMETABLES: u16 = 0x2000;
+const NAMETABLES_MIRRORS_END: u16 = 0x3EFF;
+const PALETTE_TABLE: u16 = 0x3F00;
+
+// A sprite as copied to secondary OAM during evaluation. Everything the next scanline needs is
+// latched here, so OAM writes or a sprite size switch after dot 257 only show up a line later.
+#[derive(Clone, Copy, Default)]
+struct LineSprite {
+    index: u8, // Position in OAM, sprite 0 is the one that can hit
+    tile: u8,
+    attributes: u8,
+    x: u8,
+    row: u16, // Row of the sprite on the next scanline, before flipping
+    height: u16,
+}
+
+// 2C02 Picture Processing Unit.
+//
+// Scrolling uses the internal "loopy" registers the real chip has:
+//   v: current VRAM address (15 bits)  yyy NN YYYYY XXXXX
+//   t: temporary VRAM address          fine Y, nametable, coarse Y, coarse X
+//   x: fine X scroll (3 bits)
+//   w: first/second write toggle shared by PPUSCROLL and PPUADDR
+// Each visible scanline is drawn in one go at its first dot, from whatever v holds then.
+pub struct NesPPU {
+    mapper: SharedMapper, // Pattern tables and nametable mirroring come from the cartridge
+    pub vram: [u8; 4096], // 2 KiB on the console, the other 2 KiB for four-screen boards
+    pub palette_table: [u8; 32],
+    pub oam_data: [u8; 256],
+    pub oam_addr: u8,
+    // Secondary OAM: the (up to 8) sprites on the next scanline
+    line_sprites: [LineSprite; 8],
+    line_sprite_count: usize,
+    sprite_zero_hit_dot: Option<u16>,
+
+    pub ctrl: u8,
+    pub mask: u8,
+    pub status: u8,
+    v: u16,
+    t: u16,
+    fine_x: u8,
+    write_toggle: bool,
+    read_buffer: u8, // PPUDATA reads lag one behind, except for the palette
+    io_latch: u8,    // Last value written to any register, seen when reading write only ones
+
+    pub scanline: u16,
+    pub cycle: u16,
+    odd_frame: bool,
+    frame_count: u64,
+    dots: u64,
+    a12: bool, // Level of address line A12, as the cartridge last saw it
+    pub frame: Frame,
+}
+
+impl NesPPU {
+    pub fn new(mapper: SharedMapper) -> Self {
+        NesPPU {
+            mapper,
+            vram: [0; 4096],
+            palette_table: [0; 32],
+            oam_data: [0; 256],
+            oam_addr: 0,
+            line_sprites: [LineSprite::default(); 8],
+            line_sprite_count: 0,
+            sprite_zero_hit_dot: None,
+            ctrl: 0,
+            mask: 0,
+            status: 0,
+            v: 0,
+            t: 0,
+            fine_x: 0,
+            write_toggle: false,
+            read_buffer: 0,
+            io_latch: 0,
+            scanline: 0,
+            cycle: 0,
+            odd_frame: false,
+            frame_count: 0,
+            dots: 0,
+            a12: false,
+            frame: Frame::new(),
+        }
+    }
+    pub fn new_empty_rom() -> Self {
+        NesPPU::new(Rc::new(RefCell::new(Nrom::from_chr(vec![0; 0x2000], Mirroring::Horizontal))))
+    }
+
+    // Number of frames that have reached vblank since power on
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+    // Level of the PPU's /NMI output: low (true here) while in vblank with NMI enabled
+    pub fn nmi_line(&self) -> bool {
+        self.status & STATUS_VBLANK != 0 && self.ctrl & CTRL_GENERATE_NMI != 0
+    }
+    // Current VRAM address (loopy v)
+    pub fn vram_addr(&self) -> u16 {
+        self.v
+    }
+    fn rendering_enabled(&self) -> bool {
+        self.mask & (MASK_SHOW_BACKGROUND | MASK_SHOW_SPRITES) != 0
+    }
+    // Whether the PPU is fetching tiles and sprites on this scanline
+    fn fetching(&self) -> bool {
+        self.rendering_enabled() && (self.scanline < 240 || self.scanline == PRE_RENDER_SCANLINE)
+    }
+    fn vram_addr_increment(&self) -> u16 {
+        if self.ctrl & CTRL_VRAM_ADD_INCREMENT == 0 {
+            1
+        } else {
+            32
+        }
+    }
+
+    // $2000
+    pub fn write_to_ctrl(&mut self, value: u8) {
+        self.io_latch = value;
+        self.ctrl = value;
+        // t: ...GH.. ........ <- d: ......GH
+        self.t = (self.t & 0b1111_0011_1111_1111) | (((value & CTRL_NAMETABLE) as u16) << 10);
+    }
+    // $2001
+    pub fn write_to_mask(&mut self, value: u8) {
+        self.io_latch = value;
+        self.mask = value;
+    }
+    // $2002
+    pub fn read_status(&mut self) -> u8 {
+        let data: u8 = (self.status & 0b1110_0000) | (self.io_latch & 0b0001_1111);
+        self.status &= !STATUS_VBLANK;
+        self.write_toggle = false;
+        self.io_latch = data;
+        data
+    }
+    // $2003
+    pub fn write_to_oam_addr(&mut self, value: u8) {
+        self.io_latch = value;
+        self.oam_addr = value;
+    }
+    // $2004
+    pub fn write_to_oam_data(&mut self, value: u8) {
+        self.io_latch = value;
+        self.oam_data[self.oam_addr as usize] = value;
+        self.oam_addr = self.oam_addr.wrapping_add(1);
+    }
+    pub fn read_oam_data(&mut self) -> u8 {
+        let mut data: u8 = self.oam_data[self.oam_addr as usize];
+        // Bits 2-4 of the attribute byte don't exist and always read back as 0
+        if self.oam_addr & 0b11 == 2 {
+            data &= 0b1110_0011;
+        }
+        self.io_latch = data;
+        data
+    }
+    // $4014: the CPU copies a whole page into OAM, starting at OAMADDR
+    pub fn write_oam_dma(&mut self, data: &[u8; 256]) {
+        for byte in data.iter() {
+            self.oam_data[self.oam_addr as usize] = *byte;
+            self.oam_addr = self.oam_addr.wrapping_add(1);
+        }
+    }
+    // $2005
+    pub fn write_to_scroll(&mut self, value: u8) {
+        self.io_latch = value;
+        if !self.write_toggle {
+            // t: ....... ...ABCDE <- d: ABCDE...
+            // x:              FGH <- d: .....FGH
+            self.t = (self.t & 0b1111_1111_1110_0000) | (value >> 3) as u16;
+            self.fine_x = value & 0b111;
+        } else {
+            // t: FGH..AB CDE..... <- d: ABCDEFGH
+            self.t = (self.t & 0b1000_1100_0001_1111)
+                | (((value & 0b111) as u16) << 12)
+                | (((value >> 3) as u16) << 5);
+        }
+        self.write_toggle = !self.write_toggle;
+    }
+    // $2006
+    pub fn write_to_ppu_addr(&mut self, value: u8) {
+        self.io_latch = value;
+        if !self.write_toggle {
+            // t: .CDEFGH ........ <- d: ..CDEFGH, bit 14 cleared
+            self.t = (self.t & 0x00FF) | (((value & 0b0011_1111) as u16) << 8);
+        } else {
+            // t: ....... ABCDEFGH <- d: ABCDEFGH, then v = t
+            self.t = (self.t & 0xFF00) | value as u16;
+            self.v = self.t;
+            self.drive_vram_addr();
+        }
+        self.write_toggle = !self.write_toggle;
+    }
+    // $2007
+    pub fn write_to_data(&mut self, value: u8) {
+        self.io_latch = value;
+        let addr: u16 = self.v & 0x3FFF;
+        self.ppu_write(addr, value);
+        self.v = self.v.wrapping_add(self.vram_addr_increment()) & 0x7FFF;
+        self.drive_vram_addr();
+    }
+    pub fn read_data(&mut self) -> u8 {
+        let addr: u16 = self.v & 0x3FFF;
+        self.v = self.v.wrapping_add(self.vram_addr_increment()) & 0x7FFF;
+        self.drive_vram_addr();
+        let data: u8 = if addr >= PALETTE_TABLE {
+            // Palette reads come straight back, but the buffer still gets the nametable
+            // byte "underneath" the palette
+            self.read_buffer = self.ppu_read(addr - 0x1000);
+            (self.io_latch & 0b1100_0000) | self.read_palette(addr)
+        } else {
+            let result: u8 = self.read_buffer;
+            self.read_buffer = self.ppu_read(addr);
+            result
+        };
+        self.io_latch = data;
+        data
+    }
+    // Writing to PPUSTATUS does nothing except fill the data latch
+    pub fn write_to_status(&mut self, value: u8) {
+        self.io_latch = value;
+    }
+    // Reading one of the write only registers gives back the PPU's internal data latch
+    pub fn read_io_latch(&self) -> u8 {
+        self.io_latch
+    }
+
+    // Horizontal:
+    //   [ A ] [ a ]
+    //   [ B ] [ b ]
+    // Vertical:
+    //   [ A ] [ B ]
+    //   [ a ] [ b ]
+    // Single screen, lower and upper:
+    //   [ A ] [ a ]    [ B ] [ b ]
+    //   [ a ] [ a ]    [ b ] [ b ]
+    pub fn mirror_vram_addr(&self, addr: u16) -> u16 {
+        let mirrored_vram: u16 = addr & 0b0010_1111_1111_1111; // $3000-$3EFF -> $2000-$2EFF
+        let vram_index: u16 = mirrored_vram - NAMETABLES;
+        let name_table: u16 = vram_index / 0x400;
+        match (self.mapper.borrow().mirroring(), name_table) {
+            (Mirroring::Vertical, 2) | (Mirroring::Vertical, 3) => vram_index - 0x800,
+            (Mirroring::Horizontal, 1) | (Mirroring::Horizontal, 2) => vram_index - 0x400,
+            (Mirroring::Horizontal, 3) => vram_index - 0x800,
+            (Mirroring::SingleScreenLower, _) => vram_index & 0x3FF,
+            (Mirroring::SingleScreenUpper, _) => 0x400 | (vram_index & 0x3FF),
+            _ => vram_index,
+        }
+    }
+    fn palette_index(addr: u16) -> usize {
+        let index: usize = (addr & 0x1F) as usize;
+        // $3F10/$3F14/$3F18/$3F1C are mirrors of $3F00/$3F04/$3F08/$3F0C
+        if index >= 0x10 && index.is_multiple_of(4) {
+            index - 0x10
+        } else {
+            index
+        }
+    }
+    fn read_palette(&self, addr: u16) -> u8 {
+        let data: u8 = self.palette_table[NesPPU::palette_index(addr)];
+        if self.mask & MASK_GREYSCALE != 0 {
+            data & 0x30
+        } else {
+            data & 0x3F
+        }
+    }
+    fn ppu_read(&self, addr: u16) -> u8 {
+        match addr {
+            0..=0x1FFF => self.mapper.borrow().ppu_read(addr),
+            NAMETABLES..=NAMETABLES_MIRRORS_END => self.vram[self.mirror_vram_addr(addr) as usize],
+            _ => self.read_palette(addr),
+        }
+    }
+    fn ppu_write(&mut self, addr: u16, value: u8) {
+        match addr {
+            0..=0x1FFF => self.mapper.borrow_mut().ppu_write(addr, value),
+            NAMETABLES..=NAMETABLES_MIRRORS_END => {
+                let index: usize = self.mirror_vram_addr(addr) as usize;
+                self.vram[index] = value;
+            }
+            _ => self.palette_table[NesPPU::palette_index(addr)] = value,
+        }
+    }
+
+    // Outside of rendering the address bus holds v, so PPUADDR and PPUDATA can move A12 too
+    fn drive_vram_addr(&mut self) {
+        if !self.fetching() {
+            self.set_a12(self.v & 0x1000 != 0);
+        }
+    }
+    fn set_a12(&mut self, high: bool) {
+        if high != self.a12 {
+            self.a12 = high;
+            self.mapper.borrow_mut().ppu_a12(high, self.dots);
+        }
+    }
+    // A12 as the fetches of the current dot drive it, where it changes. Every 8 dots the PPU
+    // fetches a nametable byte (dot 1), an attribute or garbage byte (3) and two pattern bytes
+    // (5 and 7): background tiles for dots 1-256 and 321-336, sprites for 257-320.
+    fn fetch_a12(&self) -> Option<bool> {
+        match (self.cycle, self.cycle % 8) {
+            (1..=256 | 321..=336, 5) => Some(self.ctrl & CTRL_BACKGROUND_PATTERN_ADDR != 0),
+            (257..=320, 5) => {
+                let slot: usize = (self.cycle as usize - 257) / 8;
+                if self.sprite_height() == 16 {
+                    // Empty slots fetch tile $FF
+                    let tile: u8 = if slot < self.line_sprite_count {
+                        self.line_sprites[slot].tile
+                    } else {
+                        0xFF
+                    };
+                    Some(tile & 1 != 0)
+                } else {
+                    Some(self.ctrl & CTRL_SPRITE_PATTERN_ADDR != 0)
+                }
+            }
+            (1..=336, 1) | (337 | 339, _) => Some(false),
+            _ => None,
+        }
+    }
+
+    // Runs the PPU for the given number of dots (3 per CPU cycle on NTSC).
+    // Returns true if a new frame entered vblank along the way.
+    pub fn tick(&mut self, cycles: u32) -> bool {
+        let mut frame_done: bool = false;
+        for _ in 0..cycles {
+            frame_done |= self.clock();
+        }
+        frame_done
+    }
+
+    fn clock(&mut self) -> bool {
+        let mut frame_done: bool = false;
+        let rendering: bool = self.rendering_enabled();
+
+        if self.scanline < 240 && self.cycle == 0 {
+            self.sprite_zero_hit_dot = None;
+            self.render_scanline();
+        }
+        if self.sprite_zero_hit_dot == Some(self.cycle) {
+            self.status |= STATUS_SPRITE_ZERO_HIT;
+            self.sprite_zero_hit_dot = None;
+        }
+        if self.fetching() {
+            match self.cycle {
+                256 => self.increment_y(),
+                257 => {
+                    self.copy_horizontal_bits();
+                    self.oam_addr = 0;
+                    if self.scanline == PRE_RENDER_SCANLINE {
+                        // Nothing is evaluated for scanline 0; sprites can't start there
+                        self.line_sprite_count = 0;
+                    } else {
+                        self.evaluate_sprites(self.scanline);
+                    }
+                }
+                280..=304 if self.scanline == PRE_RENDER_SCANLINE => self.copy_vertical_bits(),
+                _ => {}
+            }
+            if let Some(high) = self.fetch_a12() {
+                self.set_a12(high);
+            }
+        }
+        if self.scanline == VBLANK_SCANLINE && self.cycle == 1 {
+            self.status |= STATUS_VBLANK;
+            self.frame_count += 1;
+            frame_done = true;
+        }
+        if self.scanline == PRE_RENDER_SCANLINE && self.cycle == 1 {
+            self.status &= !(STATUS_VBLANK | STATUS_SPRITE_ZERO_HIT | STATUS_SPRITE_OVERFLOW);
+        }
+        if !rendering && self.scanline < 240 && self.cycle == 257 {
+            self.line_sprite_count = 0;
+        }
+
+        self.dots += 1;
+        self.cycle += 1;
+        // With rendering on, the pre-render line of odd frames is one dot shorter
+        let skip_dot: bool = self.scanline == PRE_RENDER_SCANLINE
+            && self.cycle == DOTS_PER_SCANLINE - 1
+            && self.odd_frame
+            && rendering;
+        if self.cycle >= DOTS_PER_SCANLINE || skip_dot {
+            self.cycle = 0;
+            self.scanline += 1;
+            if self.scanline > PRE_RENDER_SCANLINE {
+                self.scanline = 0;
+                self.odd_frame = !self.odd_frame;
+            }
+        }
+        frame_done
+    }
+
+    fn increment_y(&mut self) {
+        if self.v & 0x7000 != 0x7000 {
+            self.v += 0x1000; // fine Y < 7
+        } else {
+            self.v &= !0x7000;
+            let mut coarse_y: u16 = (self.v & 0x03E0) >> 5;
+            if coarse_y == 29 {
+                coarse_y = 0;
+                self.v ^= 0x0800; // switch vertical nametable
+            } else if coarse_y == 31 {
+                coarse_y = 0; // attribute rows wrap without switching nametable
+            } else {
+                coarse_y += 1;
+            }
+            self.v = (self.v & !0x03E0) | (coarse_y << 5);
+        }
+    }
+    fn copy_horizontal_bits(&mut self) {
+        // v: ....A.. ...BCDEF <- t: ....A.. ...BCDEF
+        self.v = (self.v & !0x041F) | (self.t & 0x041F);
+    }
+    fn copy_vertical_bits(&mut self) {
+        // v: GHIA.BC DEF..... <- t: GHIA.BC DEF.....
+        self.v = (self.v & !0x7BE0) | (self.t & 0x7BE0);
+    }
+
+    fn sprite_height(&self) -> u16 {
+        if self.ctrl & CTRL_SPRITE_SIZE == 0 {
+            8
+        } else {
+            16
+        }
+    }
+
+    // Sprite evaluation as done during dots 65-256 of a scanline, picking the sprites for the
+    // next one. Only 8 fit in secondary OAM; after that the PPU keeps scanning for overflow but
+    // increments both the sprite index and the byte within the sprite, so it checks X, tile or
+    // attribute bytes as if they were Y coordinates, missing some overflows and inventing others.
+    fn evaluate_sprites(&mut self, line: u16) {
+        let height: u16 = self.sprite_height();
+        let in_range = |y: u8| -> bool {
+            let row: i32 = line as i32 - y as i32;
+            (0..height as i32).contains(&row)
+        };
+
+        self.line_sprite_count = 0;
+        let mut n: usize = 0;
+        while n < 64 && self.line_sprite_count < 8 {
+            let y: u8 = self.oam_data[n * 4];
+            if in_range(y) {
+                self.line_sprites[self.line_sprite_count] = LineSprite {
+                    index: n as u8,
+                    tile: self.oam_data[n * 4 + 1],
+                    attributes: self.oam_data[n * 4 + 2],
+                    x: self.oam_data[n * 4 + 3],
+                    row: line - y as u16,
+                    height,
+                };
+                self.line_sprite_count += 1;
+            }
+            n += 1;
+        }
+
+        let mut m: usize = 0;
+        while n < 64 {
+            if in_range(self.oam_data[n * 4 + m]) {
+                self.status |= STATUS_SPRITE_OVERFLOW;
+                break;
+            }
+            n += 1;
+            m = (m + 1) & 0b11; // The hardware bug
+        }
+    }
+
+    // Pattern bytes (low, high) for the latched row of a sprite, after vertical flip
+    fn sprite_pattern(&self, sprite: &LineSprite) -> (u8, u8) {
+        let tile: u16 = sprite.tile as u16;
+        let row: u16 = if sprite.attributes & SPRITE_FLIP_VERTICAL != 0 {
+            sprite.height - 1 - sprite.row
+        } else {
+            sprite.row
+        };
+        let addr: u16 = if sprite.height == 16 {
+            // 8x16 sprites pick their own pattern table with bit 0 of the tile number
+            let bank: u16 = (tile & 1) * 0x1000;
+            let tile: u16 = (tile & 0xFE) + row / 8;
+            bank + tile * 16 + row % 8
+        } else {
+            let bank: u16 = if self.ctrl & CTRL_SPRITE_PATTERN_ADDR == 0 {
+                0
+            } else {
+                0x1000
+            };
+            bank + tile * 16 + row
+        };
+        (self.ppu_read(addr), self.ppu_read(addr + 8))
+    }
+
+    fn render_scanline(&mut self) {
+        let y: usize = self.scanline as usize;
+        // Palette RAM index of every pixel on the line, and whether the background is opaque
+        let mut line: [u8; Frame::WIDTH] = [0; Frame::WIDTH];
+        let mut background_opaque: [bool; Frame::WIDTH] = [false; Frame::WIDTH];
+
+        if self.mask & MASK_SHOW_BACKGROUND != 0 {
+            self.render_background(&mut line, &mut background_opaque);
+        }
+        if self.mask & MASK_SHOW_SPRITES != 0 {
+            self.render_sprites(&mut line, &background_opaque);
+        }
+
+        for (x, palette_idx) in line.iter().enumerate() {
+            let color: u8 = self.read_palette(PALETTE_TABLE + *palette_idx as u16);
+            self.frame.set_pixel(x, y, SYSTEM_PALETTE[color as usize]);
+        }
+    }
+
+    fn render_background(&self, line: &mut [u8; Frame::WIDTH], opaque: &mut [bool; Frame::WIDTH]) {
+        let bank: u16 = if self.ctrl & CTRL_BACKGROUND_PATTERN_ADDR == 0 {
+            0
+        } else {
+            0x1000
+        };
+        let fine_y: u16 = (self.v >> 12) & 0b111;
+        let mut v: u16 = self.v;
+        // 33 tiles cover the 256 pixels when fine X scroll is not 0
+        for tile_column in 0..33 {
+            let tile_idx: u16 = self.ppu_read(NAMETABLES | (v & 0x0FFF)) as u16;
+            let attribute_addr: u16 =
+                0x23C0 | (v & 0x0C00) | ((v >> 4) & 0x38) | ((v >> 2) & 0x07);
+            let attribute: u8 = self.ppu_read(attribute_addr);
+            // Each attribute byte covers 4x4 tiles, two bits per 2x2 quadrant
+            let shift: u16 = ((v >> 4) & 0b100) | (v & 0b10);
+            let palette_idx: u8 = (attribute >> shift) & 0b11;
+
+            let lower: u8 = self.ppu_read(bank + tile_idx * 16 + fine_y);
+            let upper: u8 = self.ppu_read(bank + tile_idx * 16 + fine_y + 8);
+
+            for pixel in 0..8 {
+                let screen_x: i32 = tile_column * 8 + pixel - self.fine_x as i32;
+                if !(0..Frame::WIDTH as i32).contains(&screen_x) {
+                    continue;
+                }
+                if screen_x < 8 && self.mask & MASK_SHOW_BACKGROUND_LEFT == 0 {
+                    continue;
+                }
+                let bit: u8 = 7 - pixel as u8;
+                let value: u8 = (((upper >> bit) & 1) << 1) | ((lower >> bit) & 1);
+                if value == 0 {
+                    continue;
+                }
+                line[screen_x as usize] = palette_idx * 4 + value;
+                opaque[screen_x as usize] = true;
+            }
+
+            // Coarse X increment, switching horizontal nametable on wrap
+            if v & 0x001F == 31 {
+                v &= !0x001F;
+                v ^= 0x0400;
+            } else {
+                v += 1;
+            }
+        }
+    }
+
+    fn render_sprites(&mut self, line: &mut [u8; Frame::WIDTH], background_opaque: &[bool; Frame::WIDTH]) {
+        // Lower OAM index wins, even when that sprite ends up behind the background
+        let mut sprite_drawn: [bool; Frame::WIDTH] = [false; Frame::WIDTH];
+        for i in 0..self.line_sprite_count {
+            let sprite: LineSprite = self.line_sprites[i];
+            let attributes: u8 = sprite.attributes;
+            let sprite_x: usize = sprite.x as usize;
+            let (lower, upper) = self.sprite_pattern(&sprite);
+            let palette_idx: u8 = attributes & 0b11;
+
+            for pixel in 0..8 {
+                let x: usize = sprite_x + pixel;
+                if x >= Frame::WIDTH || sprite_drawn[x] {
+                    continue;
+                }
+                if x < 8 && self.mask & MASK_SHOW_SPRITES_LEFT == 0 {
+                    continue;
+                }
+                let bit: usize = if attributes & SPRITE_FLIP_HORIZONTAL != 0 {
+                    pixel
+                } else {
+                    7 - pixel
+                };
+                let value: u8 = (((upper >> bit) & 1) << 1) | ((lower >> bit) & 1);
+                if value == 0 {
+                    continue;
+                }
+                sprite_drawn[x] = true;
+
+                // Never at x=255, and the hit happens on the dot after the pixel is output
+                if sprite.index == 0
+                    && background_opaque[x]
+                    && x != 255
+                    && self.sprite_zero_hit_dot.is_none()
+                    && self.status & STATUS_SPRITE_ZERO_HIT == 0
+                {
+                    self.sprite_zero_hit_dot = Some(x as u16 + 2);
+                }
+
+                if attributes & SPRITE_BEHIND_BACKGROUND == 0 || !background_opaque[x] {
+                    line[x] = 0x10 + palette_idx * 4 + value;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+
+    const SCANLINE: u32 = DOTS_PER_SCANLINE as u32;
+
+    fn ppu_with_chr(chr: Vec<u8>, mirroring: Mirroring) -> NesPPU {
+        NesPPU::new(Rc::new(RefCell::new(Nrom::from_chr(chr, mirroring))))
+    }
+
+    #[test]
+    fn test_ppu_vram_writes() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ppu_addr(0x23);
+        ppu.write_to_ppu_addr(0x05);
+        ppu.write_to_data(0x66);
+
+        assert_eq!(ppu.vram[0x0305], 0x66);
+    }
+
+    #[test]
+    fn test_ppu_vram_reads() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0);
+        ppu.vram[0x0305] = 0x66;
+
+        ppu.write_to_ppu_addr(0x23);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.vram_addr(), 0x2306);
+        assert_eq!(ppu.read_data(), 0x66);
+    }
+
+    #[test]
+    fn test_ppu_vram_reads_cross_page() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0);
+        ppu.vram[0x01ff] = 0x66;
+        ppu.vram[0x0200] = 0x77;
+
+        ppu.write_to_ppu_addr(0x21);
+        ppu.write_to_ppu_addr(0xff);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x66);
+        assert_eq!(ppu.read_data(), 0x77);
+    }
+
+    #[test]
+    fn test_ppu_vram_reads_step_32() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0b100);
+        ppu.vram[0x01ff] = 0x66;
+        ppu.vram[0x01ff + 32] = 0x77;
+        ppu.vram[0x01ff + 64] = 0x88;
+
+        ppu.write_to_ppu_addr(0x21);
+        ppu.write_to_ppu_addr(0xff);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x66);
+        assert_eq!(ppu.read_data(), 0x77);
+        assert_eq!(ppu.read_data(), 0x88);
+    }
+
+    // Horizontal: https://wiki.nesdev.com/w/index.php/Mirroring
+    //   [0x2000 A ] [0x2400 a ]
+    //   [0x2800 B ] [0x2C00 b ]
+    #[test]
+    fn test_vram_horizontal_mirror() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ppu_addr(0x24);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.write_to_data(0x66); // write to a
+
+        ppu.write_to_ppu_addr(0x28);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.write_to_data(0x77); // write to B
+
+        ppu.write_to_ppu_addr(0x20);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x66); // read from A
+
+        ppu.write_to_ppu_addr(0x2C);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x77); // read from b
+    }
+
+    // Vertical: https://wiki.nesdev.com/w/index.php/Mirroring
+    //   [0x2000 A ] [0x2400 B ]
+    //   [0x2800 a ] [0x2C00 b ]
+    #[test]
+    fn test_vram_vertical_mirror() {
+        let mut ppu: NesPPU = ppu_with_chr(vec![0; 2048], Mirroring::Vertical);
+
+        ppu.write_to_ppu_addr(0x20);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.write_to_data(0x66); // write to A
+
+        ppu.write_to_ppu_addr(0x2C);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.write_to_data(0x77); // write to b
+
+        ppu.write_to_ppu_addr(0x28);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x66); // read from a
+
+        ppu.write_to_ppu_addr(0x24);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x77); // read from B
+    }
+
+    #[test]
+    fn test_vram_single_screen_mirror() {
+        let lower: NesPPU = ppu_with_chr(vec![0; 2048], Mirroring::SingleScreenLower);
+        let upper: NesPPU = ppu_with_chr(vec![0; 2048], Mirroring::SingleScreenUpper);
+        for addr in [0x2005, 0x2405, 0x2805, 0x2C05] {
+            assert_eq!(lower.mirror_vram_addr(addr), 0x0005);
+            assert_eq!(upper.mirror_vram_addr(addr), 0x0405);
+        }
+    }
+
+    #[test]
+    fn test_read_status_resets_latch() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.vram[0x0305] = 0x66;
+
+        ppu.write_to_ppu_addr(0x21);
+        ppu.write_to_ppu_addr(0x23);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_ne!(ppu.read_data(), 0x66);
+
+        ppu.read_status();
+
+        ppu.write_to_ppu_addr(0x23);
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x66);
+    }
+
+    #[test]
+    fn test_ppu_vram_mirroring() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0);
+        ppu.vram[0x0305] = 0x66;
+
+        ppu.write_to_ppu_addr(0x63); // 0x6305 -> 0x2305
+        ppu.write_to_ppu_addr(0x05);
+
+        ppu.read_data(); // load into buffer
+        assert_eq!(ppu.read_data(), 0x66);
+    }
+
+    #[test]
+    fn test_read_status_resets_vblank() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.status |= STATUS_VBLANK;
+
+        let status: u8 = ppu.read_status();
+
+        assert_eq!(status >> 7, 1);
+        assert_eq!(ppu.status >> 7, 0);
+    }
+
+    #[test]
+    fn test_palette_reads_are_not_buffered() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ppu_addr(0x3F);
+        ppu.write_to_ppu_addr(0x01);
+        ppu.write_to_data(0x21);
+
+        ppu.write_to_ppu_addr(0x3F);
+        ppu.write_to_ppu_addr(0x01);
+        assert_eq!(ppu.read_data(), 0x21);
+    }
+
+    #[test]
+    fn test_palette_background_mirrors() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ppu_addr(0x3F);
+        ppu.write_to_ppu_addr(0x10);
+        ppu.write_to_data(0x0F);
+        assert_eq!(ppu.palette_table[0x00], 0x0F);
+
+        // The whole of $3F20-$3FFF mirrors $3F00-$3F1F
+        ppu.write_to_ppu_addr(0x3F);
+        ppu.write_to_ppu_addr(0xE5);
+        ppu.write_to_data(0x2A);
+        assert_eq!(ppu.palette_table[0x05], 0x2A);
+    }
+
+    #[test]
+    fn test_oam_read_write() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_oam_addr(0x10);
+        ppu.write_to_oam_data(0x66);
+        ppu.write_to_oam_data(0x77);
+
+        ppu.write_to_oam_addr(0x10);
+        assert_eq!(ppu.read_oam_data(), 0x66);
+
+        ppu.write_to_oam_addr(0x11);
+        assert_eq!(ppu.read_oam_data(), 0x77);
+    }
+
+    #[test]
+    fn test_scroll_writes_fill_t_and_fine_x() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(0b01);
+        ppu.write_to_scroll(0b0111_1101); // coarse X 15, fine X 5
+        ppu.write_to_scroll(0b0101_1110); // coarse Y 11, fine Y 6
+        assert_eq!(ppu.t, 0b0110_0101_0110_1111); // fine Y 6, nametable 1, coarse Y 11, coarse X 15
+        assert_eq!(ppu.fine_x, 0b101);
+    }
+
+    #[test]
+    fn test_vblank_and_nmi_line() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        ppu.write_to_ctrl(CTRL_GENERATE_NMI);
+        // Up to dot 1 of scanline 241
+        assert!(!ppu.tick(VBLANK_SCANLINE as u32 * SCANLINE + 1));
+        assert!(ppu.tick(1));
+        assert!(ppu.status & STATUS_VBLANK != 0);
+        assert!(ppu.nmi_line());
+        assert_eq!(ppu.frame_count(), 1);
+
+        // Cleared on the pre-render scanline
+        ppu.tick((PRE_RENDER_SCANLINE - VBLANK_SCANLINE) as u32 * SCANLINE);
+        assert!(ppu.status & STATUS_VBLANK == 0);
+        assert!(!ppu.nmi_line());
+    }
+
+    #[test]
+    fn test_background_rendering() {
+        // Tile 1 is a solid block of color 3
+        let mut chr: Vec<u8> = vec![0; 0x2000];
+        for row in 0..16 {
+            chr[16 + row] = 0xFF;
+        }
+        let mut ppu: NesPPU = ppu_with_chr(chr, Mirroring::Horizontal);
+        ppu.palette_table[0] = 0x0F; // Backdrop black
+        ppu.palette_table[3] = 0x30; // Palette 0, color 3 white
+        ppu.palette_table[7] = 0x16; // Palette 1, color 3 red
+        ppu.vram[0] = 1; // Top left tile
+        ppu.vram[33] = 1; // Second row, second tile
+        ppu.vram[0x3C0] = 0b0000_0100; // Top right quadrant of first attribute uses palette 1
+        ppu.write_to_mask(MASK_SHOW_BACKGROUND | MASK_SHOW_BACKGROUND_LEFT);
+
+        // Start from the pre-render line so v gets loaded from t
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.tick(SCANLINE + 10 * SCANLINE);
+
+        assert_eq!(ppu.frame.get_pixel(0, 0), SYSTEM_PALETTE[0x30]);
+        assert_eq!(ppu.frame.get_pixel(7, 7), SYSTEM_PALETTE[0x30]);
+        assert_eq!(ppu.frame.get_pixel(8, 0), SYSTEM_PALETTE[0x0F]);
+        assert_eq!(ppu.frame.get_pixel(8, 8), SYSTEM_PALETTE[0x30]);
+        assert_eq!(ppu.frame.get_pixel(0, 8), SYSTEM_PALETTE[0x0F]);
+
+        // Same nametable entry drawn through palette 1
+        ppu.vram[2] = 1;
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.cycle = 0;
+        ppu.tick(2 * SCANLINE);
+        assert_eq!(ppu.frame.get_pixel(16, 0), SYSTEM_PALETTE[0x16]);
+    }
+
+    #[test]
+    fn test_fine_x_scroll() {
+        let mut chr: Vec<u8> = vec![0; 0x2000];
+        for row in 0..16 {
+            chr[16 + row] = 0xFF;
+        }
+        let mut ppu: NesPPU = ppu_with_chr(chr, Mirroring::Horizontal);
+        ppu.palette_table[3] = 0x30;
+        ppu.vram[1] = 1; // Second tile of the top row
+        ppu.write_to_scroll(3); // Scroll 3 pixels right
+        ppu.write_to_scroll(0);
+        ppu.write_to_mask(MASK_SHOW_BACKGROUND | MASK_SHOW_BACKGROUND_LEFT);
+
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.tick(2 * SCANLINE);
+
+        assert_eq!(ppu.frame.get_pixel(4, 0), SYSTEM_PALETTE[0x00]);
+        assert_eq!(ppu.frame.get_pixel(5, 0), SYSTEM_PALETTE[0x30]);
+        assert_eq!(ppu.frame.get_pixel(12, 0), SYSTEM_PALETTE[0x30]);
+        assert_eq!(ppu.frame.get_pixel(13, 0), SYSTEM_PALETTE[0x00]);
+    }
+
+    // Tile 1: solid color 3. Tile 2: only the top left pixel set (color 1).
+    fn sprite_test_chr() -> Vec<u8> {
+        let mut chr: Vec<u8> = vec![0; 0x2000];
+        for row in 0..16 {
+            chr[16 + row] = 0xFF;
+        }
+        chr[32] = 0b1000_0000;
+        chr
+    }
+    fn sprite_test_ppu() -> NesPPU {
+        sprite_test_ppu_with_chr(sprite_test_chr())
+    }
+    fn sprite_test_ppu_with_chr(chr: Vec<u8>) -> NesPPU {
+        let mut ppu: NesPPU = ppu_with_chr(chr, Mirroring::Horizontal);
+        ppu.palette_table[0x00] = 0x0F;
+        ppu.palette_table[0x03] = 0x30; // Background color 3
+        ppu.palette_table[0x11] = 0x16; // Sprite palette 0 color 1
+        ppu.palette_table[0x13] = 0x2A; // Sprite palette 0 color 3
+        ppu.palette_table[0x17] = 0x12; // Sprite palette 1 color 3
+        // Park every sprite below the screen
+        for i in 0..64 {
+            ppu.oam_data[i * 4] = 0xFF;
+        }
+        ppu
+    }
+    fn set_sprite(ppu: &mut NesPPU, index: usize, y: u8, tile: u8, attributes: u8, x: u8) {
+        ppu.oam_data[index * 4] = y;
+        ppu.oam_data[index * 4 + 1] = tile;
+        ppu.oam_data[index * 4 + 2] = attributes;
+        ppu.oam_data[index * 4 + 3] = x;
+    }
+    // Runs from the pre-render line until the end of the given scanline
+    fn render_until(ppu: &mut NesPPU, scanline: u16) {
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.cycle = 0;
+        ppu.tick(SCANLINE * (scanline as u32 + 2));
+    }
+
+    #[test]
+    fn test_sprite_rendering() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 9, 1, 0b01, 20); // Lines 10-17, palette 1
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        render_until(&mut ppu, 20);
+
+        assert_eq!(ppu.frame.get_pixel(20, 9), SYSTEM_PALETTE[0x0F]);
+        assert_eq!(ppu.frame.get_pixel(20, 10), SYSTEM_PALETTE[0x12]);
+        assert_eq!(ppu.frame.get_pixel(27, 17), SYSTEM_PALETTE[0x12]);
+        assert_eq!(ppu.frame.get_pixel(28, 17), SYSTEM_PALETTE[0x0F]);
+        assert_eq!(ppu.frame.get_pixel(20, 18), SYSTEM_PALETTE[0x0F]);
+    }
+
+    #[test]
+    fn test_sprite_flips() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 9, 2, 0, 0);
+        set_sprite(&mut ppu, 1, 9, 2, SPRITE_FLIP_HORIZONTAL, 16);
+        set_sprite(&mut ppu, 2, 9, 2, SPRITE_FLIP_VERTICAL, 32);
+        set_sprite(&mut ppu, 3, 9, 2, SPRITE_FLIP_HORIZONTAL | SPRITE_FLIP_VERTICAL, 48);
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        render_until(&mut ppu, 20);
+
+        let red: (u8, u8, u8) = SYSTEM_PALETTE[0x16];
+        assert_eq!(ppu.frame.get_pixel(0, 10), red);
+        assert_eq!(ppu.frame.get_pixel(23, 10), red);
+        assert_eq!(ppu.frame.get_pixel(32, 17), red);
+        assert_eq!(ppu.frame.get_pixel(55, 17), red);
+        assert_eq!(ppu.frame.get_pixel(16, 10), SYSTEM_PALETTE[0x0F]);
+        assert_eq!(ppu.frame.get_pixel(32, 10), SYSTEM_PALETTE[0x0F]);
+    }
+
+    #[test]
+    fn test_8x16_sprites() {
+        // Tile 0x03 in 8x16 mode: bank $1000, tiles 2 (top) and 3 (bottom)
+        let mut chr: Vec<u8> = sprite_test_chr();
+        chr[0x1000 + 2 * 16 + 7] = 0b0000_0001; // Last row of top tile
+        chr[0x1000 + 3 * 16] = 0b1000_0000; // First row of bottom tile
+        let mut ppu: NesPPU = sprite_test_ppu_with_chr(chr);
+        set_sprite(&mut ppu, 0, 9, 0x03, 0, 40);
+        set_sprite(&mut ppu, 1, 9, 0x03, SPRITE_FLIP_VERTICAL, 80);
+        ppu.write_to_ctrl(CTRL_SPRITE_SIZE);
+        ppu.write_to_mask(MASK_SHOW_SPRITES);
+        render_until(&mut ppu, 30);
+
+        let red: (u8, u8, u8) = SYSTEM_PALETTE[0x16];
+        assert_eq!(ppu.frame.get_pixel(47, 17), red);
+        assert_eq!(ppu.frame.get_pixel(40, 18), red);
+        // Vertical flip swaps the two halves as well
+        assert_eq!(ppu.frame.get_pixel(80, 17), red);
+        assert_eq!(ppu.frame.get_pixel(87, 18), red);
+        assert_eq!(ppu.frame.get_pixel(40, 26), SYSTEM_PALETTE[0x0F]);
+    }
+
+    #[test]
+    fn test_oam_write_after_evaluation_waits_a_line() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 9, 1, 0, 20);
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.cycle = 0;
+        ppu.tick(SCANLINE * 11 + 258);
+        assert_eq!((ppu.scanline, ppu.cycle), (10, 258));
+        // Moving the sprite below the line after evaluation doesn't reach line 11
+        ppu.oam_data[0] = 48;
+        ppu.tick(SCANLINE * 2);
+        assert_eq!(ppu.frame.get_pixel(20, 11), SYSTEM_PALETTE[0x2A]);
+        assert_eq!(ppu.frame.get_pixel(20, 12), SYSTEM_PALETTE[0x0F]);
+    }
+
+    #[test]
+    fn test_sprite_size_switch_after_evaluation_waits_a_line() {
+        let mut chr: Vec<u8> = sprite_test_chr();
+        chr[0x1000 + 2 * 16 + 4] = 0xFF; // Row 11 of tile 0x03 once flipped
+        let mut ppu: NesPPU = sprite_test_ppu_with_chr(chr);
+        set_sprite(&mut ppu, 0, 9, 0x03, SPRITE_FLIP_VERTICAL, 80);
+        ppu.write_to_ctrl(CTRL_SPRITE_SIZE);
+        ppu.write_to_mask(MASK_SHOW_SPRITES);
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.cycle = 0;
+        ppu.tick(SCANLINE * 21 + 258);
+        assert_eq!((ppu.scanline, ppu.cycle), (20, 258));
+        // Line 21 still draws the 8x16 sprite it was evaluated with
+        ppu.write_to_ctrl(0);
+        ppu.tick(SCANLINE * 2);
+        assert_eq!(ppu.frame.get_pixel(80, 21), SYSTEM_PALETTE[0x16]);
+        assert_eq!(ppu.frame.get_pixel(80, 22), SYSTEM_PALETTE[0x0F]);
+    }
+
+    #[test]
+    fn test_8x16_sprite_fetch_uses_the_latched_tile() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 9, 0x01, 0, 0); // Odd tile: pattern table $1000
+        ppu.write_to_ctrl(CTRL_SPRITE_SIZE);
+        ppu.write_to_mask(MASK_SHOW_SPRITES);
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.cycle = 0;
+        ppu.tick(SCANLINE * 11 + 258);
+        ppu.oam_data[1] = 0x00;
+        // Slot 0's pattern fetch on dot 261 still puts A12 high
+        ppu.tick(4);
+        assert!(ppu.a12);
+    }
+
+    #[test]
+    fn test_sprite_priority() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        ppu.vram[1] = 1; // Opaque background tile at x 8-15, lines 0-7
+        set_sprite(&mut ppu, 0, 0, 1, SPRITE_BEHIND_BACKGROUND, 4);
+        set_sprite(&mut ppu, 1, 0, 1, 0b01, 8);
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_BACKGROUND | MASK_SHOW_SPRITES_LEFT | MASK_SHOW_BACKGROUND_LEFT);
+        render_until(&mut ppu, 5);
+
+        // Behind background: only visible where the background is transparent
+        assert_eq!(ppu.frame.get_pixel(4, 1), SYSTEM_PALETTE[0x2A]);
+        assert_eq!(ppu.frame.get_pixel(8, 1), SYSTEM_PALETTE[0x30]);
+        // Sprite 0 still wins over sprite 1 where they overlap, hiding it behind the background
+        assert_eq!(ppu.frame.get_pixel(11, 1), SYSTEM_PALETTE[0x30]);
+        assert_eq!(ppu.frame.get_pixel(12, 1), SYSTEM_PALETTE[0x12]);
+    }
+
+    #[test]
+    fn test_sprites_left_column_clipping() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 0, 1, 0, 4);
+        ppu.write_to_mask(MASK_SHOW_SPRITES);
+        render_until(&mut ppu, 5);
+
+        assert_eq!(ppu.frame.get_pixel(7, 1), SYSTEM_PALETTE[0x0F]);
+        assert_eq!(ppu.frame.get_pixel(8, 1), SYSTEM_PALETTE[0x2A]);
+    }
+
+    #[test]
+    fn test_eight_sprites_per_line() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        for i in 0..9 {
+            set_sprite(&mut ppu, i, 9, 1, 0, (i * 10) as u8);
+        }
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_SPRITES_LEFT);
+        render_until(&mut ppu, 12);
+
+        assert_eq!(ppu.frame.get_pixel(70, 10), SYSTEM_PALETTE[0x2A]);
+        assert_eq!(ppu.frame.get_pixel(80, 10), SYSTEM_PALETTE[0x0F]);
+        assert!(ppu.status & STATUS_SPRITE_OVERFLOW != 0);
+    }
+
+    #[test]
+    fn test_sprite_overflow_hardware_bug() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        for i in 0..8 {
+            set_sprite(&mut ppu, i, 9, 1, 0, 0);
+        }
+        // The 9th sprite is on the line but the buggy scan checks byte 0 of sprite 8, then
+        // byte 1 (tile) of sprite 9, byte 2 of sprite 10 and so on.
+        set_sprite(&mut ppu, 8, 0xF0, 1, 0, 0);
+        set_sprite(&mut ppu, 9, 9, 0xF0, 0, 0);
+        ppu.write_to_mask(MASK_SHOW_SPRITES);
+        render_until(&mut ppu, 12);
+        assert!(ppu.status & STATUS_SPRITE_OVERFLOW == 0);
+
+        // Putting a Y-looking value in sprite 9's tile byte makes it report overflow
+        set_sprite(&mut ppu, 9, 0xF0, 9, 0, 0);
+        render_until(&mut ppu, 12);
+        assert!(ppu.status & STATUS_SPRITE_OVERFLOW != 0);
+    }
+
+    #[test]
+    fn test_sprite_zero_hit() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        ppu.vram[2] = 1; // Opaque background at x 16-23, lines 0-7
+        set_sprite(&mut ppu, 0, 3, 2, 0, 18); // One opaque pixel at (18, 4)
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_BACKGROUND);
+
+        ppu.scanline = PRE_RENDER_SCANLINE;
+        ppu.cycle = 0;
+        ppu.tick(SCANLINE * 5);
+        assert_eq!(ppu.scanline, 4);
+        assert!(ppu.status & STATUS_SPRITE_ZERO_HIT == 0);
+        // Pixel 18 is output on dot 19, the flag shows up one dot later
+        ppu.tick(20);
+        assert!(ppu.status & STATUS_SPRITE_ZERO_HIT == 0);
+        ppu.tick(1);
+        assert!(ppu.status & STATUS_SPRITE_ZERO_HIT != 0);
+
+        // Cleared again on the pre-render line
+        ppu.tick(SCANLINE * (PRE_RENDER_SCANLINE as u32 - 4));
+        assert!(ppu.status & STATUS_SPRITE_ZERO_HIT == 0);
+    }
+
+    #[test]
+    fn test_no_sprite_zero_hit_on_transparent_background() {
+        let mut ppu: NesPPU = sprite_test_ppu();
+        set_sprite(&mut ppu, 0, 3, 1, 0, 18);
+        ppu.write_to_mask(MASK_SHOW_SPRITES | MASK_SHOW_BACKGROUND);
+        render_until(&mut ppu, 20);
+        assert!(ppu.status & STATUS_SPRITE_ZERO_HIT == 0);
+    }
+
+    #[test]
+    fn test_oam_dma() {
+        let mut ppu: NesPPU = NesPPU::new_empty_rom();
+        let mut page: [u8; 256] = [0; 256];
+        for (i, byte) in page.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        ppu.write_to_oam_addr(0x10);
+        ppu.write_oam_dma(&page);
+        assert_eq!(ppu.oam_data[0x10], 0x00);
+        assert_eq!(ppu.oam_data[0x0F], 0xFF);
+        assert_eq!(ppu.oam_addr, 0x10);
+    }
+}
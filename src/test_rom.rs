@@ -0,0 +1,190 @@
+use std::fmt;
+
+use crate::bus::Bus;
+use crate::bus::NesBus;
+use crate::cartridge::Rom;
+use crate::cpu::CpuError;
+use crate::cpu::CPU;
+use crate::mapper::MapperError;
+
+// Headless runner for blargg's test ROMs (instr_test-v5, cpu_timing_test, ppu_vbl_nmi, ...).
+// They report through cartridge RAM:
+// $6000       status: $80 while running, $81 when the reset button should be pressed,
+//             anything else is the final result code (0 means passed)
+// $6001-$6003 $DE $B0 $61 once the rest of the values can be trusted
+// $6004-      zero terminated text, the same message the ROM prints on screen
+const STATUS: u16 = 0x6000;
+const SIGNATURE: u16 = 0x6001;
+const SIGNATURE_BYTES: [u8; 3] = [0xDE, 0xB0, 0x61];
+const MESSAGE: u16 = 0x6004;
+const MESSAGE_END: u16 = 0x7FFF;
+const STATUS_RUNNING: u8 = 0x80;
+const STATUS_NEEDS_RESET: u8 = 0x81;
+// The ROMs want the reset to come at least 100ms after they ask for it
+const RESET_DELAY_CYCLES: u64 = 180_000;
+// How often the status byte is checked
+const POLL_CYCLES: u64 = 10_000;
+// A minute of NES time, more than any of the suites need
+pub const DEFAULT_CYCLE_LIMIT: u64 = 60 * 1_789_773;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestRomResult {
+    pub status: u8,
+    pub message: String,
+}
+
+impl TestRomResult {
+    pub fn passed(&self) -> bool {
+        self.status == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestRomError {
+    // The cartridge needs a mapper that isn't implemented
+    Mapper(MapperError),
+    // The CPU jammed or hit an opcode it can't run
+    Cpu(CpuError),
+    // No result within the cycle limit. Carries whatever text the ROM had written so far.
+    Timeout { cycles: u64, message: String },
+}
+
+impl fmt::Display for TestRomError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestRomError::Mapper(e) => write!(f, "{}", e),
+            TestRomError::Cpu(e) => write!(f, "{}", e),
+            TestRomError::Timeout { cycles, message } => {
+                write!(f, "no result after {} cycles", cycles)?;
+                if !message.is_empty() {
+                    write!(f, ": {}", message.trim_end())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for TestRomError {}
+
+// Runs the ROM until it reports a result, pressing reset whenever it asks for it
+pub fn run_test_rom(rom: Rom, cycle_limit: u64) -> Result<TestRomResult, TestRomError> {
+    let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(rom).map_err(TestRomError::Mapper)?);
+    cpu.halt_on_brk = false;
+    cpu.reset();
+
+    let mut reset_due: Option<u64> = None;
+    while cpu.cycles() < cycle_limit {
+        cpu.run_for_cycles(POLL_CYCLES).map_err(TestRomError::Cpu)?;
+        if !has_signature(&cpu.bus) {
+            continue;
+        }
+        match cpu.bus.peek(STATUS) {
+            STATUS_RUNNING => {}
+            STATUS_NEEDS_RESET => {
+                let due: u64 = *reset_due.get_or_insert(cpu.cycles() + RESET_DELAY_CYCLES);
+                if cpu.cycles() >= due {
+                    cpu.reset();
+                    reset_due = None;
+                }
+            }
+            status => {
+                return Ok(TestRomResult {
+                    status,
+                    message: read_message(&cpu.bus),
+                })
+            }
+        }
+    }
+    Err(TestRomError::Timeout {
+        cycles: cpu.cycles(),
+        message: read_message(&cpu.bus),
+    })
+}
+
+fn has_signature(bus: &NesBus) -> bool {
+    (0..3).all(|i| bus.peek(SIGNATURE + i) == SIGNATURE_BYTES[i as usize])
+}
+
+fn read_message(bus: &NesBus) -> String {
+    let bytes: Vec<u8> = (MESSAGE..=MESSAGE_END)
+        .map(|addr| bus.peek(addr))
+        .take_while(|&byte| byte != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cartridge::test::test_rom;
+
+    // Program at $8000 with the reset vector pointing at it
+    fn rom_with_program(program: &[u8]) -> Rom {
+        let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+        prg_rom[..program.len()].copy_from_slice(program);
+        prg_rom[0x3FFC] = 0x00;
+        prg_rom[0x3FFD] = 0x80;
+        test_rom(prg_rom)
+    }
+    // LDA #value STA addr
+    fn store(addr: u16, value: u8) -> Vec<u8> {
+        vec![0xA9, value, 0x8D, addr as u8, (addr >> 8) as u8]
+    }
+    fn report(status: u8, message: &str) -> Vec<u8> {
+        let mut program: Vec<u8> = Vec::new();
+        for (i, byte) in message.bytes().chain([0]).enumerate() {
+            program.extend(store(MESSAGE + i as u16, byte));
+        }
+        for (i, byte) in SIGNATURE_BYTES.iter().enumerate() {
+            program.extend(store(SIGNATURE + i as u16, *byte));
+        }
+        program.extend(store(STATUS, status));
+        program
+    }
+
+    #[test]
+    fn test_passing_rom() {
+        let mut program: Vec<u8> = report(STATUS_RUNNING, "running");
+        // Let a poll or two see it running, then pass
+        program.extend([0xA0, 0x20, 0xA2, 0x00, 0xCA, 0xD0, 0xFD, 0x88, 0xD0, 0xF8]);
+        program.extend(report(0, "\nPassed\n"));
+        let end: u16 = 0x8000 + program.len() as u16;
+        program.extend([0x4C, end as u8, (end >> 8) as u8]); // JMP *
+        let result: TestRomResult = run_test_rom(rom_with_program(&program), DEFAULT_CYCLE_LIMIT).unwrap();
+        assert!(result.passed());
+        assert_eq!(result.message, "\nPassed\n");
+    }
+    #[test]
+    fn test_rom_that_asks_for_a_reset() {
+        // INC $7000  LDA $7000  CMP #2  BEQ second_boot
+        let mut program: Vec<u8> = vec![0xEE, 0x00, 0x70, 0xAD, 0x00, 0x70, 0xC9, 0x02, 0xF0, 0x00];
+        program.extend(report(STATUS_NEEDS_RESET, ""));
+        program.extend([0x4C, 0x00, 0x00]); // JMP *
+        let spin: usize = program.len() - 3;
+        let second_boot: usize = program.len();
+        program[9] = (second_boot - 10) as u8;
+        program.extend(report(3, "Failed #3\n"));
+        program.extend([0x4C, 0x00, 0x00]);
+        let last: usize = program.len() - 3;
+        for at in [spin, last] {
+            program[at + 1] = (0x8000 + at) as u8;
+            program[at + 2] = ((0x8000 + at) >> 8) as u8;
+        }
+        let result: TestRomResult = run_test_rom(rom_with_program(&program), DEFAULT_CYCLE_LIMIT).unwrap();
+        assert!(!result.passed());
+        assert_eq!(result.status, 3);
+        assert_eq!(result.message, "Failed #3\n");
+    }
+    #[test]
+    fn test_rom_that_never_reports() {
+        let rom: Rom = rom_with_program(&[0x4C, 0x00, 0x80]); // JMP $8000
+        match run_test_rom(rom, 100_000) {
+            Err(TestRomError::Timeout { cycles, message }) => {
+                assert!(cycles >= 100_000);
+                assert_eq!(message, "");
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+    }
+}
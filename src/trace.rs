@@ -0,0 +1,272 @@
+use std::io;
+use std::io::Write;
+
+use crate::bus::Bus;
+use crate::cpu::AddressingMode;
+use crate::cpu::Variant;
+use crate::cpu::CPU;
+use crate::opcodes::OpCode;
+
+// Execution log in the format of nestest.log, one line per instruction, written before the
+// instruction runs:
+// C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+// Memory values are read with Bus::peek, so tracing never changes what the program sees.
+pub struct Tracer<W: Write> {
+    out: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(out: W) -> Self {
+        Tracer { out }
+    }
+    // Meant to be called from run_with_callback:
+    // cpu.run_with_callback(|cpu| tracer.trace(cpu).unwrap());
+    pub fn trace<B: Bus>(&mut self, cpu: &CPU<B>) -> io::Result<()> {
+        writeln!(self.out, "{}", trace_line(cpu))
+    }
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+// Formats the instruction at the CPU's PC along with the current register state
+pub fn trace_line<B: Bus>(cpu: &CPU<B>) -> String {
+    let pc: u16 = cpu.program_counter;
+    let opcode: u8 = cpu.bus.peek(pc);
+    let operation: &OpCode = &cpu.variant.opcodes()[opcode as usize];
+
+    let bytes: Vec<u8> = (0..operation.num_bytes as u16)
+        .map(|i| cpu.bus.peek(pc.wrapping_add(i)))
+        .collect();
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    // nestest marks undocumented opcodes with a '*' in the column before the mnemonic.
+    // 65C02 mnemonics like BBR0 are a character longer and push the operand along.
+    let mnemonic: String = format!("{}{:3}", if operation.unofficial { "*" } else { " " }, operation.name);
+
+    let asm: String = format!(
+        "{:04X}  {:8} {} {}",
+        pc,
+        hex.join(" "),
+        mnemonic,
+        format_operand(cpu, operation, &bytes)
+    );
+    let mut line: String = format!(
+        "{:47} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X}",
+        asm.trim_end(),
+        cpu.register_a,
+        cpu.register_x,
+        cpu.register_y,
+        cpu.status,
+        cpu.stack_ptr
+    );
+    if let Some((scanline, dot)) = cpu.bus.ppu_position() {
+        line.push_str(&format!(" PPU:{:3},{:3}", scanline, dot));
+    }
+    line.push_str(&format!(" CYC:{}", cpu.cycles()));
+    line
+}
+
+// The operand column: the operand as written, then the effective address and the value there
+fn format_operand<B: Bus>(cpu: &CPU<B>, operation: &OpCode, bytes: &[u8]) -> String {
+    let pc: u16 = cpu.program_counter;
+    let x: u8 = cpu.register_x;
+    let y: u8 = cpu.register_y;
+    let peek = |addr: u16| -> u8 { cpu.bus.peek(addr) };
+    let peek_u16_zero_page = |ptr: u8| -> u16 {
+        (peek(ptr.wrapping_add(1) as u16) as u16) << 8 | peek(ptr as u16) as u16
+    };
+    let byte: u8 = bytes.get(1).copied().unwrap_or(0);
+    let word: u16 = (bytes.get(2).copied().unwrap_or(0) as u16) << 8 | byte as u16;
+
+    match operation.mode {
+        AddressingMode::Immediate => format!("#${:02X}", byte),
+        AddressingMode::ZeroPage => format!("${:02X} = {:02X}", byte, peek(byte as u16)),
+        AddressingMode::ZeroPage_X => {
+            let addr: u8 = byte.wrapping_add(x);
+            format!("${:02X},X @ {:02X} = {:02X}", byte, addr, peek(addr as u16))
+        }
+        AddressingMode::ZeroPage_Y => {
+            let addr: u8 = byte.wrapping_add(y);
+            format!("${:02X},Y @ {:02X} = {:02X}", byte, addr, peek(addr as u16))
+        }
+        // JMP doesn't touch the memory it points at
+        AddressingMode::Absolute if operation.instruction == 0x4c => format!("${:04X}", word),
+        AddressingMode::Absolute => format!("${:04X} = {:02X}", word, peek(word)),
+        AddressingMode::Absolute_X => {
+            let addr: u16 = word.wrapping_add(x as u16);
+            format!("${:04X},X @ {:04X} = {:02X}", word, addr, peek(addr))
+        }
+        AddressingMode::Absolute_Y => {
+            let addr: u16 = word.wrapping_add(y as u16);
+            format!("${:04X},Y @ {:04X} = {:02X}", word, addr, peek(addr))
+        }
+        AddressingMode::Indirect_X => {
+            let ptr: u8 = byte.wrapping_add(x);
+            let addr: u16 = peek_u16_zero_page(ptr);
+            format!("(${:02X},X) @ {:02X} = {:04X} = {:02X}", byte, ptr, addr, peek(addr))
+        }
+        AddressingMode::Indirect_Y => {
+            let base: u16 = peek_u16_zero_page(byte);
+            let addr: u16 = base.wrapping_add(y as u16);
+            format!("(${:02X}),Y = {:04X} @ {:04X} = {:02X}", byte, base, addr, peek(addr))
+        }
+        AddressingMode::ZeroPage_Indirect => {
+            let addr: u16 = peek_u16_zero_page(byte);
+            format!("(${:02X}) = {:04X} = {:02X}", byte, addr, peek(addr))
+        }
+        AddressingMode::NoneAddressing => match (operation.name, operation.num_bytes) {
+            // Shifts, rotates and the 65C02's INC/DEC on the accumulator
+            ("ASL" | "LSR" | "ROL" | "ROR" | "INC" | "DEC", 1) => "A".to_string(),
+            (_, 1) => String::new(),
+            // Branches: the operand is relative to the next instruction
+            (_, 2) => {
+                let target: u16 = pc.wrapping_add(2).wrapping_add(byte as i8 as u16);
+                format!("${:04X}", target)
+            }
+            // 65C02 BBRn/BBSn: zero page address, then a branch relative to the next instruction
+            (name, _) if name.starts_with("BB") => {
+                let offset: u8 = bytes.get(2).copied().unwrap_or(0);
+                let target: u16 = pc.wrapping_add(3).wrapping_add(offset as i8 as u16);
+                format!("${:02X},${:04X}", byte, target)
+            }
+            // JMP ($xxxx), including the NMOS page wrap bug when the pointer ends in $FF
+            _ if operation.instruction == 0x6c => {
+                let hi_addr: u16 = if cpu.variant == Variant::Wdc65C02 {
+                    word.wrapping_add(1)
+                } else {
+                    (word & 0xFF00) | (word.wrapping_add(1) & 0x00FF)
+                };
+                let target: u16 = (peek(hi_addr) as u16) << 8 | peek(word) as u16;
+                format!("(${:04X}) = {:04X}", word, target)
+            }
+            // 65C02 JMP ($xxxx,X)
+            _ if operation.instruction == 0x7c => {
+                let ptr: u16 = word.wrapping_add(x as u16);
+                let target: u16 = (peek(ptr.wrapping_add(1)) as u16) << 8 | peek(ptr) as u16;
+                format!("(${:04X},X) = {:04X}", word, target)
+            }
+            // JSR, and the 65C02's 3-byte NOP
+            _ => format!("${:04X}", word),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::bus::NesBus;
+    use crate::cartridge::test::test_rom;
+
+    fn trace_program(program: Vec<u8>, setup: fn(&mut CPU)) -> Vec<String> {
+        let mut cpu: CPU = CPU::new();
+        cpu.load_at(program, 0x0064).unwrap();
+        cpu.program_counter = 0x0064;
+        cpu.status = 0x24;
+        cpu.stack_ptr = 0xfd;
+        setup(&mut cpu);
+        let mut tracer: Tracer<Vec<u8>> = Tracer::new(Vec::new());
+        cpu.run_with_callback(|cpu| tracer.trace(cpu).unwrap()).unwrap();
+        String::from_utf8(tracer.into_inner())
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect()
+    }
+    // Just the address, bytes and disassembly columns
+    fn asm(line: &str) -> &str {
+        line[..47].trim_end()
+    }
+
+    #[test]
+    fn test_format_trace() {
+        let lines: Vec<String> = trace_program(vec![0xa2, 0x02, 0xca, 0x88, 0x00], |cpu| {
+            cpu.register_a = 1;
+            cpu.register_x = 3;
+            cpu.register_y = 3;
+            cpu.status = 0x20;
+        });
+        assert_eq!(
+            "0064  A2 02     LDX #$02                        A:01 X:03 Y:03 P:20 SP:FD CYC:0",
+            lines[0]
+        );
+        assert_eq!(
+            "0066  CA        DEX                             A:01 X:02 Y:03 P:20 SP:FD CYC:2",
+            lines[1]
+        );
+        assert_eq!(
+            "0067  88        DEY                             A:01 X:01 Y:03 P:20 SP:FD CYC:4",
+            lines[2]
+        );
+        assert_eq!(
+            "0068  00        BRK                             A:01 X:01 Y:02 P:20 SP:FD CYC:6",
+            lines[3]
+        );
+    }
+    #[test]
+    fn test_format_memory_access() {
+        let lines: Vec<String> = trace_program(vec![0x11, 0x33, 0xb5, 0x10, 0xbd, 0xf0, 0x01, 0x00], |cpu| {
+            cpu.register_x = 0x12;
+            cpu.register_y = 0x00;
+            cpu.mem_write_u16(0x33, 0x0400);
+            cpu.mem_write(0x0400, 0xaa);
+            cpu.mem_write(0x22, 0x55);
+        });
+        assert_eq!("0064  11 33     ORA ($33),Y = 0400 @ 0400 = AA", asm(&lines[0]));
+        assert_eq!("0066  B5 10     LDA $10,X @ 22 = 55", asm(&lines[1]));
+        assert_eq!("0068  BD F0 01  LDA $01F0,X @ 0202 = 00", asm(&lines[2]));
+    }
+    #[test]
+    fn test_format_jumps_and_branches() {
+        let lines: Vec<String> = trace_program(vec![0xd0, 0x02, 0x00, 0x00, 0x6c, 0xff, 0x02], |cpu| {
+            cpu.mem_write(0x02ff, 0x00);
+            cpu.mem_write(0x0200, 0x03); // Indirect JMP wraps within the page
+            cpu.mem_write(0x0300, 0x00);
+        });
+        assert_eq!("0064  D0 02     BNE $0068", asm(&lines[0]));
+        assert_eq!("0068  6C FF 02  JMP ($02FF) = 0300", asm(&lines[1]));
+    }
+    #[test]
+    fn test_format_unofficial_opcode() {
+        let lines: Vec<String> = trace_program(vec![0x04, 0x10, 0xa7, 0x10, 0x00], |_| {});
+        assert_eq!("0064  04 10    *NOP $10 = 00", asm(&lines[0]));
+        assert_eq!("0066  A7 10    *LAX $10 = 00", asm(&lines[1]));
+    }
+    #[test]
+    fn test_format_65c02_operands() {
+        let mut cpu: CPU = CPU::new();
+        cpu.variant = Variant::Wdc65C02;
+        // BBR0 $10,+2  LDA ($12)  INC A  JMP ($02FF)
+        cpu.load(vec![0x0f, 0x10, 0x02, 0xb2, 0x12, 0x1a, 0x6c, 0xff, 0x02]).unwrap();
+        cpu.reset();
+        cpu.mem_write_u16(0x12, 0x0400);
+        cpu.mem_write(0x0400, 0x77);
+        cpu.mem_write_u16(0x02ff, 0x1234);
+        let mut lines: Vec<String> = Vec::new();
+        for _ in 0..4 {
+            lines.push(asm(&trace_line(&cpu)).to_string());
+            cpu.program_counter += cpu.variant.opcodes()[cpu.bus.peek(cpu.program_counter) as usize].num_bytes as u16;
+        }
+        assert_eq!("8000  0F 10 02  BBR0 $10,$8005", lines[0]);
+        assert_eq!("8003  B2 12     LDA ($12) = 0400 = 77", lines[1]);
+        assert_eq!("8005  1A        INC A", lines[2]);
+        assert_eq!("8006  6C FF 02  JMP ($02FF) = 1234", lines[3]);
+    }
+    #[test]
+    fn test_trace_does_not_touch_the_bus() {
+        let mut prg_rom: Vec<u8> = vec![0; 0x4000];
+        prg_rom[..4].copy_from_slice(&[0xad, 0x02, 0x20, 0x00]); // LDA $2002 BRK
+        prg_rom[0x3FFD] = 0x80; // Reset vector -> $8000
+        let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(test_rom(prg_rom)).unwrap());
+        cpu.reset();
+        cpu.bus.ppu.status = 0b1000_0000; // In vblank
+        cpu.bus.mem_write(0x0000, 0x5A); // Leaves $5A on the data bus
+        let before: u64 = cpu.cycles();
+        let line: String = trace_line(&cpu);
+        // Reading $2002 would clear vblank and put the status on the bus
+        assert!(line.starts_with("8000  AD 02 20  LDA $2002 = 5A"));
+        assert_eq!(cpu.bus.ppu.status, 0b1000_0000);
+        assert_eq!(cpu.bus.peek(0x5000), 0x5A);
+        assert_eq!(cpu.program_counter, 0x8000);
+        assert_eq!(cpu.cycles(), before);
+    }
+}
@@ -0,0 +1,48 @@
+// Runs every blargg test ROM found under tests/fixtures/blargg/ through the $6000 status
+// protocol and fails with the ROM's own message for each one that doesn't pass.
+// The ROMs aren't redistributed with the crate, so the test is ignored by default. Copy the .nes
+// files from the suites you want (instr_test-v5/rom_singles, cpu_timing_test6, ...) into that
+// directory and run it with `cargo test -- --ignored`.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nes_emulator::cartridge::Rom;
+use nes_emulator::test_rom::{run_test_rom, DEFAULT_CYCLE_LIMIT};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/blargg");
+
+fn find_roms(dir: &Path, roms: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap_or_else(|e| panic!("{}: {}", dir.display(), e)) {
+        let path: PathBuf = entry.unwrap().path();
+        if path.is_dir() {
+            find_roms(&path, roms);
+        } else if path.extension().is_some_and(|ext| ext == "nes") {
+            roms.push(path);
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs test ROMs in tests/fixtures/blargg"]
+fn blargg_test_roms() {
+    let mut roms: Vec<PathBuf> = Vec::new();
+    find_roms(Path::new(FIXTURES), &mut roms);
+    roms.sort();
+    assert!(!roms.is_empty(), "no .nes files under {}", FIXTURES);
+
+    let mut failures: Vec<String> = Vec::new();
+    for path in &roms {
+        let rom: Rom = Rom::new(&fs::read(path).unwrap()).unwrap();
+        match run_test_rom(rom, DEFAULT_CYCLE_LIMIT) {
+            Ok(result) if result.passed() => {}
+            Ok(result) => failures.push(format!(
+                "{}: failed with code {}\n{}",
+                path.display(),
+                result.status,
+                result.message.trim_end()
+            )),
+            Err(e) => failures.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    assert!(failures.is_empty(), "{} of {} ROMs failed\n\n{}", failures.len(), roms.len(), failures.join("\n\n"));
+}
@@ -0,0 +1,52 @@
+// Klaus Dormann's 6502 functional test (https://github.com/Klaus2m5/6502_65C02_functional_tests).
+// The test is a 64KB memory image. It starts at $0400 and, when something fails, parks the CPU
+// in a `JMP *` or a branch to itself. Reaching the trap at SUCCESS_TRAP means every test passed.
+//
+// The image isn't redistributed with the crate, so the test is ignored by default. Put the stock
+// build of bin_files/6502_functional_test.bin in tests/fixtures/ and run it with
+// `cargo test -- --ignored`. SUCCESS_TRAP is the trap in that build's listing
+// (6502_functional_test.lst); other builds move it.
+use nes_emulator::cpu::{Variant, CPU};
+
+const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/6502_functional_test.bin");
+const START: u16 = 0x0400;
+const SUCCESS_TRAP: u16 = 0x3469;
+// The suite keeps the number of the test it's running here
+const TEST_CASE: u16 = 0x0200;
+// Comfortably more than the ~96 million cycles a full pass takes
+const CYCLE_LIMIT: u64 = 200_000_000;
+
+#[test]
+#[ignore = "needs tests/fixtures/6502_functional_test.bin"]
+fn functional_test() {
+    let image: Vec<u8> = std::fs::read(FIXTURE).unwrap_or_else(|e| panic!("{}: {}", FIXTURE, e));
+    assert_eq!(image.len(), 0x10000, "expected a full 64KB memory image");
+
+    let mut cpu: CPU = CPU::new();
+    cpu.variant = Variant::Nmos6502; // The stock build tests decimal mode too
+    cpu.halt_on_brk = false; // BRK is one of the things under test
+    cpu.bus.memory.copy_from_slice(&image);
+    cpu.program_counter = START;
+    cpu.stack_ptr = 0xfd;
+    cpu.status = 0x24;
+
+    // Stepping one instruction at a time, a trap is the PC staying put
+    let mut last_pc: Option<u16> = None;
+    let result = cpu.run_until(|cpu| {
+        let trapped: bool = last_pc == Some(cpu.program_counter);
+        last_pc = Some(cpu.program_counter);
+        trapped || cpu.cycles() > CYCLE_LIMIT
+    });
+    if let Err(e) = result {
+        panic!("{} in test case {:#04x}", e, cpu.bus.memory[TEST_CASE as usize]);
+    }
+
+    assert!(cpu.cycles() <= CYCLE_LIMIT, "no trap after {} cycles", CYCLE_LIMIT);
+    assert_eq!(
+        cpu.program_counter,
+        SUCCESS_TRAP,
+        "trapped at {:#06x} in test case {:#04x}",
+        cpu.program_counter,
+        cpu.bus.memory[TEST_CASE as usize]
+    );
+}
@@ -0,0 +1,62 @@
+// Runs kevtris' nestest.nes in automation mode (starting at $C000, no PPU needed) and compares
+// our trace with the reference nestest.log from Nintendulator, line by line.
+//
+// Neither file is redistributed with the crate, so the test is ignored by default. Put
+// nestest.nes and nestest.log in tests/fixtures/ and run it with `cargo test -- --ignored`.
+use nes_emulator::bus::Bus;
+use nes_emulator::bus::NesBus;
+use nes_emulator::cartridge::Rom;
+use nes_emulator::cpu::CPU;
+use nes_emulator::trace::trace_line;
+
+const ROM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nestest.nes");
+const LOG: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/nestest.log");
+
+#[test]
+#[ignore = "needs tests/fixtures/nestest.nes and nestest.log"]
+fn nestest_matches_golden_log() {
+    let image: Vec<u8> = std::fs::read(ROM).unwrap_or_else(|e| panic!("{}: {}", ROM, e));
+    let rom: Rom = Rom::new(&image).unwrap();
+    let log: String = std::fs::read_to_string(LOG).unwrap_or_else(|e| panic!("{}: {}", LOG, e));
+    let expected: Vec<&str> = log.lines().map(|line| line.trim_end()).collect();
+
+    let mut cpu: CPU<NesBus> = CPU::with_bus(NesBus::new(rom).unwrap());
+    cpu.halt_on_brk = false;
+    cpu.reset();
+    // Automation mode skips the reset vector, and the log starts from the power up state
+    cpu.program_counter = 0xC000;
+    cpu.status = 0x24;
+    cpu.stack_ptr = 0xFD;
+
+    let mut line: usize = 0;
+    let mut divergence: Option<(String, String)> = None;
+    cpu.run_until(|cpu| {
+        if line == expected.len() {
+            return true;
+        }
+        let actual: String = trace_line(cpu);
+        if actual != expected[line] {
+            divergence = Some((expected[line].to_string(), actual));
+            return true;
+        }
+        line += 1;
+        false
+    })
+    .unwrap_or_else(|e| panic!("{} after line {} of nestest.log", e, line));
+
+    if let Some((expected_line, actual_line)) = divergence {
+        panic!(
+            "diverged from nestest.log at line {}\n\
+             previous: {}\n\
+             expected: {}\n\
+             actual:   {}",
+            line + 1,
+            if line > 0 { expected[line - 1] } else { "" },
+            expected_line,
+            actual_line
+        );
+    }
+    // The ROM also leaves error codes for the official and unofficial opcode tests here
+    assert_eq!(cpu.bus.peek(0x0002), 0x00, "official opcode test failed");
+    assert_eq!(cpu.bus.peek(0x0003), 0x00, "unofficial opcode test failed");
+}